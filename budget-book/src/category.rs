@@ -0,0 +1,237 @@
+use crate::{config::Config, MyError, Record};
+use chrono::NaiveDate;
+use regex::Regex;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use unicode_width::UnicodeWidthStr;
+
+/// 分類の指定がない取引の表示名
+pub const UNCATEGORIZED: &str = "未分類";
+
+/// 用途から分類を決めるルール
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    /// 用途に対する正規表現
+    pub pattern: String,
+    pub category: String,
+}
+
+impl Config {
+    /// ルールを末尾に追加する
+    pub fn add_rule(&mut self, pattern: &str, category: &str) -> Result<(), MyError> {
+        compile(pattern)?;
+        self.rules.push(Rule {
+            pattern: pattern.to_string(),
+            category: category.to_string(),
+        });
+        Ok(())
+    }
+
+    /// ルールの正規表現をまとめてコンパイルする
+    // NOTE: 取引ごとにコンパイルし直さないよう、コマンドの処理の初めに1回だけ呼ぶ
+    pub fn compile_rules(&self) -> Result<Rules<'_>, MyError> {
+        let rules = self
+            .rules
+            .iter()
+            .map(|rule| Ok((compile(&rule.pattern)?, rule.category.as_str())))
+            .collect::<Result<_, MyError>>()?;
+        Ok(Rules(rules))
+    }
+}
+
+/// コンパイル済みの分類のルール
+pub struct Rules<'a>(Vec<(Regex, &'a str)>);
+impl Rules<'_> {
+    /// 用途に一致するルールの分類を返す
+    pub fn categorize(&self, usage: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(usage))
+            .map(|(_, category)| *category)
+    }
+
+    /// 分類の指定がない取引に、ルールで決めた分類を設定する
+    pub fn apply(&self, record: &mut Record) {
+        if record.分類.is_empty() {
+            if let Some(category) = self.categorize(&record.用途) {
+                record.分類 = category.to_string();
+            }
+        }
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex, MyError> {
+    Regex::new(pattern).map_err(|error| MyError::InvalidPattern {
+        pattern: pattern.to_string(),
+        reason: error.to_string(),
+    })
+}
+
+/// 分類ごとの予算の消化状況
+#[derive(Debug, PartialEq, Eq)]
+pub struct BudgetStatus<'a> {
+    pub category: &'a str,
+    /// 基準通貨での予算
+    pub limit: Decimal,
+    /// 出金の合計（正の値）
+    pub spent: Decimal,
+}
+impl BudgetStatus<'_> {
+    pub fn is_over(&self) -> bool {
+        self.spent > self.limit
+    }
+}
+
+/// month を含む月について、予算を設定した分類の出金を集計する
+pub fn budget_status<'a>(
+    config: &'a Config,
+    records: &[Record],
+    month: NaiveDate,
+) -> Vec<BudgetStatus<'a>> {
+    let month = month.format("%Y-%m").to_string();
+    config
+        .budgets
+        .iter()
+        .map(|(category, limit)| {
+            // NOTE: 入金（返金など）は差し引かず、出金だけを予算の消化とみなす
+            let spent = records
+                .iter()
+                .filter(|record| record.日付.format("%Y-%m").to_string() == month)
+                .filter(|record| &record.分類 == category && record.金額 < Decimal::ZERO)
+                .map(|record| -record.金額)
+                .sum();
+            BudgetStatus {
+                category,
+                limit: *limit,
+                spent,
+            }
+        })
+        .collect()
+}
+
+/// 予算の消化状況を表形式で出力し、超過した分類について基準通貨の金額で警告する
+pub fn render_budget_status(statuses: &[BudgetStatus], base_currency: &str) -> String {
+    let name_width = statuses
+        .iter()
+        .map(|status| status.category.width())
+        .chain(["分類".width()])
+        .max()
+        .unwrap_or_default();
+    let pad = |name: &str| format!("{}{}", name, " ".repeat(name_width - name.width()));
+
+    let mut output = String::new();
+    writeln!(
+        output,
+        "{}  {:>10}  {:>10}  {:>10}",
+        pad("分類"),
+        "予算",
+        "支出",
+        "残り"
+    )
+    .unwrap();
+    for status in statuses {
+        writeln!(
+            output,
+            "{}  {:>12}  {:>12}  {:>12}",
+            pad(status.category),
+            status.limit,
+            status.spent,
+            status.limit - status.spent
+        )
+        .unwrap();
+    }
+    for status in statuses.iter().filter(|status| status.is_over()) {
+        writeln!(
+            output,
+            "警告：「{}」が予算を {} {} 超過しています",
+            status.category,
+            status.spent - status.limit,
+            base_currency
+        )
+        .unwrap();
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_config() -> Config {
+        let mut config = Config::default();
+        config.add_rule("スーパー|コンビニ", "食費").unwrap();
+        config.add_rule("^電気|^ガス", "光熱費").unwrap();
+        config
+            .budgets
+            .insert("食費".to_string(), Decimal::from(30000));
+        config
+            .budgets
+            .insert("光熱費".to_string(), "10000.5".parse().unwrap());
+        config
+    }
+
+    #[test]
+    fn test_categorize() {
+        let config = sample_config();
+        let rules = config.compile_rules().unwrap();
+
+        assert_eq!(Some("食費"), rules.categorize("コンビニ 弁当"));
+        assert_eq!(Some("光熱費"), rules.categorize("電気代 1月分"));
+        assert_eq!(None, rules.categorize("水道代"));
+
+        let mut categorized = Record::test("2024-01-01", "スーパー", -1000);
+        rules.apply(&mut categorized);
+        assert_eq!("食費", categorized.分類);
+        let mut specified = Record::test_categorized("2024-01-01", "スーパー", -1000, "日用品");
+        rules.apply(&mut specified);
+        assert_eq!("日用品", specified.分類);
+    }
+
+    #[test]
+    fn test_add_invalid_rule() {
+        let mut config = Config::default();
+
+        let actual = config.add_rule("(", "食費");
+
+        assert!(matches!(actual, Err(MyError::InvalidPattern { .. })));
+        assert!(config.rules.is_empty());
+    }
+
+    #[test]
+    fn test_budget_status() {
+        let config = sample_config();
+        let records = vec![
+            Record::test_categorized("2024-01-05", "スーパー", -20000, "食費"),
+            Record::test_categorized("2024-01-20", "コンビニ", -15000, "食費"),
+            Record::test_categorized("2024-01-21", "返品", 2000, "食費"),
+            Record::test_categorized("2024-02-01", "スーパー", -5000, "食費"),
+            Record::test_categorized("2024-01-10", "電気代", -8000, "光熱費"),
+        ];
+
+        let actual = budget_status(&config, &records, "2024-01-15".parse().unwrap());
+
+        assert_eq!(
+            vec![
+                BudgetStatus {
+                    category: "光熱費",
+                    limit: "10000.5".parse().unwrap(),
+                    spent: Decimal::from(8000),
+                },
+                BudgetStatus {
+                    category: "食費",
+                    limit: Decimal::from(30000),
+                    spent: Decimal::from(35000),
+                },
+            ],
+            actual
+        );
+        assert_eq!(
+            "分類            予算          支出          残り\n\
+             光熱費       10000.5          8000        2000.5\n\
+             食費           30000         35000         -5000\n\
+             警告：「食費」が予算を 5000 JPY 超過しています\n",
+            render_budget_status(&actual, "JPY")
+        );
+    }
+}
@@ -0,0 +1,555 @@
+use crate::{
+    report::{self, GroupBy, ReportRow},
+    Record,
+};
+use chrono::{Datelike, Months, NaiveDate};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use std::{collections::BTreeMap, fmt::Write};
+use unicode_width::UnicodeWidthStr;
+
+/// 端末の棒グラフで、最大の金額を表す棒の長さ（文字数）
+const BAR_WIDTH: usize = 40;
+
+/// 集計結果の収入と支出を、端末に表示する棒グラフにする
+pub fn render_bars(rows: &[ReportRow]) -> String {
+    let labels: Vec<String> = rows
+        .iter()
+        .map(|row| match &row.account {
+            Some(account) => format!("{} {}", account, row.group),
+            None => row.group.clone(),
+        })
+        .collect();
+    let label_width = labels
+        .iter()
+        .map(|label| label.width())
+        .max()
+        .unwrap_or_default();
+    let amount_width = rows
+        .iter()
+        .flat_map(|row| [row.income, row.expense])
+        .map(|amount| amount.to_string().len())
+        .max()
+        .unwrap_or_default();
+    let max = rows
+        .iter()
+        .flat_map(|row| [row.income, row.expense])
+        .max()
+        .unwrap_or_default();
+
+    let mut output = String::new();
+    for (row, label) in rows.iter().zip(&labels) {
+        for (name, amount) in [("収入", row.income), ("支出", row.expense)] {
+            // NOTE: グループの名前は1行目だけに表示する
+            let label = if name == "収入" { label.as_str() } else { "" };
+            writeln!(
+                output,
+                "{}{}  {} {:<bar$}  {:>amount$}",
+                label,
+                " ".repeat(label_width - label.width()),
+                name,
+                "#".repeat(bar_length(amount, max)),
+                amount,
+                bar = BAR_WIDTH,
+                amount = amount_width
+            )
+            .unwrap();
+        }
+    }
+    output
+}
+
+/// 最大の金額に対する割合から決めた棒の長さ。0 でない金額は少なくとも 1 文字にする
+fn bar_length(amount: Decimal, max: Decimal) -> usize {
+    if amount.is_zero() || max.is_zero() {
+        return 0;
+    }
+    let length = (amount / max * Decimal::from(BAR_WIDTH)).round();
+    length.to_usize().unwrap_or_default().max(1)
+}
+
+/// SVG のグラフの幅と高さ
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 300.0;
+/// グラフの描画領域の外側の余白（左、右、上、下）
+const MARGIN: (f64, f64, f64, f64) = (80.0, 20.0, 30.0, 40.0);
+
+const INCOME_COLOR: &str = "#59a14f";
+const EXPENSE_COLOR: &str = "#e15759";
+/// 分類や口座を塗り分ける色
+const COLORS: [&str; 10] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7",
+    "#9c755f", "#bab0ac",
+];
+
+/// 月ごとの収支の棒グラフ、分類ごとの円グラフ、残高の推移の折れ線グラフを載せた HTML を作る
+/// 収支と分類は accounts から、残高は絞り込む前の all_accounts から求める
+// NOTE: 1つのファイルだけで表示できるよう、グラフは SVG で埋め込み、外部のファイルを読み込まない
+pub fn render_html(
+    accounts: &[(String, Vec<Record>)],
+    all_accounts: &[(String, Vec<Record>)],
+    currency: &str,
+) -> String {
+    let months = report::summarize(accounts, GroupBy::Month, false);
+    let categories = report::summarize(accounts, GroupBy::Category, false);
+    let expenses: Vec<(String, Decimal)> = categories
+        .iter()
+        .map(|row| (row.group.clone(), row.expense))
+        .collect();
+    let incomes: Vec<(String, Decimal)> = categories
+        .iter()
+        .map(|row| (row.group.clone(), row.income))
+        .collect();
+    let (balance_months, balances) = balances(all_accounts);
+
+    let mut output = String::new();
+    writeln!(
+        output,
+        "<!DOCTYPE html>\n\
+         <html lang=\"ja\">\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <title>家計簿レポート</title>\n\
+         <style>\n\
+         body {{ font-family: sans-serif; margin: 2em; color: #333; }}\n\
+         svg {{ max-width: 100%; height: auto; }}\n\
+         svg text {{ font-size: 12px; fill: #333; }}\n\
+         </style>\n\
+         </head>\n\
+         <body>\n\
+         <h1>家計簿レポート（{}）</h1>",
+        escape(currency)
+    )
+    .unwrap();
+    for (title, chart) in [
+        ("月ごとの収入と支出", bar_chart(&months, currency)),
+        ("分類ごとの支出", pie_chart(&expenses, currency)),
+        ("分類ごとの収入", pie_chart(&incomes, currency)),
+        (
+            "残高の推移",
+            line_chart(&balance_months, &balances, currency),
+        ),
+    ] {
+        writeln!(output, "<h2>{}</h2>\n{}", title, chart).unwrap();
+    }
+    writeln!(output, "</body>\n</html>").unwrap();
+    output
+}
+
+/// HTML の特殊文字をエスケープする
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn to_f64(amount: Decimal) -> f64 {
+    amount.to_f64().unwrap_or_default()
+}
+
+fn svg_open(output: &mut String, width: f64, height: f64) {
+    writeln!(
+        output,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" viewBox=\"0 0 {0} {1}\">",
+        width, height
+    )
+    .unwrap();
+}
+
+/// 縦軸の目盛り（0、最大値の半分、最大値）と補助線
+fn y_axis(output: &mut String, min: f64, max: f64) {
+    let (left, right, top, bottom) = MARGIN;
+    let plot_height = HEIGHT - top - bottom;
+    let mut ticks = vec![min, (min + max) / 2.0, max];
+    if min < 0.0 && max > 0.0 {
+        ticks.push(0.0);
+    }
+    for value in ticks {
+        let y = top + (max - value) / (max - min) * plot_height;
+        writeln!(
+            output,
+            "<line x1=\"{:.1}\" y1=\"{y:.1}\" x2=\"{:.1}\" y2=\"{y:.1}\" stroke=\"#ddd\"/>\n\
+             <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{:.0}</text>",
+            left,
+            WIDTH - right,
+            left - 6.0,
+            y + 4.0,
+            value
+        )
+        .unwrap();
+    }
+}
+
+/// 凡例を描画領域の上に横に並べる
+fn legend(output: &mut String, items: &[(&str, &str)]) {
+    let mut x = MARGIN.0;
+    for (name, color) in items {
+        writeln!(
+            output,
+            "<rect x=\"{:.1}\" y=\"6\" width=\"12\" height=\"12\" fill=\"{}\"/>\n\
+             <text x=\"{:.1}\" y=\"16\">{}</text>",
+            x,
+            color,
+            x + 16.0,
+            escape(name)
+        )
+        .unwrap();
+        // NOTE: 全角文字は半角文字の2倍の幅として、次の凡例の位置を決める
+        x += 16.0 + name.width() as f64 * 7.0 + 16.0;
+    }
+}
+
+/// 月ごとの収入と支出を並べた棒グラフ
+fn bar_chart(rows: &[ReportRow], currency: &str) -> String {
+    if rows.is_empty() {
+        return "<p>取引がありません</p>".to_string();
+    }
+    let (left, right, top, bottom) = MARGIN;
+    let plot_height = HEIGHT - top - bottom;
+    let slot = (WIDTH - left - right) / rows.len() as f64;
+    let bar = slot * 0.35;
+    let max = rows
+        .iter()
+        .flat_map(|row| [row.income, row.expense])
+        .map(to_f64)
+        .fold(0.0, f64::max);
+    // NOTE: 取引が振替だけの場合でも、目盛りが 0 で割られないようにする
+    let max = if max > 0.0 { max } else { 1.0 };
+
+    let mut output = String::new();
+    svg_open(&mut output, WIDTH, HEIGHT);
+    y_axis(&mut output, 0.0, max);
+    for (i, row) in rows.iter().enumerate() {
+        let x = left + slot * i as f64 + slot * 0.15;
+        for (j, (name, amount, color)) in [
+            ("収入", row.income, INCOME_COLOR),
+            ("支出", row.expense, EXPENSE_COLOR),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let height = to_f64(amount) / max * plot_height;
+            writeln!(
+                output,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\">\
+                 <title>{} {} {} {}</title></rect>",
+                x + bar * j as f64,
+                top + plot_height - height,
+                bar,
+                height,
+                color,
+                escape(&row.group),
+                name,
+                amount,
+                escape(currency)
+            )
+            .unwrap();
+        }
+        writeln!(
+            output,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            x + bar,
+            top + plot_height + 18.0,
+            escape(&row.group)
+        )
+        .unwrap();
+    }
+    legend(
+        &mut output,
+        &[("収入", INCOME_COLOR), ("支出", EXPENSE_COLOR)],
+    );
+    output.push_str("</svg>");
+    output
+}
+
+/// 分類ごとの金額の割合を表す円グラフ。0 の分類は除く
+fn pie_chart(items: &[(String, Decimal)], currency: &str) -> String {
+    let mut items: Vec<&(String, Decimal)> = items
+        .iter()
+        .filter(|(_, amount)| *amount > Decimal::ZERO)
+        .collect();
+    if items.is_empty() {
+        return "<p>取引がありません</p>".to_string();
+    }
+    // NOTE: 金額の大きい順に、12時の位置から時計回りに並べる
+    items.sort_by_key(|(_, amount)| std::cmp::Reverse(*amount));
+    let total: Decimal = items.iter().map(|(_, amount)| *amount).sum();
+    let (cx, cy, r) = (130.0, 130.0, 110.0);
+    let height = f64::max(260.0, items.len() as f64 * 20.0 + 20.0);
+
+    let mut output = String::new();
+    svg_open(&mut output, WIDTH, height);
+    let mut angle = -std::f64::consts::FRAC_PI_2;
+    for (i, (name, amount)) in items.iter().enumerate() {
+        let color = COLORS[i % COLORS.len()];
+        let ratio = to_f64(*amount / total);
+        let title = format!(
+            "<title>{} {} {} ({:.1}%)</title>",
+            escape(name),
+            amount,
+            escape(currency),
+            ratio * 100.0
+        );
+        if items.len() == 1 {
+            writeln!(
+                output,
+                "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\">{}</circle>",
+                cx, cy, r, color, title
+            )
+            .unwrap();
+        } else {
+            let end = angle + ratio * std::f64::consts::TAU;
+            writeln!(
+                output,
+                "<path d=\"M {} {} L {:.2} {:.2} A {} {} 0 {} 1 {:.2} {:.2} Z\" fill=\"{}\">{}</path>",
+                cx,
+                cy,
+                cx + r * angle.cos(),
+                cy + r * angle.sin(),
+                r,
+                r,
+                u8::from(ratio > 0.5),
+                cx + r * end.cos(),
+                cy + r * end.sin(),
+                color,
+                title
+            )
+            .unwrap();
+            angle = end;
+        }
+        let y = 20.0 + i as f64 * 20.0;
+        writeln!(
+            output,
+            "<rect x=\"280\" y=\"{:.1}\" width=\"12\" height=\"12\" fill=\"{}\"/>\n\
+             <text x=\"298\" y=\"{:.1}\">{} {} ({:.1}%)</text>",
+            y,
+            color,
+            y + 10.0,
+            escape(name),
+            amount,
+            ratio * 100.0
+        )
+        .unwrap();
+    }
+    output.push_str("</svg>");
+    output
+}
+
+/// 口座ごとの月末の残高の折れ線グラフ
+fn line_chart(months: &[String], series: &[(String, Vec<Decimal>)], currency: &str) -> String {
+    if months.is_empty() {
+        return "<p>取引がありません</p>".to_string();
+    }
+    let (left, right, top, bottom) = MARGIN;
+    let plot_height = HEIGHT - top - bottom;
+    let values = || {
+        series
+            .iter()
+            .flat_map(|(_, values)| values)
+            .map(|v| to_f64(*v))
+    };
+    let min = values().fold(0.0, f64::min);
+    let max = values().fold(0.0, f64::max);
+    let (min, max) = if min < max {
+        (min, max)
+    } else {
+        (min, min + 1.0)
+    };
+    let step = if months.len() > 1 {
+        (WIDTH - left - right) / (months.len() - 1) as f64
+    } else {
+        0.0
+    };
+    let x = |i: usize| {
+        if months.len() > 1 {
+            left + step * i as f64
+        } else {
+            (left + WIDTH - right) / 2.0
+        }
+    };
+    let y = |value: Decimal| top + (max - to_f64(value)) / (max - min) * plot_height;
+
+    let mut output = String::new();
+    svg_open(&mut output, WIDTH, HEIGHT);
+    y_axis(&mut output, min, max);
+    // NOTE: 月が多い場合は、月の名前が重ならないよう間引いて表示する
+    let every = months.len().div_ceil(12);
+    for (i, month) in months.iter().enumerate().step_by(every) {
+        writeln!(
+            output,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            x(i),
+            top + plot_height + 18.0,
+            escape(month)
+        )
+        .unwrap();
+    }
+    for (n, (account, values)) in series.iter().enumerate() {
+        let color = COLORS[n % COLORS.len()];
+        let points: Vec<String> = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| format!("{:.1},{:.1}", x(i), y(*value)))
+            .collect();
+        writeln!(
+            output,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>",
+            points.join(" "),
+            color
+        )
+        .unwrap();
+        for (i, value) in values.iter().enumerate() {
+            writeln!(
+                output,
+                "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"{}\">\
+                 <title>{} {} {} {}</title></circle>",
+                x(i),
+                y(*value),
+                color,
+                escape(account),
+                escape(&months[i]),
+                value,
+                escape(currency)
+            )
+            .unwrap();
+        }
+    }
+    let items: Vec<(&str, &str)> = series
+        .iter()
+        .enumerate()
+        .map(|(n, (account, _))| (account.as_str(), COLORS[n % COLORS.len()]))
+        .collect();
+    legend(&mut output, &items);
+    output.push_str("</svg>");
+    output
+}
+
+/// 最初の取引の月から最後の取引の月までの、口座ごとの月末の残高
+// NOTE: 残高なので、口座間の振替も含めて集計する
+fn balances(accounts: &[(String, Vec<Record>)]) -> (Vec<String>, Vec<(String, Vec<Decimal>)>) {
+    let dates = accounts
+        .iter()
+        .flat_map(|(_, records)| records)
+        .map(|record| record.日付);
+    let (Some(first), Some(last)) = (dates.clone().min(), dates.max()) else {
+        return (vec![], vec![]);
+    };
+    let mut months = vec![];
+    let mut month = NaiveDate::from_ymd_opt(first.year(), first.month(), 1).unwrap();
+    while month <= last {
+        months.push(month.format("%Y-%m").to_string());
+        month = month.checked_add_months(Months::new(1)).unwrap();
+    }
+
+    let series = accounts
+        .iter()
+        .map(|(account, records)| {
+            let mut totals: BTreeMap<String, Decimal> = BTreeMap::new();
+            for record in records {
+                *totals
+                    .entry(record.日付.format("%Y-%m").to_string())
+                    .or_default() += record.金額;
+            }
+            let mut balance = Decimal::ZERO;
+            let values = months
+                .iter()
+                .map(|month| {
+                    balance += totals.get(month).copied().unwrap_or_default();
+                    balance
+                })
+                .collect();
+            (account.clone(), values)
+        })
+        .collect();
+    (months, series)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_accounts() -> Vec<(String, Vec<Record>)> {
+        vec![
+            (
+                "bank".to_string(),
+                vec![
+                    Record::test_categorized("2024-01-25", "テスト", 200000, "給与"),
+                    Record::test_categorized("2024-03-27", "テスト", -80000, "<家賃>"),
+                ],
+            ),
+            (
+                "wallet".to_string(),
+                vec![Record::test_categorized(
+                    "2024-01-10",
+                    "テスト",
+                    -3000,
+                    "食費",
+                )],
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_render_bars() {
+        let rows = report::summarize(&sample_accounts(), GroupBy::Month, false);
+
+        let actual = render_bars(&rows);
+
+        assert_eq!(
+            "2024-01  収入 ########################################  200000\n\
+             \x20        支出 #                                           3000\n\
+             2024-03  収入                                                0\n\
+             \x20        支出 ################                           80000\n",
+            actual
+        );
+    }
+
+    #[test]
+    fn test_balances() {
+        let (months, series) = balances(&sample_accounts());
+
+        assert_eq!(vec!["2024-01", "2024-02", "2024-03"], months);
+        let amounts = |values: &[i32]| values.iter().map(|v| Decimal::from(*v)).collect();
+        assert_eq!(
+            vec![
+                ("bank".to_string(), amounts(&[200000, 200000, 120000])),
+                ("wallet".to_string(), amounts(&[-3000, -3000, -3000])),
+            ],
+            series
+        );
+    }
+
+    #[test]
+    fn test_render_html() {
+        let actual = render_html(&sample_accounts(), &sample_accounts(), "JPY");
+
+        assert!(actual.starts_with("<!DOCTYPE html>"));
+        assert_eq!(4, actual.matches("<svg ").count());
+        // NOTE: 分類の名前は HTML としてエスケープする
+        assert!(actual.contains("&lt;家賃&gt;"));
+        assert!(!actual.contains("<家賃>"));
+        // NOTE: 外部のファイルを読み込まない
+        assert!(!actual.contains("src="));
+        assert!(!actual.contains("href="));
+    }
+
+    #[test]
+    fn test_render_html_without_records() {
+        let accounts = [("bank".to_string(), vec![])];
+
+        let actual = render_html(&accounts, &accounts, "JPY");
+
+        assert_eq!(4, actual.matches("<p>取引がありません</p>").count());
+    }
+
+    #[test]
+    fn test_render_html_balances_ignore_filter() {
+        // NOTE: タグで絞り込んで取引がなくなっても、残高の推移はすべての取引から求める
+        let filtered = [("bank".to_string(), vec![]), ("wallet".to_string(), vec![])];
+
+        let actual = render_html(&filtered, &sample_accounts(), "JPY");
+
+        assert_eq!(3, actual.matches("<p>取引がありません</p>").count());
+        assert!(actual.contains("<title>bank 2024-03 120000 JPY</title>"));
+    }
+}
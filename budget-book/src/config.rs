@@ -0,0 +1,157 @@
+use crate::{
+    category::Rule, currency::DEFAULT_CURRENCY, import::Profile, journal::AccountKind,
+    recurring::Recurring, MyError,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{collections::BTreeMap, fs, io::ErrorKind, path::Path};
+
+/// データディレクトリに置く設定ファイルの名前
+pub const CONFIG_FILE_NAME: &str = "budget-book.json";
+
+/// 分類のルール、予算、インポートのプロファイル、通貨、定期取引、勘定の種類の設定
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// 先頭から順に評価し、最初に一致したルールの分類を使う
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// 分類ごとの1か月の予算（基準通貨での金額）
+    #[serde(default, deserialize_with = "deserialize_budgets")]
+    pub budgets: BTreeMap<String, Decimal>,
+    /// 銀行の明細など、口座と異なる形式の CSV を読み取るためのプロファイル
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    /// レポートや予算で金額をそろえる通貨（省略した場合は JPY）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_currency: Option<String>,
+    /// 基準通貨以外の通貨を使う口座と、その通貨
+    #[serde(default)]
+    pub currencies: BTreeMap<String, String>,
+    /// 定期的な取引。キーは定期取引の名前
+    #[serde(default)]
+    pub recurring: BTreeMap<String, Recurring>,
+    /// 複式簿記での勘定（口座・分類）の種類
+    #[serde(default)]
+    pub kinds: BTreeMap<String, AccountKind>,
+}
+impl Config {
+    /// 設定ファイルを読み込む。ファイルがない場合は空の設定とする
+    pub fn load(path: &Path) -> Result<Config, MyError> {
+        let config: Config = match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Config::default()),
+            Err(error) => return Err(error.into()),
+        };
+        // NOTE: 設定ファイルは手で書き換えられるので、定期取引の日付を記帳の前に確認する
+        for recurring in config.recurring.values() {
+            recurring.schedule.validate()?;
+        }
+        Ok(config)
+    }
+
+    pub fn base_currency(&self) -> &str {
+        self.base_currency.as_deref().unwrap_or(DEFAULT_CURRENCY)
+    }
+
+    /// 口座の通貨
+    pub fn currency(&self, account_name: &str) -> &str {
+        self.currencies
+            .get(account_name)
+            .map_or_else(|| self.base_currency(), String::as_str)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), MyError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+}
+
+/// 予算の金額。以前の設定ファイルでは整数で保存していた
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BudgetLimit {
+    Integer(u32),
+    Decimal(Decimal),
+}
+
+fn deserialize_budgets<'de, D>(deserializer: D) -> Result<BTreeMap<String, Decimal>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let budgets = BTreeMap::<String, BudgetLimit>::deserialize(deserializer)?;
+    Ok(budgets
+        .into_iter()
+        .map(|(category, limit)| {
+            let limit = match limit {
+                BudgetLimit::Integer(limit) => Decimal::from(limit),
+                BudgetLimit::Decimal(limit) => limit,
+            };
+            (category, limit)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::Column;
+
+    #[test]
+    fn test_load_and_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        assert_eq!(Config::default(), Config::load(&path).unwrap());
+        let mut config = Config::default();
+        config.add_rule("スーパー", "食費").unwrap();
+        config
+            .budgets
+            .insert("食費".to_string(), "30000.5".parse().unwrap());
+        config.profiles.insert(
+            "bank".to_string(),
+            Profile {
+                date: Column::Name("取引日".to_string()),
+                usage: Column::Index(2),
+                amount: Some(Column::Index(3)),
+                ..Default::default()
+            },
+        );
+
+        config.save(&path).unwrap();
+
+        assert_eq!(config, Config::load(&path).unwrap());
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_recurring() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        fs::write(
+            &path,
+            r#"{"recurring":{"rent":{"account":"bank","usage":"家賃","amount":"-80000",
+                "every":"yearly","month":13,"day":1,"start":"2024-01-01"}}}"#,
+        )
+        .unwrap();
+
+        let actual = Config::load(&path);
+
+        assert!(matches!(actual, Err(MyError::InvalidSchedule(_))));
+    }
+
+    #[test]
+    fn test_load_integer_budgets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        fs::write(&path, r#"{"budgets":{"食費":30000,"光熱費":"10000.50"}}"#).unwrap();
+
+        let actual = Config::load(&path).unwrap();
+
+        assert_eq!(Some(&Decimal::from(30000)), actual.budgets.get("食費"));
+        assert_eq!(
+            Some(&"10000.50".parse().unwrap()),
+            actual.budgets.get("光熱費")
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use crate::{MyError, Record};
+use chrono::NaiveDate;
+use csv::{Reader, Writer};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use std::{fs, io::ErrorKind, path::Path};
+
+/// 通貨を指定しない口座の通貨
+pub const DEFAULT_CURRENCY: &str = "JPY";
+
+/// データディレクトリに置く為替レートのファイルの名前
+pub const RATES_FILE_NAME: &str = "rates.csv";
+
+/// 補助単位がない通貨
+const ZERO_DECIMAL_CURRENCIES: [&str; 3] = ["JPY", "KRW", "VND"];
+
+/// 通貨の金額を表すのに使う小数点以下の桁数
+pub fn decimal_places(currency: &str) -> u32 {
+    if ZERO_DECIMAL_CURRENCIES.contains(&currency) {
+        0
+    } else {
+        2
+    }
+}
+
+/// 1 通貨 = レート 基準通貨（その日以降、次のレートまで有効）
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Rate {
+    pub 日付: NaiveDate,
+    pub 通貨: String,
+    pub 基準通貨: String,
+    pub レート: Decimal,
+}
+
+/// 為替レートの表
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Rates {
+    rates: Vec<Rate>,
+}
+impl Rates {
+    /// CSV ファイルから読み込む。ファイルがない場合は空の表とする
+    pub fn load(path: &Path) -> Result<Rates, MyError> {
+        let mut reader = match Reader::from_path(path) {
+            Ok(reader) => reader,
+            Err(error) => match error.kind() {
+                csv::ErrorKind::Io(io) if io.kind() == ErrorKind::NotFound => {
+                    return Ok(Rates::default())
+                }
+                _ => return Err(error.into()),
+            },
+        };
+        let rates = reader
+            .deserialize()
+            .map(|result| {
+                result.map_err(|error: csv::Error| MyError::Parse {
+                    file: path.to_string_lossy().to_string(),
+                    line: error.position().map_or(0, |position| position.line()),
+                    reason: error.to_string(),
+                })
+            })
+            .collect::<Result<Vec<Rate>, _>>()?;
+        let mut table = Rates::default();
+        for rate in rates {
+            table.add(rate);
+        }
+        Ok(table)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), MyError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut writer = Writer::from_path(path)?;
+        for rate in &self.rates {
+            writer.serialize(rate)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// レートを追加する。同じ日付・通貨の組み合わせのレートは置き換える
+    pub fn add(&mut self, rate: Rate) {
+        self.rates.retain(|other| {
+            (&other.日付, &other.通貨, &other.基準通貨) != (&rate.日付, &rate.通貨, &rate.基準通貨)
+        });
+        self.rates.push(rate);
+        self.rates
+            .sort_by(|a, b| (&a.通貨, &a.基準通貨, a.日付).cmp(&(&b.通貨, &b.基準通貨, b.日付)));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rate> {
+        self.rates.iter()
+    }
+
+    /// date の時点で有効な、1 from あたりの to の金額
+    fn rate(&self, from: &str, to: &str, date: NaiveDate) -> Option<Decimal> {
+        let latest = |from: &str, to: &str| {
+            self.rates
+                .iter()
+                .filter(|rate| rate.通貨 == from && rate.基準通貨 == to && rate.日付 <= date)
+                .max_by_key(|rate| rate.日付)
+        };
+        // NOTE: 逆向きのレートしかない場合は、その逆数を使う
+        match (latest(from, to), latest(to, from)) {
+            (Some(direct), Some(inverse)) if inverse.日付 > direct.日付 => {
+                Decimal::ONE.checked_div(inverse.レート)
+            }
+            (Some(direct), _) => Some(direct.レート),
+            (None, Some(inverse)) => Decimal::ONE.checked_div(inverse.レート),
+            (None, None) => None,
+        }
+    }
+
+    /// 金額を date の時点のレートで換算し、換算先の通貨の桁数に丸める
+    pub fn convert(
+        &self,
+        amount: Decimal,
+        from: &str,
+        to: &str,
+        date: NaiveDate,
+    ) -> Result<Decimal, MyError> {
+        if from == to {
+            return Ok(amount);
+        }
+        let rate = self
+            .rate(from, to, date)
+            .ok_or_else(|| MyError::MissingRate {
+                from: from.to_string(),
+                to: to.to_string(),
+                date,
+            })?;
+        Ok((amount * rate)
+            .round_dp_with_strategy(decimal_places(to), RoundingStrategy::MidpointAwayFromZero))
+    }
+
+    /// 取引の金額を、それぞれの日付のレートで換算する
+    pub fn convert_records(
+        &self,
+        records: &mut [Record],
+        from: &str,
+        to: &str,
+    ) -> Result<(), MyError> {
+        for record in records {
+            record.金額 = self.convert(record.金額, from, to, record.日付)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn rate(date: &str, currency: &str, base: &str, rate: &str) -> Rate {
+        Rate {
+            日付: date.parse().unwrap(),
+            通貨: currency.to_string(),
+            基準通貨: base.to_string(),
+            レート: rate.parse().unwrap(),
+        }
+    }
+
+    fn sample_rates() -> Rates {
+        let mut rates = Rates::default();
+        rates.add(rate("2024-01-01", "USD", "JPY", "140"));
+        rates.add(rate("2024-02-01", "USD", "JPY", "150"));
+        rates.add(rate("2024-01-01", "JPY", "EUR", "0.0064"));
+        rates
+    }
+
+    #[rstest]
+    #[case("12.34", "USD", "JPY", "2024-01-15", "1728")]
+    #[case("12.34", "USD", "JPY", "2024-02-01", "1851")]
+    #[case("1000", "JPY", "USD", "2024-01-15", "7.14")]
+    #[case("1000", "JPY", "EUR", "2024-01-15", "6.40")]
+    #[case("-3.5", "USD", "USD", "2023-01-01", "-3.5")]
+    fn test_convert(
+        #[case] amount: Decimal,
+        #[case] from: &str,
+        #[case] to: &str,
+        #[case] date: &str,
+        #[case] expected: Decimal,
+    ) {
+        let actual = sample_rates().convert(amount, from, to, date.parse().unwrap());
+
+        assert_eq!(expected, actual.unwrap());
+    }
+
+    #[test]
+    fn test_convert_without_rate() {
+        let actual =
+            sample_rates().convert(Decimal::ONE, "USD", "JPY", "2023-12-31".parse().unwrap());
+
+        assert!(matches!(actual, Err(MyError::MissingRate { .. })));
+    }
+
+    #[test]
+    fn test_load_and_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(RATES_FILE_NAME);
+        assert_eq!(Rates::default(), Rates::load(&path).unwrap());
+
+        sample_rates().save(&path).unwrap();
+
+        assert_eq!(
+            "日付,通貨,基準通貨,レート\n\
+             2024-01-01,JPY,EUR,0.0064\n\
+             2024-01-01,USD,JPY,140\n\
+             2024-02-01,USD,JPY,150\n",
+            fs::read_to_string(&path).unwrap()
+        );
+        assert_eq!(sample_rates(), Rates::load(&path).unwrap());
+    }
+}
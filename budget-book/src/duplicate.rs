@@ -0,0 +1,252 @@
+use crate::Record;
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use rust_decimal::Decimal;
+use std::{collections::HashMap, fmt::Write};
+
+/// 重複とみなす取引の決め方
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateKey {
+    /// 取引番号がある場合は取引番号、ない場合は日付・金額・用途で比べる
+    Auto,
+    /// 日付・金額・用途で比べる
+    Content,
+    /// 取引番号だけで比べる
+    Id,
+}
+
+/// 重複した取引の扱い
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnDuplicate {
+    /// 取り込まない
+    Skip,
+    /// タグを付けて取り込む
+    Flag,
+}
+
+/// 重複として取り込む取引に付けるタグ
+pub const DUPLICATE_TAG: &str = "重複候補";
+
+/// 取り込む取引を、登録済みの取引と比べて分類した結果
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Classified {
+    pub new: Vec<Record>,
+    pub duplicates: Vec<Record>,
+    /// 取引番号が同じで内容が異なる取引と、登録済みの取引の組
+    pub conflicts: Vec<(Record, Record)>,
+}
+
+type Content = (NaiveDate, Decimal, String);
+
+fn content(record: &Record) -> Content {
+    (record.日付, record.金額, record.用途.clone())
+}
+
+/// 内容が同じ登録済みの取引が残っていれば、1件分を使って重複とする
+fn take_duplicate(by_content: &mut HashMap<Content, usize>, record: &Record) -> bool {
+    match by_content.get_mut(&content(record)) {
+        Some(count) if *count > 0 => {
+            *count -= 1;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// 取り込む取引を、新しい取引・重複・競合に分ける
+// NOTE: 同じ日に同じ金額の買い物をすることもあるので、登録済みの件数を超えた分は新しい取引とする
+pub fn classify(existing: &[Record], incoming: Vec<Record>, key: DuplicateKey) -> Classified {
+    let mut by_id: HashMap<&str, &Record> = HashMap::new();
+    let mut by_content: HashMap<Content, usize> = HashMap::new();
+    for record in existing.iter().filter(|record| !record.is_transfer()) {
+        if !record.取引番号.is_empty() {
+            by_id.insert(&record.取引番号, record);
+        }
+        // NOTE: auto の場合、取引番号のある取引は取引番号でだけ比べる
+        if key == DuplicateKey::Content || record.取引番号.is_empty() {
+            *by_content.entry(content(record)).or_default() += 1;
+        }
+    }
+
+    let mut classified = Classified::default();
+    for record in incoming {
+        let use_id = match key {
+            DuplicateKey::Auto => !record.取引番号.is_empty(),
+            DuplicateKey::Content => false,
+            DuplicateKey::Id => true,
+        };
+        if use_id {
+            match by_id.get(record.取引番号.as_str()) {
+                Some(found) if content(found) == content(&record) => {
+                    classified.duplicates.push(record)
+                }
+                Some(found) => classified.conflicts.push((record, (*found).clone())),
+                // NOTE: 取引番号を記録する前に取り込んだ取引とは、内容で比べる
+                None if key == DuplicateKey::Auto && take_duplicate(&mut by_content, &record) => {
+                    classified.duplicates.push(record)
+                }
+                None => classified.new.push(record),
+            }
+        } else if take_duplicate(&mut by_content, &record) {
+            classified.duplicates.push(record);
+        } else {
+            classified.new.push(record);
+        }
+    }
+    classified
+}
+
+impl Classified {
+    /// 口座に書き込む取引
+    pub fn into_records(self, on_duplicate: OnDuplicate) -> Vec<Record> {
+        let mut records = self.new;
+        if on_duplicate == OnDuplicate::Flag {
+            records.extend(self.duplicates.into_iter().map(|mut record| {
+                record.タグ = record
+                    .tags()
+                    .chain([DUPLICATE_TAG])
+                    .collect::<Vec<_>>()
+                    .join(";");
+                record
+            }));
+        }
+        records
+    }
+
+    /// 取り込み結果の件数と、競合した取引の一覧
+    pub fn render_summary(&self, on_duplicate: OnDuplicate) -> String {
+        let mut output = String::new();
+        match on_duplicate {
+            OnDuplicate::Skip => writeln!(
+                output,
+                "取り込み：{} 件、重複のためスキップ：{} 件、競合：{} 件",
+                self.new.len(),
+                self.duplicates.len(),
+                self.conflicts.len()
+            ),
+            OnDuplicate::Flag => writeln!(
+                output,
+                "取り込み：{} 件（うち重複候補：{} 件）、競合：{} 件",
+                self.new.len() + self.duplicates.len(),
+                self.duplicates.len(),
+                self.conflicts.len()
+            ),
+        }
+        .unwrap();
+        for (incoming, existing) in &self.conflicts {
+            writeln!(
+                output,
+                "競合：取引番号 {}（取り込み：{} {} {}、登録済み：{} {} {}）",
+                incoming.取引番号,
+                incoming.日付,
+                incoming.金額,
+                incoming.用途,
+                existing.日付,
+                existing.金額,
+                existing.用途
+            )
+            .unwrap();
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn existing() -> Vec<Record> {
+        vec![
+            Record::test("2024-01-05", "コーヒー", -400),
+            Record {
+                取引番号: "A001".to_string(),
+                ..Record::test("2024-01-06", "スーパー", -2000)
+            },
+            Record {
+                取引番号: "A002".to_string(),
+                ..Record::test("2024-01-07", "給与", 200000)
+            },
+        ]
+    }
+
+    fn summary(classified: &Classified) -> (usize, usize, usize) {
+        (
+            classified.new.len(),
+            classified.duplicates.len(),
+            classified.conflicts.len(),
+        )
+    }
+
+    #[rstest]
+    #[case(DuplicateKey::Auto, (2, 2, 1))]
+    #[case(DuplicateKey::Content, (2, 3, 0))]
+    #[case(DuplicateKey::Id, (3, 1, 1))]
+    fn test_classify(#[case] key: DuplicateKey, #[case] expected: (usize, usize, usize)) {
+        let incoming = vec![
+            // 同じ日に同じ金額のコーヒーを2回買った場合、1件は新しい取引とする
+            Record::test("2024-01-05", "コーヒー", -400),
+            Record::test("2024-01-05", "コーヒー", -400),
+            Record {
+                取引番号: "A001".to_string(),
+                ..Record::test("2024-01-06", "スーパー", -2000)
+            },
+            // 取引番号が同じで金額が異なる
+            Record {
+                取引番号: "A002".to_string(),
+                ..Record::test("2024-01-07", "給与", 210000)
+            },
+            // 取引番号のない、内容が同じ取引
+            Record::test("2024-01-07", "給与", 200000),
+        ];
+
+        let actual = classify(&existing(), incoming, key);
+
+        assert_eq!(expected, summary(&actual));
+    }
+
+    #[test]
+    fn test_flag_duplicates() {
+        let incoming = vec![
+            Record {
+                取引番号: "A001".to_string(),
+                ..Record::test("2024-01-06", "スーパー", -2000)
+            },
+            Record {
+                取引番号: "A003".to_string(),
+                ..Record::test("2024-01-08", "書店", -1500)
+            },
+        ];
+        let classified = classify(&existing(), incoming, DuplicateKey::Auto);
+
+        assert_eq!(
+            "取り込み：2 件（うち重複候補：1 件）、競合：0 件\n",
+            classified.render_summary(OnDuplicate::Flag)
+        );
+        let actual = classified.into_records(OnDuplicate::Flag);
+
+        assert_eq!(
+            vec![("A003", ""), ("A001", "重複候補")],
+            actual
+                .iter()
+                .map(|record| (record.取引番号.as_str(), record.タグ.as_str()))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_render_conflicts() {
+        let incoming = vec![Record {
+            取引番号: "A002".to_string(),
+            ..Record::test("2024-01-07", "給与", 210000)
+        }];
+
+        let actual = classify(&existing(), incoming, DuplicateKey::Id);
+
+        assert_eq!(
+            "取り込み：0 件、重複のためスキップ：0 件、競合：1 件\n\
+             競合：取引番号 A002（取り込み：2024-01-07 210000 給与、登録済み：2024-01-07 200000 給与）\n",
+            actual.render_summary(OnDuplicate::Skip)
+        );
+    }
+}
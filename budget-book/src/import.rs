@@ -0,0 +1,363 @@
+use crate::{MyError, Record};
+use chrono::NaiveDate;
+use csv::{ReaderBuilder, StringRecord};
+use encoding_rs::Encoding;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::{fmt::Write, fs, path::Path};
+use unicode_width::UnicodeWidthStr;
+
+/// 列の指定。0 から始まる列番号、またはヘッダーの名前
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Column {
+    Index(usize),
+    Name(String),
+}
+
+/// 口座と異なる形式の CSV を読み取るための設定
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct Profile {
+    /// 文字コード（utf-8、shift_jis など）
+    pub encoding: String,
+    /// ヘッダーの行番号（1 から始まる）。これより前の行は読み飛ばし、0 の場合はヘッダーなしとする
+    pub header_row: u64,
+    pub date: Column,
+    /// 日付の書式（strftime 形式）
+    pub date_format: String,
+    pub usage: Column,
+    /// 符号付きの金額の列
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<Column>,
+    /// 出金額の列（正の値で記載されているもの）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debit: Option<Column>,
+    /// 入金額の列
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credit: Option<Column>,
+    /// 金額の列の符号を反転する（クレジットカードの明細など、支払いが正の値で記載される場合）
+    pub invert_sign: bool,
+    /// 銀行が付けた取引番号の列。重複の検出に使う
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Column>,
+}
+impl Default for Profile {
+    fn default() -> Self {
+        Profile {
+            encoding: "utf-8".to_string(),
+            header_row: 1,
+            date: Column::Index(0),
+            date_format: "%Y-%m-%d".to_string(),
+            usage: Column::Index(1),
+            amount: None,
+            debit: None,
+            credit: None,
+            invert_sign: false,
+            id: None,
+        }
+    }
+}
+impl Profile {
+    /// 設定の組み合わせが正しいかを確認する
+    pub fn validate(&self) -> Result<(), MyError> {
+        self.encoding()?;
+        match (&self.amount, &self.debit, &self.credit) {
+            (Some(_), None, None) => Ok(()),
+            (None, Some(_), _) | (None, _, Some(_)) => Ok(()),
+            (None, None, None) => Err(MyError::InvalidProfile(
+                "amount、または debit と credit のどちらかを指定してください".to_string(),
+            )),
+            _ => Err(MyError::InvalidProfile(
+                "amount と debit・credit は同時に指定できません".to_string(),
+            )),
+        }
+    }
+
+    fn encoding(&self) -> Result<&'static Encoding, MyError> {
+        Encoding::for_label(self.encoding.as_bytes()).ok_or_else(|| {
+            MyError::InvalidProfile(format!(
+                "文字コード「{}」には対応していません",
+                self.encoding
+            ))
+        })
+    }
+
+    /// CSV ファイルから取引を読み取る
+    pub fn read(&self, path: &Path) -> Result<Vec<Record>, MyError> {
+        self.validate()?;
+        let file = path.to_string_lossy().to_string();
+        let bytes = fs::read(path)?;
+        // NOTE: BOM がある場合は、プロファイルの指定より BOM の文字コードを優先する
+        let (content, encoding, had_errors) = self.encoding()?.decode(&bytes);
+        if had_errors {
+            return Err(MyError::Parse {
+                file,
+                line: 0,
+                reason: format!("{} として読み取れない文字があります", encoding.name()),
+            });
+        }
+
+        let mut reader = ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(content.as_bytes());
+        let mut headers = StringRecord::new();
+        let mut records = vec![];
+        for result in reader.records() {
+            let row = result?;
+            let line = row.position().map_or(0, |position| position.line());
+            if line < self.header_row {
+                continue;
+            }
+            if line == self.header_row {
+                headers = row;
+                continue;
+            }
+            // NOTE: 明細の末尾にある空行は読み飛ばす
+            if row.iter().all(|field| field.trim().is_empty()) {
+                continue;
+            }
+            let parse_error = |reason: String| MyError::Parse {
+                file: file.clone(),
+                line,
+                reason,
+            };
+            let field = |column: &Column| -> Result<&str, MyError> {
+                let index = match column {
+                    Column::Index(index) => *index,
+                    Column::Name(name) => headers
+                        .iter()
+                        .position(|header| header.trim() == name)
+                        .ok_or_else(|| {
+                        MyError::InvalidProfile(format!("列「{}」がヘッダーにありません", name))
+                    })?,
+                };
+                Ok(row.get(index).unwrap_or_default().trim())
+            };
+
+            let date = field(&self.date)?;
+            let date = NaiveDate::parse_from_str(date, &self.date_format)
+                .map_err(|_| parse_error(format!("日付「{}」が不正です", date)))?;
+            let amount = |column: &Column| -> Result<Option<Decimal>, MyError> {
+                let text = field(column)?;
+                parse_amount(text).map_err(|_| parse_error(format!("金額「{}」が不正です", text)))
+            };
+            let amount = match &self.amount {
+                Some(column) => {
+                    let amount =
+                        amount(column)?.ok_or_else(|| parse_error("金額が空です".to_string()))?;
+                    if self.invert_sign {
+                        -amount
+                    } else {
+                        amount
+                    }
+                }
+                None => {
+                    let credit = self.credit.as_ref().map(amount).transpose()?.flatten();
+                    let debit = self.debit.as_ref().map(amount).transpose()?.flatten();
+                    credit.unwrap_or_default() - debit.unwrap_or_default()
+                }
+            };
+            records.push(Record {
+                日付: date,
+                用途: field(&self.usage)?.to_string(),
+                金額: amount,
+                分類: String::new(),
+                タグ: String::new(),
+                振替番号: String::new(),
+                取引番号: self
+                    .id
+                    .as_ref()
+                    .map(field)
+                    .transpose()?
+                    .unwrap_or_default()
+                    .to_string(),
+            });
+        }
+        Ok(records)
+    }
+}
+
+/// 取り込む前の確認用に、取引を表形式で出力する
+pub fn render_preview(records: &[Record]) -> String {
+    let category_width = records
+        .iter()
+        .map(|record| record.分類.width())
+        .chain(["分類".width()])
+        .max()
+        .unwrap_or_default();
+    let pad = |text: &str| format!("{}{}", text, " ".repeat(category_width - text.width()));
+
+    let mut output = String::new();
+    writeln!(output, "日付        {:>8}  {}  用途", "金額", pad("分類")).unwrap();
+    for record in records {
+        writeln!(
+            output,
+            "{}  {:>10}  {}  {}",
+            record.日付,
+            record.金額,
+            pad(&record.分類),
+            record.用途
+        )
+        .unwrap();
+    }
+    output
+}
+
+/// 「1,234」「¥1,234」「△500」のような表記の金額を読み取る。空の場合は None
+fn parse_amount(text: &str) -> Result<Option<Decimal>, rust_decimal::Error> {
+    let text: String = text
+        .chars()
+        .filter(|c| !matches!(c, ',' | '，' | '¥' | '￥' | '円' | ' ' | '　'))
+        .collect();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    // NOTE: 銀行の明細では負の値を「△」「▲」で表すことがある
+    match text.strip_prefix(['-', '−', '△', '▲']) {
+        Some(digits) => Ok(Some(-digits.parse::<Decimal>()?)),
+        None => Ok(Some(text.parse::<Decimal>()?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("1234", Some("1234"))]
+    #[case("1,234.5", Some("1234.5"))]
+    #[case("￥1,234円", Some("1234"))]
+    #[case("-500", Some("-500"))]
+    #[case("△500", Some("-500"))]
+    #[case(" ", None)]
+    fn test_parse_amount(#[case] text: &str, #[case] expected: Option<&str>) {
+        let expected = expected.map(|amount| amount.parse::<Decimal>().unwrap());
+
+        assert_eq!(expected, parse_amount(text).unwrap());
+    }
+
+    #[test]
+    fn test_read_shift_jis_with_debit_and_credit() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("statement.csv");
+        let content = "入出金明細\n\
+                       取引日,摘要,お引出し,お預入れ,残高,番号\n\
+                       2024/01/05,カード,\"1,200\",,\"98,800\",A001\n\
+                       2024/01/25,給与,,\"200,000\",\"298,800\",A002\n\
+                       ,,,,,\n";
+        fs::write(&path, encoding_rs::SHIFT_JIS.encode(content).0).unwrap();
+        let profile = Profile {
+            encoding: "shift_jis".to_string(),
+            header_row: 2,
+            date: Column::Name("取引日".to_string()),
+            date_format: "%Y/%m/%d".to_string(),
+            usage: Column::Name("摘要".to_string()),
+            id: Some(Column::Name("番号".to_string())),
+            debit: Some(Column::Name("お引出し".to_string())),
+            credit: Some(Column::Index(3)),
+            ..Default::default()
+        };
+
+        let actual: Vec<(String, String, Decimal, String)> = profile
+            .read(&path)
+            .unwrap()
+            .into_iter()
+            .map(|record| {
+                (
+                    record.日付.to_string(),
+                    record.用途,
+                    record.金額,
+                    record.取引番号,
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            vec![
+                (
+                    "2024-01-05".to_string(),
+                    "カード".to_string(),
+                    Decimal::from(-1200),
+                    "A001".to_string()
+                ),
+                (
+                    "2024-01-25".to_string(),
+                    "給与".to_string(),
+                    Decimal::from(200000),
+                    "A002".to_string()
+                ),
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn test_read_with_inverted_amount() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("card.csv");
+        fs::write(
+            &path,
+            "2024-01-05,書店,1500\n2024-01-06,返品,-300\n2024-01-07,書店,千円\n",
+        )
+        .unwrap();
+        let profile = Profile {
+            header_row: 0,
+            amount: Some(Column::Index(2)),
+            invert_sign: true,
+            ..Default::default()
+        };
+
+        let actual = profile.read(&path);
+
+        assert!(matches!(actual, Err(MyError::Parse { line: 3, .. })));
+        fs::write(&path, "2024-01-05,書店,1500\n2024-01-06,返品,-300\n").unwrap();
+        let amounts: Vec<Decimal> = profile
+            .read(&path)
+            .unwrap()
+            .iter()
+            .map(|record| record.金額)
+            .collect();
+        assert_eq!(vec![Decimal::from(-1500), Decimal::from(300)], amounts);
+    }
+
+    #[test]
+    fn test_render_preview() {
+        let actual = render_preview(&[
+            Record::test_categorized("2024-01-05", "スーパー", -1200, "食費"),
+            Record::test("2024-01-25", "給与", 200000),
+        ]);
+
+        assert_eq!(
+            "日付              金額  分類  用途\n\
+             2024-01-05       -1200  食費  スーパー\n\
+             2024-01-25      200000        給与\n",
+            actual
+        );
+    }
+
+    #[rstest]
+    #[case(None, None, None, "utf-8", false)]
+    #[case(Some(2), Some(3), None, "utf-8", false)]
+    #[case(None, Some(2), None, "utf-8", true)]
+    #[case(Some(2), None, None, "unknown", false)]
+    fn test_validate(
+        #[case] amount: Option<usize>,
+        #[case] debit: Option<usize>,
+        #[case] credit: Option<usize>,
+        #[case] encoding: &str,
+        #[case] expected: bool,
+    ) {
+        let profile = Profile {
+            encoding: encoding.to_string(),
+            amount: amount.map(Column::Index),
+            debit: debit.map(Column::Index),
+            credit: credit.map(Column::Index),
+            ..Default::default()
+        };
+
+        assert_eq!(expected, profile.validate().is_ok());
+    }
+}
@@ -0,0 +1,630 @@
+use crate::{category::UNCATEGORIZED, config::Config, MyError, Record};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write,
+};
+use unicode_width::UnicodeWidthStr;
+
+/// 勘定の種類
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountKind {
+    /// 資産（現金、預金など）
+    Asset,
+    /// 負債（クレジットカード、借入金など）
+    Liability,
+    /// 純資産（元入金など）
+    Equity,
+    /// 収益（給与、会費など）
+    Income,
+    /// 費用（食費、家賃など）
+    Expense,
+}
+impl AccountKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AccountKind::Asset => "資産",
+            AccountKind::Liability => "負債",
+            AccountKind::Equity => "純資産",
+            AccountKind::Income => "収益",
+            AccountKind::Expense => "費用",
+        }
+    }
+
+    /// 残高が貸方に出る勘定
+    fn is_credit(&self) -> bool {
+        matches!(
+            self,
+            AccountKind::Liability | AccountKind::Equity | AccountKind::Income
+        )
+    }
+}
+
+/// 通貨の異なる口座間の振替で、換算の端数を計上する勘定
+pub const EXCHANGE_ACCOUNT: &str = "為替差損益";
+
+/// 仕訳の1行。金額は借方を正、貸方を負とする
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Posting {
+    pub account: String,
+    pub amount: Decimal,
+}
+
+/// 借方と貸方の合計が一致する（金額の合計が 0 になる）べき取引
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub date: NaiveDate,
+    /// 振替番号（1件の取引だけの仕訳は空）
+    pub id: String,
+    pub description: String,
+    pub postings: Vec<Posting>,
+}
+impl Entry {
+    /// 借方と貸方の差額。0 でない場合は仕訳が壊れている
+    pub fn difference(&self) -> Decimal {
+        self.postings.iter().map(|posting| posting.amount).sum()
+    }
+}
+
+/// 口座の取引から仕訳帳を作る。金額は基準通貨に換算したものを渡す
+// NOTE: 分類のある取引は、分類を相手勘定とする仕訳とみなす（deposit・withdraw は収益・費用との仕訳になる）
+// NOTE: 分類のない振替の取引は、同じ振替番号の取引どうしで1つの仕訳とする
+pub fn journal(config: &Config, accounts: &[(String, Vec<Record>)]) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = vec![];
+    let mut currencies: Vec<BTreeSet<&str>> = vec![];
+    let mut linked: HashMap<&str, usize> = HashMap::new();
+    for (account, records) in accounts {
+        for record in records {
+            let mut new_entry = || {
+                entries.push(Entry {
+                    date: record.日付,
+                    id: record.振替番号.clone(),
+                    description: record.用途.clone(),
+                    postings: vec![],
+                });
+                currencies.push(BTreeSet::new());
+                entries.len() - 1
+            };
+            let index = if record.振替番号.is_empty() {
+                new_entry()
+            } else {
+                *linked
+                    .entry(record.振替番号.as_str())
+                    .or_insert_with(new_entry)
+            };
+            let entry = &mut entries[index];
+            entry.postings.push(Posting {
+                account: account.clone(),
+                amount: record.金額,
+            });
+            currencies[index].insert(config.currency(account));
+            if !record.is_transfer() {
+                let category = match record.分類.as_str() {
+                    "" => UNCATEGORIZED,
+                    category => category,
+                };
+                entry.postings.push(Posting {
+                    account: category.to_string(),
+                    amount: -record.金額,
+                });
+            }
+        }
+    }
+    for (entry, currencies) in entries.iter_mut().zip(currencies) {
+        let difference = entry.difference();
+        if currencies.len() > 1 && !difference.is_zero() {
+            entry.postings.push(Posting {
+                account: EXCHANGE_ACCOUNT.to_string(),
+                amount: -difference,
+            });
+        }
+    }
+    entries.sort_by(|a, b| (a.date, &a.id).cmp(&(b.date, &b.id)));
+    entries
+}
+
+/// 借方と貸方の合計が一致しない仕訳
+pub fn unbalanced(entries: &[Entry]) -> Vec<&Entry> {
+    entries
+        .iter()
+        .filter(|entry| !entry.difference().is_zero())
+        .collect()
+}
+
+/// 勘定ごとの借方・貸方の合計
+#[derive(Debug, PartialEq, Eq)]
+pub struct AccountBalance {
+    pub account: String,
+    pub kind: AccountKind,
+    pub debit: Decimal,
+    /// 貸方の合計（正の値）
+    pub credit: Decimal,
+}
+impl AccountBalance {
+    /// 借方残高を正とする残高
+    pub fn balance(&self) -> Decimal {
+        self.debit - self.credit
+    }
+
+    /// 勘定の種類に合わせて、残高が出る側を正とした金額
+    fn amount(&self) -> Decimal {
+        // NOTE: 残高が 0 の貸方の勘定を「-0」と表示しないよう、符号を反転する前に確認する
+        if self.kind.is_credit() && !self.balance().is_zero() {
+            -self.balance()
+        } else {
+            self.balance()
+        }
+    }
+}
+
+/// 試算表を作る。real_accounts は口座として記録している勘定
+// NOTE: 種類を設定していない勘定は、口座なら資産、分類なら残高の側で収益か費用かを決める
+pub fn trial_balance(
+    config: &Config,
+    entries: &[Entry],
+    real_accounts: &[String],
+) -> Vec<AccountBalance> {
+    let mut totals: BTreeMap<&str, (Decimal, Decimal)> = BTreeMap::new();
+    for account in real_accounts {
+        totals.entry(account).or_default();
+    }
+    for posting in entries.iter().flat_map(|entry| &entry.postings) {
+        let (debit, credit) = totals.entry(&posting.account).or_default();
+        if posting.amount.is_sign_negative() {
+            *credit -= posting.amount;
+        } else {
+            *debit += posting.amount;
+        }
+    }
+    let mut balances: Vec<AccountBalance> = totals
+        .into_iter()
+        .map(|(account, (debit, credit))| {
+            let kind = match config.kinds.get(account) {
+                Some(kind) => *kind,
+                None if real_accounts.iter().any(|real| real == account) => AccountKind::Asset,
+                None if debit >= credit => AccountKind::Expense,
+                None => AccountKind::Income,
+            };
+            AccountBalance {
+                account: account.to_string(),
+                kind,
+                debit,
+                credit,
+            }
+        })
+        .collect();
+    balances.sort_by_key(|balance| balance.kind);
+    balances
+}
+
+/// 仕訳の金額を、口座の取引として記帳する形に分ける
+///
+/// 口座への金額 real と、分類（収益・費用など）への金額 nominal の合計は 0 であること。
+/// 戻り値は (口座, 金額, 分類) で、分類が空のものは口座間の振替になる
+pub fn split_postings(
+    real: &[(String, Decimal)],
+    nominal: &[(String, Decimal)],
+) -> Result<Vec<(String, Decimal, String)>, MyError> {
+    if real.is_empty() && !nominal.is_empty() {
+        return Err(MyError::InvalidPosting(
+            "口座の勘定を1つ以上指定してください".to_string(),
+        ));
+    }
+    let mut remaining: Vec<Decimal> = real.iter().map(|(_, amount)| *amount).collect();
+    let mut records = vec![];
+    for (category, amount) in nominal {
+        // NOTE: 分類に借方で計上する金額は、口座の貸方（出金）から充てる
+        let mut need = -*amount;
+        for (i, (account, _)) in real.iter().enumerate() {
+            if need.is_zero() {
+                break;
+            }
+            if remaining[i].is_zero() || remaining[i].is_sign_negative() != need.is_sign_negative()
+            {
+                continue;
+            }
+            let take = if remaining[i].abs() < need.abs() {
+                remaining[i]
+            } else {
+                need
+            };
+            push(&mut records, account, take, category);
+            remaining[i] -= take;
+            need -= take;
+        }
+        // NOTE: 差し引きで計上する場合（給与から天引きされる費用など）は、残りを最初の口座に計上する
+        if !need.is_zero() {
+            push(&mut records, &real[0].0, need, category);
+            remaining[0] -= need;
+        }
+    }
+    for ((account, _), amount) in real.iter().zip(remaining) {
+        if !amount.is_zero() {
+            push(&mut records, account, amount, "");
+        }
+    }
+    Ok(records)
+}
+
+/// 口座と分類が同じ取引があればまとめ、なければ追加する
+fn push(
+    records: &mut Vec<(String, Decimal, String)>,
+    account: &str,
+    amount: Decimal,
+    category: &str,
+) {
+    match records
+        .iter_mut()
+        .find(|(a, _, c)| a == account && c == category)
+    {
+        Some((_, total, _)) => *total += amount,
+        None => records.push((account.to_string(), amount, category.to_string())),
+    }
+}
+
+/// 勘定名を表示幅でそろえる関数
+fn padder<'a>(names: impl Iterator<Item = &'a str>) -> impl Fn(&str) -> String {
+    let width = names.map(UnicodeWidthStr::width).max().unwrap_or_default();
+    move |name: &str| format!("{}{}", name, " ".repeat(width.saturating_sub(name.width())))
+}
+
+/// 仕訳帳を出力する
+pub fn render_journal(entries: &[Entry]) -> String {
+    let pad = padder(
+        entries
+            .iter()
+            .flat_map(|entry| &entry.postings)
+            .map(|posting| posting.account.as_str()),
+    );
+    let mut output = String::new();
+    for entry in entries {
+        write!(output, "{}  {}", entry.date, entry.description).unwrap();
+        if !entry.id.is_empty() {
+            write!(output, "（{}）", entry.id).unwrap();
+        }
+        writeln!(output).unwrap();
+        for posting in &entry.postings {
+            writeln!(
+                output,
+                "    {}  {:>12}",
+                pad(&posting.account),
+                posting.amount
+            )
+            .unwrap();
+        }
+    }
+    output
+}
+
+/// 試算表を表形式で出力する
+pub fn render_trial_balance(balances: &[AccountBalance]) -> String {
+    let pad = padder(
+        balances
+            .iter()
+            .map(|balance| balance.account.as_str())
+            .chain(["勘定", "合計"]),
+    );
+    let mut output = String::new();
+    writeln!(
+        output,
+        "{}  種類    {:>10}  {:>10}  {:>10}",
+        pad("勘定"),
+        "借方",
+        "貸方",
+        "残高"
+    )
+    .unwrap();
+    for balance in balances {
+        writeln!(
+            output,
+            "{}  {}  {:>12}  {:>12}  {:>12}",
+            pad(&balance.account),
+            pad_kind(balance.kind),
+            balance.debit,
+            balance.credit,
+            balance.balance()
+        )
+        .unwrap();
+    }
+    let debit: Decimal = balances.iter().map(|balance| balance.debit).sum();
+    let credit: Decimal = balances.iter().map(|balance| balance.credit).sum();
+    writeln!(
+        output,
+        "{}  {}  {:>12}  {:>12}  {:>12}",
+        pad("合計"),
+        pad_kind_name(""),
+        debit,
+        credit,
+        debit - credit
+    )
+    .unwrap();
+    output
+}
+
+fn pad_kind(kind: AccountKind) -> String {
+    pad_kind_name(kind.name())
+}
+
+fn pad_kind_name(name: &str) -> String {
+    format!("{}{}", name, " ".repeat(6 - name.width()))
+}
+
+/// 貸借対照表（to 時点）と損益計算書（from から to まで）を出力する
+pub fn render_statements(
+    config: &Config,
+    entries: &[Entry],
+    real_accounts: &[String],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> String {
+    let until: Vec<Entry> = entries
+        .iter()
+        .filter(|entry| entry.date <= to)
+        .cloned()
+        .collect();
+    let period: Vec<Entry> = until
+        .iter()
+        .filter(|entry| entry.date >= from)
+        .cloned()
+        .collect();
+    let sheet = trial_balance(config, &until, real_accounts);
+    let income = trial_balance(config, &period, real_accounts);
+    let pad = padder(
+        sheet
+            .iter()
+            .map(|balance| balance.account.as_str())
+            .chain(["負債・純資産合計", "利益剰余金"]),
+    );
+    let total = |balances: &[AccountBalance], kinds: &[AccountKind]| -> Decimal {
+        balances
+            .iter()
+            .filter(|balance| kinds.contains(&balance.kind))
+            .map(AccountBalance::amount)
+            .sum()
+    };
+
+    let mut output = String::new();
+    let section = |output: &mut String, balances: &[AccountBalance], kind: AccountKind| {
+        writeln!(output, "{}", kind.name()).unwrap();
+        for balance in balances.iter().filter(|balance| balance.kind == kind) {
+            writeln!(
+                output,
+                "  {}  {:>12}",
+                pad(&balance.account),
+                balance.amount()
+            )
+            .unwrap();
+        }
+    };
+    let line = |output: &mut String, label: &str, amount: Decimal| {
+        writeln!(output, "{}  {:>14}", pad(label), amount).unwrap();
+    };
+
+    writeln!(output, "貸借対照表（{} 時点）", to).unwrap();
+    section(&mut output, &sheet, AccountKind::Asset);
+    line(
+        &mut output,
+        "資産合計",
+        total(&sheet, &[AccountKind::Asset]),
+    );
+    section(&mut output, &sheet, AccountKind::Liability);
+    line(
+        &mut output,
+        "負債合計",
+        total(&sheet, &[AccountKind::Liability]),
+    );
+    section(&mut output, &sheet, AccountKind::Equity);
+    // NOTE: これまでの収益と費用の差額は、利益剰余金として純資産に含める
+    let retained = total(&sheet, &[AccountKind::Income]) - total(&sheet, &[AccountKind::Expense]);
+    writeln!(output, "  {}  {:>12}", pad("利益剰余金"), retained).unwrap();
+    let equity = total(&sheet, &[AccountKind::Equity]) + retained;
+    line(&mut output, "純資産合計", equity);
+    line(
+        &mut output,
+        "負債・純資産合計",
+        total(&sheet, &[AccountKind::Liability]) + equity,
+    );
+    writeln!(output).unwrap();
+
+    writeln!(output, "損益計算書（{} 〜 {}）", from, to).unwrap();
+    section(&mut output, &income, AccountKind::Income);
+    line(
+        &mut output,
+        "収益合計",
+        total(&income, &[AccountKind::Income]),
+    );
+    section(&mut output, &income, AccountKind::Expense);
+    line(
+        &mut output,
+        "費用合計",
+        total(&income, &[AccountKind::Expense]),
+    );
+    line(
+        &mut output,
+        "当期純利益",
+        total(&income, &[AccountKind::Income]) - total(&income, &[AccountKind::Expense]),
+    );
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> (Config, Vec<(String, Vec<Record>)>) {
+        let mut config = Config::default();
+        config
+            .kinds
+            .insert("card".to_string(), AccountKind::Liability);
+        config
+            .kinds
+            .insert("元入金".to_string(), AccountKind::Equity);
+        let accounts = vec![
+            (
+                "bank".to_string(),
+                vec![
+                    Record::test_categorized("2024-01-01", "開始残高", 50000, "元入金"),
+                    Record::test_categorized("2024-01-25", "給与", 200000, "給与"),
+                    Record {
+                        振替番号: "T1".to_string(),
+                        ..Record::test("2024-01-27", "カードの支払い", -3000)
+                    },
+                ],
+            ),
+            (
+                "card".to_string(),
+                vec![
+                    Record::test_categorized("2024-01-10", "スーパー", -5000, "食費"),
+                    Record {
+                        振替番号: "T1".to_string(),
+                        ..Record::test("2024-01-27", "カードの支払い", 3000)
+                    },
+                ],
+            ),
+        ];
+        (config, accounts)
+    }
+
+    #[test]
+    fn test_journal() {
+        let (config, accounts) = sample();
+
+        let actual = journal(&config, &accounts);
+
+        assert_eq!(4, actual.len());
+        assert!(unbalanced(&actual).is_empty());
+        assert_eq!(
+            vec![
+                ("bank", Decimal::from(-3000)),
+                ("card", Decimal::from(3000))
+            ],
+            actual[3]
+                .postings
+                .iter()
+                .map(|posting| (posting.account.as_str(), posting.amount))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_unbalanced_transfer() {
+        let (config, mut accounts) = sample();
+        // 振替の入金側だけが失われた場合
+        accounts[1].1.pop();
+
+        let entries = journal(&config, &accounts);
+
+        let actual = unbalanced(&entries);
+        assert_eq!(1, actual.len());
+        assert_eq!(Decimal::from(-3000), actual[0].difference());
+    }
+
+    #[test]
+    fn test_trial_balance_and_statements() {
+        let (config, accounts) = sample();
+        let entries = journal(&config, &accounts);
+        let real = vec!["bank".to_string(), "card".to_string()];
+
+        let balances = trial_balance(&config, &entries, &real);
+
+        assert_eq!(
+            "勘定    種類            借方          貸方          残高\n\
+             bank    資産          250000          3000        247000\n\
+             card    負債            3000          5000         -2000\n\
+             元入金  純資産             0         50000        -50000\n\
+             給与    収益               0        200000       -200000\n\
+             食費    費用            5000             0          5000\n\
+             合計                  258000        258000             0\n",
+            render_trial_balance(&balances)
+        );
+        assert_eq!(
+            "貸借対照表（2024-01-31 時点）\n\
+             資産\n\
+             \x20 bank                    247000\n\
+             資産合計                  247000\n\
+             負債\n\
+             \x20 card                      2000\n\
+             負債合計                    2000\n\
+             純資産\n\
+             \x20 元入金                   50000\n\
+             \x20 利益剰余金              195000\n\
+             純資産合計                245000\n\
+             負債・純資産合計          247000\n\
+             \n\
+             損益計算書（2024-01-15 〜 2024-01-31）\n\
+             収益\n\
+             \x20 給与                    200000\n\
+             収益合計                  200000\n\
+             費用\n\
+             費用合計                       0\n\
+             当期純利益                200000\n",
+            render_statements(
+                &config,
+                &entries,
+                &real,
+                "2024-01-15".parse().unwrap(),
+                "2024-01-31".parse().unwrap()
+            )
+        );
+    }
+
+    #[test]
+    fn test_split_postings_with_deduction() {
+        let amount = Decimal::from;
+        let real = vec![("bank".to_string(), amount(180000))];
+        let nominal = vec![
+            ("給与".to_string(), amount(-200000)),
+            ("保険料".to_string(), amount(20000)),
+        ];
+
+        let actual = split_postings(&real, &nominal).unwrap();
+
+        assert_eq!(
+            vec![
+                ("bank".to_string(), amount(200000), "給与".to_string()),
+                ("bank".to_string(), amount(-20000), "保険料".to_string()),
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn test_split_postings() {
+        let amount = Decimal::from;
+        let real = vec![
+            ("bank".to_string(), amount(-3000)),
+            ("wallet".to_string(), amount(-1000)),
+            ("card".to_string(), amount(500)),
+        ];
+        let nominal = vec![
+            ("食費".to_string(), amount(2500)),
+            ("日用品".to_string(), amount(1000)),
+        ];
+
+        let actual = split_postings(&real, &nominal).unwrap();
+
+        assert_eq!(
+            vec![
+                ("bank".to_string(), amount(-2500), "食費".to_string()),
+                ("bank".to_string(), amount(-500), "日用品".to_string()),
+                ("wallet".to_string(), amount(-500), "日用品".to_string()),
+                ("wallet".to_string(), amount(-500), String::new()),
+                ("card".to_string(), amount(500), String::new()),
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn test_split_postings_without_real() {
+        let nominal = vec![("食費".to_string(), Decimal::from(1000))];
+
+        let actual = split_postings(&[], &nominal);
+
+        assert!(matches!(actual, Err(MyError::InvalidPosting(_))));
+    }
+}
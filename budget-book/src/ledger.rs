@@ -0,0 +1,693 @@
+use crate::{
+    category::UNCATEGORIZED,
+    config::Config,
+    journal::{self, AccountKind},
+    parse_currency, validate_account_name, MyError, Record,
+};
+use chrono::NaiveDate;
+use regex::Regex;
+use rust_decimal::Decimal;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::LazyLock,
+};
+use unicode_width::UnicodeWidthStr;
+
+/// 勘定名と金額をそろえる幅
+const ACCOUNT_WIDTH: usize = 36;
+
+/// 勘定の種類に対応する、ledger の最上位の勘定名
+fn top_level(kind: AccountKind) -> &'static str {
+    match kind {
+        AccountKind::Asset => "Assets",
+        AccountKind::Liability => "Liabilities",
+        AccountKind::Equity => "Equity",
+        AccountKind::Income => "Income",
+        AccountKind::Expense => "Expenses",
+    }
+}
+
+/// すべての口座の取引を、ledger 形式の仕訳帳として出力する
+// NOTE: 口座は Assets・Liabilities、分類は Income・Expenses・Equity の下の勘定にする
+// NOTE: 通貨の異なる口座間の振替は、ledger が2つの金額からレートを推定する
+pub fn export(config: &Config, accounts: &[(String, Vec<Record>)]) -> String {
+    let names: Vec<String> = accounts.iter().map(|(name, _)| name.clone()).collect();
+    let entries = journal::journal(config, accounts);
+    let kinds: HashMap<String, AccountKind> = journal::trial_balance(config, &entries, &names)
+        .into_iter()
+        .map(|balance| (balance.account, balance.kind))
+        .collect();
+    let ledger_account = |name: &str| {
+        let kind = kinds.get(name).copied().unwrap_or(AccountKind::Asset);
+        format!("{}:{}", top_level(kind), name)
+    };
+
+    // NOTE: 同じ振替番号の取引は、1つの仕訳にまとめる
+    let mut groups: Vec<Vec<(&str, &Record)>> = vec![];
+    let mut linked: HashMap<&str, usize> = HashMap::new();
+    for (account, records) in accounts {
+        for record in records {
+            let index = if record.振替番号.is_empty() {
+                groups.push(vec![]);
+                groups.len() - 1
+            } else {
+                *linked.entry(&record.振替番号).or_insert_with(|| {
+                    groups.push(vec![]);
+                    groups.len() - 1
+                })
+            };
+            groups[index].push((account, record));
+        }
+    }
+    groups.sort_by_key(|group| group[0].1.日付);
+
+    let mut output = String::new();
+    for group in &groups {
+        let (_, first) = group[0];
+        writeln!(output, "{} {}", first.日付, first.用途).unwrap();
+        if !first.振替番号.is_empty() {
+            writeln!(output, "    ; 振替番号: {}", first.振替番号).unwrap();
+        }
+        for (account, record) in group {
+            let currency = config.currency(account);
+            write_posting(&mut output, &ledger_account(account), record.金額, currency);
+            if record.用途 != first.用途 {
+                writeln!(output, "    ; 用途: {}", record.用途).unwrap();
+            }
+            if !record.取引番号.is_empty() {
+                writeln!(output, "    ; 取引番号: {}", record.取引番号).unwrap();
+            }
+            let tags: Vec<&str> = record.tags().collect();
+            if !tags.is_empty() {
+                writeln!(output, "    ; :{}:", tags.join(":")).unwrap();
+            }
+            if !record.is_transfer() {
+                let category = match record.分類.as_str() {
+                    "" => UNCATEGORIZED,
+                    category => category,
+                };
+                write_posting(
+                    &mut output,
+                    &ledger_account(category),
+                    -record.金額,
+                    currency,
+                );
+            }
+        }
+        writeln!(output).unwrap();
+    }
+    output
+}
+
+fn write_posting(output: &mut String, account: &str, amount: Decimal, currency: &str) {
+    // NOTE: ledger は2つ以上の空白で勘定名と金額を区切る
+    let padding = ACCOUNT_WIDTH.saturating_sub(account.width()).max(2);
+    writeln!(
+        output,
+        "    {}{}{:>12} {}",
+        account,
+        " ".repeat(padding),
+        amount,
+        currency
+    )
+    .unwrap();
+}
+
+/// ledger 形式の仕訳帳から読み取った取引
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Imported {
+    /// 口座ごとの取引（仕訳帳に書かれた順）
+    pub records: BTreeMap<String, Vec<Record>>,
+    /// 金額の通貨から決めた、口座の通貨
+    pub currencies: BTreeMap<String, String>,
+    /// 最上位の勘定名から決めた、勘定の種類
+    pub kinds: BTreeMap<String, AccountKind>,
+    /// 読み飛ばした仕訳についての警告
+    pub warnings: Vec<String>,
+}
+
+/// コメントに書かれたメタデータ（「; キー: 値」）とタグ（「; :タグ1:タグ2:」）
+#[derive(Debug, Default)]
+struct Meta {
+    values: BTreeMap<String, String>,
+    tags: Vec<String>,
+}
+impl Meta {
+    fn add(&mut self, comment: &str) {
+        let comment = comment.trim();
+        if let Some(tags) = comment.strip_prefix(':').and_then(|c| c.strip_suffix(':')) {
+            self.tags.extend(
+                tags.split(':')
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string),
+            );
+        } else if let Some((key, value)) = comment.split_once(':') {
+            if !key.is_empty() && !key.contains(char::is_whitespace) {
+                self.values
+                    .insert(key.to_string(), value.trim().to_string());
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PostingLine {
+    account: String,
+    /// 金額と通貨。省略された金額は None
+    amount: Option<(Decimal, Option<String>)>,
+    meta: Meta,
+}
+
+#[derive(Debug)]
+struct Transaction {
+    line: u64,
+    date: NaiveDate,
+    payee: String,
+    code: String,
+    meta: Meta,
+    postings: Vec<PostingLine>,
+}
+
+/// 行をコメントの前後に分ける
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    match line.split_once(';') {
+        Some((text, comment)) => (text, Some(comment)),
+        None => (line, None),
+    }
+}
+
+/// 仕訳の1行目（日付・状態・番号・摘要）を読み取る
+fn parse_header(line: &str, number: u64) -> Result<Transaction, String> {
+    let (text, comment) = split_comment(line);
+    let text = text.trim();
+    let (date_text, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    // NOTE: 「実際の日付=補助の日付」の形式では、実際の日付を使う
+    let date_text = date_text.split('=').next().unwrap_or_default();
+    let date = ["%Y-%m-%d", "%Y/%m/%d", "%Y.%m.%d"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(date_text, format).ok())
+        .ok_or_else(|| format!("日付「{}」が不正です", date_text))?;
+    let mut rest = rest.trim_start();
+    rest = rest.strip_prefix(['*', '!']).unwrap_or(rest).trim_start();
+    let mut code = String::new();
+    if let Some(inner) = rest.strip_prefix('(') {
+        if let Some((inner, after)) = inner.split_once(')') {
+            code = inner.trim().to_string();
+            rest = after;
+        }
+    }
+    let mut meta = Meta::default();
+    if let Some(comment) = comment {
+        meta.add(comment);
+    }
+    Ok(Transaction {
+        line: number,
+        date,
+        payee: rest.trim().to_string(),
+        code,
+        meta,
+        postings: vec![],
+    })
+}
+
+/// 勘定名と金額の区切り（2つ以上の空白、またはタブ）
+static SEPARATOR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\t|  +").unwrap());
+
+/// 「-1,234.50 JPY」「$12.50」「¥1,000」のような金額
+static AMOUNT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^(-)?\s*([^\d\s.,\-]+)?\s*(-)?\s*(\d[\d,]*(?:\.\d+)?|\.\d+)\s*([^\d\s.,\-]+)?$"#)
+        .unwrap()
+});
+
+/// 仕訳の2行目以降（勘定と金額）を読み取る
+fn parse_posting(line: &str) -> Result<PostingLine, String> {
+    let (text, comment) = split_comment(line);
+    let text = text.trim().trim_start_matches(['*', '!']).trim_start();
+    let (account, amount) = match SEPARATOR.find(text) {
+        Some(found) => (&text[..found.start()], text[found.end()..].trim()),
+        None => (text, ""),
+    };
+    // NOTE: 仮想の勘定（[勘定]・(勘定)）も、通常の勘定として扱う
+    let account = account
+        .trim()
+        .trim_start_matches(['[', '('])
+        .trim_end_matches([']', ')']);
+    if account.is_empty() {
+        return Err("勘定名がありません".to_string());
+    }
+    let mut meta = Meta::default();
+    if let Some(comment) = comment {
+        meta.add(comment);
+    }
+    Ok(PostingLine {
+        account: account.to_string(),
+        amount: parse_amount(amount)?,
+        meta,
+    })
+}
+
+/// 金額と通貨を読み取る。価格（@）と残高の表明（=）は使わない
+fn parse_amount(text: &str) -> Result<Option<(Decimal, Option<String>)>, String> {
+    let text = text.split(['@', '=']).next().unwrap_or_default().trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let invalid = || format!("金額「{}」が不正です", text);
+    let captures = AMOUNT.captures(text).ok_or_else(invalid)?;
+    let mut amount: Decimal = captures[4]
+        .replace(',', "")
+        .parse()
+        .map_err(|_| invalid())?;
+    if captures.get(1).is_some() || captures.get(3).is_some() {
+        amount = -amount;
+    }
+    let commodity = match captures.get(2).or(captures.get(5)) {
+        Some(commodity) => Some(parse_commodity(commodity.as_str())?),
+        None => None,
+    };
+    Ok(Some((amount, commodity)))
+}
+
+/// ledger の通貨の表記を、通貨コードにする
+fn parse_commodity(commodity: &str) -> Result<String, String> {
+    let commodity = commodity.trim_matches('"');
+    let code = match commodity {
+        "¥" | "￥" | "円" => "JPY",
+        "$" => "USD",
+        "€" => "EUR",
+        "£" => "GBP",
+        code => code,
+    };
+    parse_currency(code).map_err(|_| format!("通貨「{}」には対応していません", commodity))
+}
+
+/// 勘定名から、口座か分類か、その名前と種類を決める
+// NOTE: 最上位の勘定名が分からない勘定は、資産の口座とする
+fn classify(account: &str) -> Result<(bool, String, AccountKind), String> {
+    let (top, rest) = account.split_once(':').unwrap_or((account, ""));
+    let kind = match top.to_lowercase().as_str() {
+        "assets" | "asset" => Some(AccountKind::Asset),
+        "liabilities" | "liability" => Some(AccountKind::Liability),
+        "equity" => Some(AccountKind::Equity),
+        "income" | "revenue" | "revenues" => Some(AccountKind::Income),
+        "expenses" | "expense" => Some(AccountKind::Expense),
+        _ => None,
+    };
+    let (real, name, kind) = match kind {
+        Some(kind @ (AccountKind::Asset | AccountKind::Liability)) if !rest.is_empty() => {
+            (true, rest.replace(':', "-"), kind)
+        }
+        Some(kind) if !rest.is_empty() => (false, rest.to_string(), kind),
+        _ => (true, account.replace(':', "-"), AccountKind::Asset),
+    };
+    if real {
+        validate_account_name(&name).map_err(|error| error.to_string())?;
+    }
+    Ok((real, name, kind))
+}
+
+/// ledger 形式の仕訳帳を読み取る。file はエラーの表示に使う
+pub fn parse(content: &str, file: &str) -> Result<Imported, MyError> {
+    let mut transactions = vec![];
+    let mut current: Option<Transaction> = None;
+    let mut in_directive = false;
+    for (index, line) in content.lines().enumerate() {
+        let number = index as u64 + 1;
+        let error = |reason: String| MyError::Parse {
+            file: file.to_string(),
+            line: number,
+            reason,
+        };
+        if line.trim().is_empty() {
+            transactions.extend(current.take());
+            continue;
+        }
+        if !line.starts_with([' ', '\t']) {
+            transactions.extend(current.take());
+            in_directive = false;
+            match line.chars().next() {
+                Some(c) if c.is_ascii_digit() => {
+                    current = Some(parse_header(line, number).map_err(error)?)
+                }
+                Some(';' | '#' | '%' | '|' | '*') => {}
+                // NOTE: account・commodity・P などの指示は読み飛ばす
+                _ => in_directive = true,
+            }
+            continue;
+        }
+        let Some(transaction) = current.as_mut().filter(|_| !in_directive) else {
+            continue;
+        };
+        let text = line.trim();
+        if let Some(comment) = text.strip_prefix(';') {
+            match transaction.postings.last_mut() {
+                Some(posting) => posting.meta.add(comment),
+                None => transaction.meta.add(comment),
+            }
+            continue;
+        }
+        transaction
+            .postings
+            .push(parse_posting(text).map_err(error)?);
+    }
+    transactions.extend(current);
+
+    let mut imported = Imported::default();
+    for transaction in transactions {
+        let line = transaction.line;
+        let error = |reason: String| MyError::Parse {
+            file: file.to_string(),
+            line,
+            reason,
+        };
+        add_transaction(&mut imported, transaction).map_err(error)?;
+    }
+    Ok(imported)
+}
+
+/// 1つの仕訳を、口座の取引にして追加する
+fn add_transaction(imported: &mut Imported, transaction: Transaction) -> Result<(), String> {
+    let mut commodities: Vec<&String> = transaction
+        .postings
+        .iter()
+        .filter_map(|posting| posting.amount.as_ref()?.1.as_ref())
+        .collect();
+    commodities.sort();
+    commodities.dedup();
+    let commodity = commodities.first().map(|commodity| commodity.to_string());
+    let single = commodities.len() <= 1;
+    let total: Decimal = transaction
+        .postings
+        .iter()
+        .filter_map(|posting| posting.amount.as_ref().map(|(amount, _)| amount))
+        .sum();
+
+    // NOTE: 金額を省略した勘定には、ほかの勘定との差額を計上する
+    let mut postings = vec![];
+    let mut elided = 0;
+    for posting in &transaction.postings {
+        let (real, name, kind) = classify(&posting.account)?;
+        let (amount, commodity) = match &posting.amount {
+            Some((amount, own)) => (*amount, own.clone().or(commodity.clone())),
+            None if !single => return Err("通貨が異なる仕訳では金額を省略できません".to_string()),
+            None => {
+                elided += 1;
+                (-total, commodity.clone())
+            }
+        };
+        postings.push((real, name, kind, amount, commodity, &posting.meta));
+    }
+    if elided > 1 {
+        return Err("金額を省略できる勘定は1つまでです".to_string());
+    }
+    if single && elided == 0 && !total.is_zero() {
+        return Err(format!("借方と貸方が一致しません（差額：{}）", total));
+    }
+    if !postings.iter().any(|(real, ..)| *real) {
+        imported.warnings.push(format!(
+            "{} 行目の仕訳「{}」には資産・負債の勘定がないため、読み飛ばしました",
+            transaction.line, transaction.payee
+        ));
+        return Ok(());
+    }
+
+    // NOTE: 口座の直後に同じ金額の分類が続く場合は、分類のある1件の取引とする（書き出した仕訳帳はこの形になる）
+    let mut parts: Vec<(String, Decimal, String, Option<&Meta>)> = vec![];
+    let mut real_rest = vec![];
+    let mut nominal_rest = vec![];
+    let mut i = 0;
+    while i < postings.len() {
+        let (real, name, _, amount, commodity, meta) = &postings[i];
+        match postings.get(i + 1) {
+            Some((false, category, _, next, next_commodity, _))
+                if *real && *next == -*amount && next_commodity == commodity =>
+            {
+                parts.push((name.clone(), *amount, category.clone(), Some(meta)));
+                i += 2;
+                continue;
+            }
+            _ if *real => real_rest.push((name.clone(), *amount)),
+            _ => nominal_rest.push((name.clone(), *amount)),
+        }
+        i += 1;
+    }
+    // NOTE: 口座をすべて分類と組にすると残りの分類を計上する口座がなくなるので、最後の組を分けて戻す
+    if real_rest.is_empty() && !nominal_rest.is_empty() {
+        if let Some((account, amount, category, _)) = parts.pop() {
+            real_rest.push((account, amount));
+            nominal_rest.insert(0, (category, -amount));
+        }
+    }
+    if !single && !nominal_rest.is_empty() {
+        return Err("通貨が異なる仕訳は、口座どうしの振替だけ読み取れます".to_string());
+    }
+    let split =
+        journal::split_postings(&real_rest, &nominal_rest).map_err(|error| error.to_string())?;
+    for (account, amount, category) in split {
+        let meta = postings
+            .iter()
+            .find(|(real, name, ..)| *real && *name == account)
+            .map(|(.., meta)| *meta);
+        parts.push((account, amount, category, meta));
+    }
+
+    let id = if parts.len() > 1 {
+        transaction
+            .meta
+            .values
+            .get("振替番号")
+            .cloned()
+            .unwrap_or_else(|| {
+                format!(
+                    "L{}-{}",
+                    transaction.date.format("%Y%m%d"),
+                    transaction.line
+                )
+            })
+    } else {
+        String::new()
+    };
+    for (account, amount, category, meta) in parts {
+        let value = |key: &str| {
+            meta.and_then(|meta| meta.values.get(key))
+                .or(transaction.meta.values.get(key))
+                .cloned()
+        };
+        let tags: Vec<&str> = transaction
+            .meta
+            .tags
+            .iter()
+            .chain(meta.map(|meta| &meta.tags).into_iter().flatten())
+            .map(String::as_str)
+            .collect();
+        let record = Record {
+            日付: transaction.date,
+            用途: value("用途").unwrap_or_else(|| transaction.payee.clone()),
+            金額: amount,
+            分類: match category.as_str() {
+                UNCATEGORIZED => String::new(),
+                _ => category,
+            },
+            タグ: tags.join(";"),
+            振替番号: id.clone(),
+            取引番号: value("取引番号").unwrap_or_else(|| transaction.code.clone()),
+        };
+        imported.records.entry(account).or_default().push(record);
+    }
+    for (real, name, kind, _, commodity, _) in &postings {
+        if *real {
+            if let Some(commodity) = commodity {
+                let currency = imported
+                    .currencies
+                    .entry(name.clone())
+                    .or_insert_with(|| commodity.clone());
+                if currency != commodity {
+                    return Err(format!("口座「{}」に通貨の異なる金額があります", name));
+                }
+            }
+        }
+        if *kind != AccountKind::Asset && name != UNCATEGORIZED {
+            imported.kinds.insert(name.clone(), *kind);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> (Config, Vec<(String, Vec<Record>)>) {
+        let mut config = Config::default();
+        config
+            .kinds
+            .insert("card".to_string(), AccountKind::Liability);
+        config
+            .currencies
+            .insert("travel".to_string(), "USD".to_string());
+        let transfer = |usage: &str, amount: i32| Record {
+            振替番号: "T1".to_string(),
+            ..Record::test("2024-01-27", usage, amount)
+        };
+        let accounts = vec![
+            (
+                "bank".to_string(),
+                vec![
+                    Record {
+                        取引番号: "A001".to_string(),
+                        ..Record::test_categorized("2024-01-25", "給与", 200000, "給与")
+                    },
+                    transfer("振替（travelへ）", -15000),
+                ],
+            ),
+            (
+                "card".to_string(),
+                vec![Record {
+                    タグ: "旅行;家族".to_string(),
+                    ..Record::test("2024-01-10", "スーパー", -5000)
+                }],
+            ),
+            (
+                "travel".to_string(),
+                vec![
+                    transfer("振替（bankから）", 100),
+                    Record::test_categorized(
+                        "2024-01-28",
+                        "ホテル",
+                        Decimal::new(-8050, 2),
+                        "宿泊費",
+                    ),
+                ],
+            ),
+        ];
+        (config, accounts)
+    }
+
+    #[test]
+    fn test_export() {
+        let (config, accounts) = sample();
+
+        let actual = export(&config, &accounts);
+
+        assert_eq!(
+            "2024-01-10 スーパー\n\
+             \x20   Liabilities:card                           -5000 JPY\n\
+             \x20   ; :旅行:家族:\n\
+             \x20   Expenses:未分類                             5000 JPY\n\
+             \n\
+             2024-01-25 給与\n\
+             \x20   Assets:bank                               200000 JPY\n\
+             \x20   ; 取引番号: A001\n\
+             \x20   Income:給与                              -200000 JPY\n\
+             \n\
+             2024-01-27 振替（travelへ）\n\
+             \x20   ; 振替番号: T1\n\
+             \x20   Assets:bank                               -15000 JPY\n\
+             \x20   Assets:travel                                100 USD\n\
+             \x20   ; 用途: 振替（bankから）\n\
+             \n\
+             2024-01-28 ホテル\n\
+             \x20   Assets:travel                             -80.50 USD\n\
+             \x20   Expenses:宿泊費                            80.50 USD\n\
+             \n",
+            actual
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (config, accounts) = sample();
+
+        let actual = parse(&export(&config, &accounts), "journal.ledger").unwrap();
+
+        assert_eq!(
+            accounts.into_iter().collect::<BTreeMap<_, _>>(),
+            actual.records
+        );
+        assert_eq!(Some(&"USD".to_string()), actual.currencies.get("travel"));
+        assert_eq!(Some(&AccountKind::Liability), actual.kinds.get("card"));
+        assert!(actual.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_parse() {
+        let content = "; ledger-cli で書いた仕訳帳\n\
+                       account Assets:Checking\n\
+                       \x20   note 普段使いの口座\n\
+                       \n\
+                       2024/01/05=2024/01/06 * (1001) 食料品店  ; :食費:\n\
+                       \x20   Expenses:Food:Groceries       $12.50\n\
+                       \x20   Expenses:Household            $7.50 @ 150 JPY\n\
+                       \x20   Assets:Checking\n\
+                       \n\
+                       2024-01-06 ! 開始残高\n\
+                       \x20   Equity:Opening Balances      -1,000 USD\n\
+                       \x20   Assets:Checking              1,000 USD = 1,000 USD\n\
+                       \n\
+                       2024-01-07 予算の振り分け\n\
+                       \x20   Expenses:Food                 10 USD\n\
+                       \x20   Income:Other                 -10 USD\n";
+
+        let actual = parse(content, "journal.ledger").unwrap();
+
+        let checking = &actual.records["Checking"];
+        assert_eq!(
+            vec![
+                ("Food:Groceries", "-12.50".to_string(), "1001", "食費"),
+                ("Household", "-7.50".to_string(), "1001", "食費"),
+                ("Opening Balances", "1000".to_string(), "", ""),
+            ],
+            checking
+                .iter()
+                .map(|record| (
+                    record.分類.as_str(),
+                    record.金額.to_string(),
+                    record.取引番号.as_str(),
+                    record.タグ.as_str()
+                ))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(checking[0].振替番号, checking[1].振替番号);
+        assert_eq!(Some(&"USD".to_string()), actual.currencies.get("Checking"));
+        assert_eq!(
+            Some(&AccountKind::Equity),
+            actual.kinds.get("Opening Balances")
+        );
+        assert_eq!(1, actual.warnings.len());
+    }
+
+    #[test]
+    fn test_parse_unbalanced() {
+        let content = "2024-01-05 食料品店\n\
+                       \x20   Expenses:Food   1000 JPY\n\
+                       \x20   Assets:Cash    -900 JPY\n";
+
+        let actual = parse(content, "journal.ledger");
+
+        assert!(matches!(actual, Err(MyError::Parse { line: 1, .. })));
+    }
+
+    #[test]
+    fn test_parse_every_account_paired() {
+        let content = "2024-01-05 買い物\n\
+                       \x20   Assets:Cash      -100 JPY\n\
+                       \x20   Expenses:Food     100 JPY\n\
+                       \x20   Expenses:Other     50 JPY\n\
+                       \x20   Income:X          -50 JPY\n";
+
+        let actual = parse(content, "journal.ledger").unwrap();
+
+        assert_eq!(
+            vec![
+                ("Food", "-100".to_string()),
+                ("Other", "-50".to_string()),
+                ("X", "50".to_string()),
+            ],
+            actual.records["Cash"]
+                .iter()
+                .map(|record| (record.分類.as_str(), record.金額.to_string()))
+                .collect::<Vec<_>>()
+        );
+    }
+}
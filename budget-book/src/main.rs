@@ -1,12 +1,407 @@
-use chrono::NaiveDate;
-use clap::{Args, Parser, Subcommand};
-use csv::{Reader, Writer, WriterBuilder};
+use category::Rules;
+use chrono::{Datelike, Local, NaiveDate, Weekday};
+use clap::{ArgGroup, Args, Parser, Subcommand};
+use config::Config;
+use csv::ReaderBuilder;
+use currency::Rates;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs::OpenOptions};
+use sqlite::SqliteStore;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+use store::{CsvStore, Store};
+use unicode_width::UnicodeWidthStr;
+
+mod category;
+mod chart;
+mod config;
+mod currency;
+mod duplicate;
+mod import;
+mod journal;
+mod ledger;
+mod recurring;
+mod report;
+mod sqlite;
+mod store;
+
+#[derive(thiserror::Error, Debug)]
+enum MyError {
+    #[error("ファイルの読み書きに失敗しました：{0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("CSV の読み書きに失敗しました：{0}")]
+    Csv(#[from] csv::Error),
+
+    #[error("JSON の読み書きに失敗しました：{0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("データベースの読み書きに失敗しました：{0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("{file} の {line} 行目を読み取れません：{reason}")]
+    Parse {
+        file: String,
+        line: u64,
+        reason: String,
+    },
+
+    #[error("口座「{0}」が見つかりません（new サブコマンドで作成してください）")]
+    AccountNotFound(String),
+
+    #[error("口座「{0}」は既に存在します（上書きする場合は --force を指定してください）")]
+    AccountAlreadyExists(String),
+
+    #[error("口座名「{name}」は使えません：{reason}")]
+    InvalidAccountName { name: String, reason: String },
+
+    #[error("正規表現「{pattern}」が不正です：{reason}")]
+    InvalidPattern { pattern: String, reason: String },
+
+    #[error("月「{0}」が不正です（YYYY-MM の形式で指定してください）")]
+    InvalidMonth(String),
+
+    #[error("振替元と振替先に同じ口座「{0}」は指定できません")]
+    SameAccount(String),
+
+    #[error("プロファイル「{0}」は登録されていません")]
+    UnknownProfile(String),
+
+    #[error("プロファイルの設定が不正です：{0}")]
+    InvalidProfile(String),
+
+    #[error("定期取引「{0}」は登録されていません")]
+    UnknownRecurring(String),
+
+    #[error("ルール {0} は存在しません（rule list で番号を確認してください）")]
+    UnknownRule(usize),
+
+    #[error("「{0}」の予算は設定されていません")]
+    UnknownBudget(String),
+
+    #[error("定期取引の日付「{0}」が不正です")]
+    InvalidSchedule(String),
+
+    #[error("既に {0} に移行しています")]
+    AlreadyMigrated(String),
+
+    #[error("仕訳が不正です：{0}")]
+    InvalidPosting(String),
+
+    #[error("借方と貸方が一致しない仕訳が {0} 件あります")]
+    UnbalancedJournal(usize),
+
+    #[error("通貨コード「{0}」が不正です（USD のような3文字の英字で指定してください）")]
+    InvalidCurrency(String),
+
+    #[error("金額「{amount}」が不正です：{reason}")]
+    InvalidAmount { amount: Decimal, reason: String },
+
+    #[error("{date} 時点の {from} から {to} への為替レートが登録されていません")]
+    MissingRate {
+        from: String,
+        to: String,
+        date: NaiveDate,
+    },
+}
+impl MyError {
+    /// エラーの種類ごとの終了コード
+    fn exit_code(&self) -> u8 {
+        match self {
+            MyError::Io(_) | MyError::Json(_) | MyError::Sqlite(_) => 1,
+            MyError::AccountNotFound(_) => 2,
+            MyError::Csv(_) | MyError::Parse { .. } | MyError::UnbalancedJournal(_) => 3,
+            MyError::AccountAlreadyExists(_) | MyError::AlreadyMigrated(_) => 4,
+            MyError::InvalidAccountName { .. }
+            | MyError::InvalidPattern { .. }
+            | MyError::InvalidMonth(_)
+            | MyError::SameAccount(_)
+            | MyError::UnknownProfile(_)
+            | MyError::InvalidProfile(_)
+            | MyError::UnknownRecurring(_)
+            | MyError::UnknownRule(_)
+            | MyError::UnknownBudget(_)
+            | MyError::InvalidSchedule(_)
+            | MyError::InvalidPosting(_)
+            | MyError::InvalidCurrency(_)
+            | MyError::InvalidAmount { .. } => 5,
+            MyError::MissingRate { .. } => 6,
+        }
+    }
+}
+
+/// Windows でファイル名として使えない予約語
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// 口座名がファイル名として安全に使えるかを確認する
+fn validate_account_name(account_name: &str) -> Result<(), MyError> {
+    let invalid = |reason: &str| MyError::InvalidAccountName {
+        name: account_name.to_string(),
+        reason: reason.to_string(),
+    };
+    if account_name.trim().is_empty() {
+        return Err(invalid("空の名前は指定できません"));
+    }
+    // NOTE: データディレクトリの外を指したり、隠しファイルになったりする名前を防ぐ
+    if account_name.contains(['/', '\\']) {
+        return Err(invalid("パス区切り文字を含められません"));
+    }
+    if account_name.starts_with('.') {
+        return Err(invalid("先頭に「.」は使えません"));
+    }
+    if account_name
+        .chars()
+        .any(|c| c.is_control() || ":*?\"<>|".contains(c))
+    {
+        return Err(invalid("ファイル名に使えない文字を含んでいます"));
+    }
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(account_name))
+    {
+        return Err(invalid("予約されたファイル名です"));
+    }
+    // NOTE: 為替レートのファイルと同じ名前の口座は作れない
+    if Path::new(currency::RATES_FILE_NAME).file_stem() == Some(account_name.as_ref()) {
+        return Err(invalid("為替レートのファイルと同じ名前です"));
+    }
+    Ok(())
+}
+
+/// 口座と同じ形式の CSV ファイルから取引をすべて読み取る
+fn read_records(path: &Path) -> Result<Vec<Record>, MyError> {
+    // NOTE: 分類とタグの列がない、古い形式の行も読み取れるようにする
+    let mut reader = ReaderBuilder::new().flexible(true).from_path(path)?;
+    let headers = reader.headers()?.clone();
+    reader
+        .records()
+        // Reader は先頭行をヘッダーとして扱うので、2行目以降が対象となる
+        .map(|result| {
+            let mut row = result?;
+            // NOTE: 列が足りない行は、足りない列を空として扱う
+            while row.len() < headers.len() {
+                row.push_field("");
+            }
+            row.deserialize(Some(&headers))
+        })
+        .map(|result| {
+            result.map_err(|error: csv::Error| MyError::Parse {
+                file: path.to_string_lossy().to_string(),
+                line: error.position().map_or(0, |position| position.line()),
+                reason: match error.kind() {
+                    csv::ErrorKind::Deserialize { err, .. } => err.to_string(),
+                    _ => error.to_string(),
+                },
+            })
+        })
+        .collect()
+}
+
+/// 口座の保存先と、設定・為替レートのファイルを置くデータディレクトリ
+struct Book {
+    dir: PathBuf,
+    store: Box<dyn Store>,
+}
+impl Book {
+    /// データディレクトリを開く。SQLite のデータベースがあればそれを使い、なければ口座ごとの CSV を使う
+    fn open(dir: impl Into<PathBuf>) -> Result<Self, MyError> {
+        let dir = dir.into();
+        let database = dir.join(sqlite::DATABASE_FILE_NAME);
+        let store: Box<dyn Store> = if database.exists() {
+            Box::new(SqliteStore::open(&database)?)
+        } else {
+            Box::new(CsvStore::new(&dir))
+        };
+        Ok(Book { dir, store })
+    }
+
+    /// 口座を作る。既存の口座は force が指定された場合のみ空にする
+    fn create_account(&self, account_name: &str, force: bool) -> Result<(), MyError> {
+        self.store.create_account(account_name, force)
+    }
+
+    /// 口座の取引をすべて読み取る
+    fn read_records(&self, account_name: &str) -> Result<Vec<Record>, MyError> {
+        self.store.read_records(account_name)
+    }
+
+    /// 口座の名前（名前順）
+    fn accounts(&self) -> Result<Vec<String>, MyError> {
+        self.store.accounts()
+    }
+
+    /// 口座の残高
+    fn balance(&self, account_name: &str) -> Result<Decimal, MyError> {
+        Ok(self
+            .read_records(account_name)?
+            .iter()
+            .map(|record| record.金額)
+            .sum())
+    }
+
+    /// 口座の末尾に取引を書き込む
+    fn append_records(&self, account_name: &str, records: &[Record]) -> Result<(), MyError> {
+        self.store.append_records(account_name, records)
+    }
+
+    /// 口座間で振り替える。出金側と入金側の取引には同じ振替番号を付ける
+    fn transfer(
+        &self,
+        from: &str,
+        to: &str,
+        date: NaiveDate,
+        amount: Decimal,
+        usage: Option<&str>,
+    ) -> Result<(), MyError> {
+        if from == to {
+            return Err(MyError::SameAccount(from.to_string()));
+        }
+        let config = self.load_config()?;
+        let (from_currency, to_currency) = (config.currency(from), config.currency(to));
+        validate_amount(amount, from_currency)?;
+        // NOTE: 通貨が異なる口座への振替では、振替日のレートで換算した金額を入金する
+        let to_amount = self
+            .load_rates()?
+            .convert(amount, from_currency, to_currency, date)?;
+        let transfer_id = Local::now().format("T%Y%m%d%H%M%S%f").to_string();
+        let record = |usage: String, amount: Decimal| Record {
+            日付: date,
+            用途: usage,
+            金額: amount,
+            分類: String::new(),
+            タグ: String::new(),
+            振替番号: transfer_id.clone(),
+            取引番号: String::new(),
+        };
+        let withdrawal = record(
+            usage.map_or_else(|| format!("振替（{}へ）", to), str::to_string),
+            -amount,
+        );
+        let deposit = record(
+            usage.map_or_else(|| format!("振替（{}から）", from), str::to_string),
+            to_amount,
+        );
+        self.store
+            .append_linked(&[(from, withdrawal), (to, deposit)])
+    }
+
+    /// 複数の口座・分類にまたがる仕訳を記帳する。分類への金額は、口座の取引の分類として記録する
+    fn post_entry(
+        &self,
+        date: NaiveDate,
+        description: &str,
+        postings: &[(String, Decimal)],
+    ) -> Result<(), MyError> {
+        let difference: Decimal = postings.iter().map(|(_, amount)| amount).sum();
+        if !difference.is_zero() {
+            return Err(MyError::InvalidPosting(format!(
+                "借方と貸方の合計が一致しません（差額：{}）",
+                difference
+            )));
+        }
+        let config = self.load_config()?;
+        let accounts = self.accounts()?;
+        let (real, nominal): (Vec<_>, Vec<_>) = postings
+            .iter()
+            .cloned()
+            .partition(|(account, _)| accounts.contains(account));
+        let Some((first, _)) = real.first() else {
+            return Err(MyError::InvalidPosting(
+                "口座の勘定を1つ以上指定してください".to_string(),
+            ));
+        };
+        // NOTE: 分類への金額は口座の通貨で記録するので、通貨の異なる口座を1つの仕訳に含められない
+        let currency = config.currency(first);
+        if let Some((account, _)) = real
+            .iter()
+            .find(|(account, _)| config.currency(account) != currency)
+        {
+            return Err(MyError::InvalidPosting(format!(
+                "通貨の異なる口座「{}」と「{}」は1つの仕訳にできません（transfer を使ってください）",
+                first, account
+            )));
+        }
+        for (_, amount) in postings {
+            validate_amount(amount.abs(), currency)?;
+        }
+        let parts = journal::split_postings(&real, &nominal)?;
+        let id = if parts.len() > 1 {
+            Local::now().format("J%Y%m%d%H%M%S%f").to_string()
+        } else {
+            String::new()
+        };
+        let entries: Vec<(&str, Record)> = parts
+            .iter()
+            .map(|(account, amount, category)| {
+                let record = Record {
+                    日付: date,
+                    用途: description.to_string(),
+                    金額: *amount,
+                    分類: category.clone(),
+                    タグ: String::new(),
+                    振替番号: id.clone(),
+                    取引番号: String::new(),
+                };
+                (account.as_str(), record)
+            })
+            .collect();
+        self.store.append_linked(&entries)
+    }
+
+    /// すべての口座の取引から、基準通貨での仕訳帳を作る。口座の名前も返す
+    fn journal(&self) -> Result<(Vec<String>, Vec<journal::Entry>), MyError> {
+        let config = self.load_config()?;
+        let rates = self.load_rates()?;
+        let names = self.accounts()?;
+        let mut accounts = vec![];
+        for name in &names {
+            let mut records = self.read_records(name)?;
+            rates.convert_records(&mut records, config.currency(name), config.base_currency())?;
+            accounts.push((name.clone(), records));
+        }
+        Ok((names, journal::journal(&config, &accounts)))
+    }
+
+    fn config_path(&self) -> PathBuf {
+        self.dir.join(config::CONFIG_FILE_NAME)
+    }
+
+    fn load_config(&self) -> Result<Config, MyError> {
+        Config::load(&self.config_path())
+    }
+
+    fn save_config(&self, config: &Config) -> Result<(), MyError> {
+        config.save(&self.config_path())
+    }
+
+    fn rates_path(&self) -> PathBuf {
+        self.dir.join(currency::RATES_FILE_NAME)
+    }
+
+    fn load_rates(&self) -> Result<Rates, MyError> {
+        Rates::load(&self.rates_path())
+    }
+
+    fn save_rates(&self, rates: &Rates) -> Result<(), MyError> {
+        rates.save(&self.rates_path())
+    }
+}
 
 #[derive(Parser)]
 #[clap(version = "1.0")]
 struct App {
+    /// 口座のファイルを置くディレクトリ
+    #[clap(long, global = true, env = "BUDGET_BOOK_DIR", default_value = ".")]
+    data_dir: PathBuf,
+
     #[clap(subcommand)]
     command: Command,
 }
@@ -23,21 +418,65 @@ enum Command {
     Import(ImportArgs),
     /// レポートを出力する
     Report(ReportArgs),
+    /// 口座の残高を表示する
+    Balance(BalanceArgs),
+    /// 口座の取引を残高付きで一覧表示する
+    Ledger(LedgerArgs),
+    /// 口座と残高を一覧表示する
+    Accounts(AccountsArgs),
+    /// 口座間で振り替える
+    Transfer(TransferArgs),
+    /// インポートのプロファイルを管理する
+    Profile(ProfileArgs),
+    /// 用途から分類を決めるルールを管理する
+    Rule(RuleArgs),
+    /// 分類ごとの予算を管理する
+    Budget(BudgetArgs),
+    /// 為替レートを管理する
+    Rate(RateArgs),
+    /// 家賃や給与のような定期的な取引を管理する
+    Recurring(RecurringArgs),
+    /// 定期的な取引のうち、記帳する日が来たものを記帳する
+    PostDue(PostDueArgs),
+    /// 口座ごとの CSV ファイルを SQLite のデータベースに移行する
+    Migrate(MigrateArgs),
+    /// 口座を CSV ファイルに書き出す
+    Export(ExportArgs),
+    /// 複式簿記の仕訳帳、試算表、財務諸表を扱う
+    Journal(JournalArgs),
+    /// すべての口座を ledger 形式の仕訳帳に書き出す
+    ExportLedger(ExportLedgerArgs),
+    /// ledger 形式の仕訳帳から取引を読み込む
+    ImportLedger(ImportLedgerArgs),
 }
 
 #[derive(Args)]
 struct NewArgs {
     account_name: String,
+    /// 既存の口座を空にして作り直す
+    #[clap(long)]
+    force: bool,
+    /// 口座の通貨（省略した場合は基準通貨）
+    #[clap(long)]
+    currency: Option<String>,
 }
 impl NewArgs {
     /// new サブコマンドの本体処理
-    fn run(&self) {
-        let file_name = format!("{}.csv", self.account_name);
-        let mut writer = Writer::from_path(file_name).unwrap();
-        writer
-            .write_record(["日付", "用途", "金額"]) // ヘッダーを書き込む
-            .unwrap();
-        writer.flush().unwrap();
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let currency = self.currency.as_deref().map(parse_currency).transpose()?;
+        book.create_account(&self.account_name, self.force)?;
+        let mut config = book.load_config()?;
+        // NOTE: --force で作り直した口座は、以前の通貨の設定を引き継がない
+        let previous = match currency {
+            Some(currency) => config
+                .currencies
+                .insert(self.account_name.clone(), currency),
+            None => config.currencies.remove(&self.account_name),
+        };
+        if previous != config.currencies.get(&self.account_name).cloned() {
+            book.save_config(&config)?;
+        }
+        Ok(())
     }
 }
 
@@ -46,23 +485,81 @@ struct DepositArgs {
     account_name: String,
     date: NaiveDate,
     usage: String,
-    amount: u32,
+    amount: Decimal,
+    /// 分類（省略した場合はルールで決める）
+    #[clap(long)]
+    category: Option<String>,
+    /// タグ（複数指定できる）
+    #[clap(long = "tag")]
+    tags: Vec<String>,
 }
 impl DepositArgs {
     /// deposit サブコマンドの本体処理
-    fn run(&self) {
-        let file_name = format!("{}.csv", self.account_name);
-        let open_option = OpenOptions::new().append(true).open(file_name).unwrap();
-        let mut writer = Writer::from_writer(open_option);
-        writer
-            .write_record([
-                self.date.format("%Y-%m-%d").to_string(),
-                self.usage.to_string(),
-                self.amount.to_string(),
-            ])
-            .unwrap();
-        writer.flush().unwrap();
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let config = book.load_config()?;
+        let record = new_record(
+            &config,
+            &config.compile_rules()?,
+            &self.account_name,
+            self.date,
+            &self.usage,
+            self.amount,
+            &self.category,
+            &self.tags,
+        )?;
+        book.append_records(&self.account_name, &[record])
+    }
+}
+
+/// 入金・出金の取引を作る
+#[allow(clippy::too_many_arguments)]
+fn new_record(
+    config: &Config,
+    rules: &Rules,
+    account_name: &str,
+    date: NaiveDate,
+    usage: &str,
+    amount: Decimal,
+    category: &Option<String>,
+    tags: &[String],
+) -> Result<Record, MyError> {
+    // NOTE: 出金は負の値で渡されるので、絶対値で確認する
+    validate_amount(amount.abs(), config.currency(account_name))?;
+    let mut record = Record {
+        日付: date,
+        用途: usage.to_string(),
+        金額: amount,
+        分類: category.clone().unwrap_or_default(),
+        タグ: tags.join(";"),
+        振替番号: String::new(),
+        取引番号: String::new(),
+    };
+    rules.apply(&mut record);
+    Ok(record)
+}
+
+/// 入金・出金・振替の金額が、口座の通貨で扱える正の値かを確認する
+fn validate_amount(amount: Decimal, currency: &str) -> Result<(), MyError> {
+    let invalid = |reason: String| MyError::InvalidAmount { amount, reason };
+    if amount <= Decimal::ZERO {
+        return Err(invalid("正の値を指定してください".to_string()));
+    }
+    let places = currency::decimal_places(currency);
+    if amount.normalize().scale() > places {
+        return Err(invalid(format!(
+            "{} は小数点以下 {} 桁までです",
+            currency, places
+        )));
+    }
+    Ok(())
+}
+
+/// 通貨コードを大文字にそろえて読み取る
+fn parse_currency(currency: &str) -> Result<String, MyError> {
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(MyError::InvalidCurrency(currency.to_string()));
     }
+    Ok(currency.to_ascii_uppercase())
 }
 
 #[derive(Args)]
@@ -70,22 +567,29 @@ struct WithdrawArgs {
     account_name: String,
     date: NaiveDate,
     usage: String,
-    amount: u32,
+    amount: Decimal,
+    /// 分類（省略した場合はルールで決める）
+    #[clap(long)]
+    category: Option<String>,
+    /// タグ（複数指定できる）
+    #[clap(long = "tag")]
+    tags: Vec<String>,
 }
 impl WithdrawArgs {
     /// withdraw サブコマンドの本体処理
-    fn run(&self) {
-        let file_name = format!("{}.csv", self.account_name);
-        let open_option = OpenOptions::new().append(true).open(file_name).unwrap();
-        let mut writer = Writer::from_writer(open_option);
-        writer
-            .write_record([
-                self.date.format("%Y-%m-%d").to_string(),
-                self.usage.to_string(),
-                format!("-{}", self.amount), // deposit との差分はココだけ
-            ])
-            .unwrap();
-        writer.flush().unwrap();
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let config = book.load_config()?;
+        let record = new_record(
+            &config,
+            &config.compile_rules()?,
+            &self.account_name,
+            self.date,
+            &self.usage,
+            -self.amount, // deposit との差分はココだけ
+            &self.category,
+            &self.tags,
+        )?;
+        book.append_records(&self.account_name, &[record])
     }
 }
 
@@ -93,64 +597,1696 @@ impl WithdrawArgs {
 struct ImportArgs {
     src_file_name: String,
     dst_account_name: String,
+    /// 読み取りに使うプロファイルの名前（省略した場合は口座と同じ形式とみなす）
+    #[clap(long)]
+    profile: Option<String>,
+    /// 取り込む取引を表示するだけで、口座には書き込まない
+    #[clap(long)]
+    dry_run: bool,
+    /// 登録済みの取引との重複の判定方法
+    #[clap(long, value_enum, default_value = "auto")]
+    key: duplicate::DuplicateKey,
+    /// 重複した取引の扱い
+    #[clap(long, value_enum, default_value = "skip")]
+    on_duplicate: duplicate::OnDuplicate,
 }
 impl ImportArgs {
     /// import サブコマンドの本体処理
-    fn run(&self) {
-        let dst_file_name = format!("{}.csv", self.dst_account_name);
-        let open_option = OpenOptions::new().append(true).open(dst_file_name).unwrap();
-        let mut writer = WriterBuilder::new()
-            .has_headers(false) // 1行目のヘッダーをスキップ
-            .from_writer(open_option);
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let config = book.load_config()?;
+        let src = Path::new(&self.src_file_name);
+        // NOTE: 途中の行で失敗した場合に一部だけが取り込まれないよう、すべての行を読み取ってから書き込む
+        let mut records = match &self.profile {
+            Some(name) => config
+                .profiles
+                .get(name)
+                .ok_or_else(|| MyError::UnknownProfile(name.clone()))?
+                .read(src)?,
+            // CSV の各行が Record 型として読み取れることを想定
+            None => read_records(src)?,
+        };
+        let rules = config.compile_rules()?;
+        for record in records.iter_mut() {
+            rules.apply(record);
+        }
+        let existing = book.read_records(&self.dst_account_name)?;
+        let classified = duplicate::classify(&existing, records, self.key);
+        let summary = classified.render_summary(self.on_duplicate);
+        let records = classified.into_records(self.on_duplicate);
+        if self.dry_run {
+            print!("{}", import::render_preview(&records));
+            print!("{}", summary);
+            println!(
+                "{} 件の取引を「{}」に取り込みます（--dry-run のため書き込みません）",
+                records.len(),
+                self.dst_account_name
+            );
+            return Ok(());
+        }
+        book.append_records(&self.dst_account_name, &records)?;
+        print!("{}", summary);
+        Ok(())
+    }
+}
 
-        let mut reader = Reader::from_path(&self.src_file_name).unwrap();
-        for result in reader.deserialize() {
-            // Reader は先頭行をヘッダーとして扱うので、ループは2行目以降について実行される
+#[derive(Args)]
+struct ProfileArgs {
+    #[clap(subcommand)]
+    command: ProfileCommand,
+}
 
-            // CSV の各行が Record 型として読み取れることを想定
-            let record: Record = result.unwrap();
-            writer.serialize(record).unwrap();
+#[derive(Subcommand)]
+enum ProfileCommand {
+    /// JSON ファイルに書いたプロファイルを登録する（同じ名前のプロファイルは置き換える）
+    Add { name: String, file: PathBuf },
+    /// プロファイルを一覧表示する
+    List,
+    /// プロファイルを削除する
+    Remove { name: String },
+}
+impl ProfileArgs {
+    /// profile サブコマンドの本体処理
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let mut config = book.load_config()?;
+        match &self.command {
+            ProfileCommand::Add { name, file } => {
+                let profile: import::Profile = serde_json::from_str(&fs::read_to_string(file)?)?;
+                profile.validate()?;
+                config.profiles.insert(name.clone(), profile);
+                book.save_config(&config)?;
+            }
+            ProfileCommand::List => {
+                for (name, profile) in &config.profiles {
+                    println!("{}: {}", name, serde_json::to_string(profile)?);
+                }
+            }
+            ProfileCommand::Remove { name } => {
+                if config.profiles.remove(name).is_none() {
+                    return Err(MyError::UnknownProfile(name.clone()));
+                }
+                book.save_config(&config)?;
+            }
         }
+        Ok(())
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 struct Record {
     日付: NaiveDate,
     用途: String,
-    金額: i32,
+    金額: Decimal,
+    /// 空の場合は未分類
+    #[serde(default)]
+    分類: String,
+    /// 「;」区切りのタグ
+    #[serde(default)]
+    タグ: String,
+    /// 振替や複数の口座にまたがる仕訳の場合は、同じ仕訳の取引で共通の番号
+    #[serde(default)]
+    振替番号: String,
+    /// 銀行の明細から取り込んだ場合の、銀行が付けた取引番号
+    #[serde(default)]
+    取引番号: String,
+}
+impl Record {
+    /// 口座間の振替。仕訳の一部でも、分類のある取引は収入・支出として扱う
+    fn is_transfer(&self) -> bool {
+        !self.振替番号.is_empty() && self.分類.is_empty()
+    }
+
+    fn tags(&self) -> impl Iterator<Item = &str> {
+        self.タグ
+            .split(';')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+    }
+}
+#[cfg(test)]
+impl Record {
+    /// テスト用の取引。分類などは空なので、必要な項目は構造体更新構文で指定する
+    pub(crate) fn test(date: &str, usage: &str, amount: impl Into<Decimal>) -> Record {
+        Record {
+            日付: date.parse().unwrap(),
+            用途: usage.to_string(),
+            金額: amount.into(),
+            分類: String::new(),
+            タグ: String::new(),
+            振替番号: String::new(),
+            取引番号: String::new(),
+        }
+    }
+
+    /// 分類を指定したテスト用の取引
+    pub(crate) fn test_categorized(
+        date: &str,
+        usage: &str,
+        amount: impl Into<Decimal>,
+        category: &str,
+    ) -> Record {
+        Record {
+            分類: category.to_string(),
+            ..Record::test(date, usage, amount)
+        }
+    }
 }
 
 #[derive(Args)]
 struct ReportArgs {
-    files: Vec<String>,
+    /// 集計する口座のファイル（省略した場合はデータディレクトリのすべての口座）
+    files: Vec<PathBuf>,
+    /// 集計の単位
+    #[clap(long, value_enum, default_value = "month")]
+    by: report::GroupBy,
+    /// 口座ごとに分けて集計する
+    #[clap(long)]
+    per_account: bool,
+    /// 出力形式
+    #[clap(long, value_enum, default_value = "table")]
+    format: report::Format,
+    /// このタグが付いた取引だけを集計する
+    #[clap(long)]
+    tag: Option<String>,
+    /// 金額をそろえる通貨（省略した場合は基準通貨）
+    #[clap(long)]
+    currency: Option<String>,
+    /// グラフ付きの HTML のレポートを書き出すファイル
+    #[clap(long, conflicts_with_all = ["by", "per_account", "format"])]
+    html: Option<PathBuf>,
 }
 impl ReportArgs {
     /// report サブコマンドの本体処理
-    fn run(&self) {
-        let mut map = HashMap::new();
-        for file in self.files.iter() {
-            let mut reader = Reader::from_path(file).unwrap();
-            for result in reader.records() {
-                let record = result.unwrap();
-                let date = record[0].parse::<NaiveDate>().unwrap();
-                let amount = record[2].parse::<i32>().unwrap();
-                let sum = map.entry(date.format("%Y-%m").to_string()).or_insert(0);
-                *sum += amount;
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let config = book.load_config()?;
+        let currency = match &self.currency {
+            Some(currency) => parse_currency(currency)?,
+            None => config.base_currency().to_string(),
+        };
+        let mut accounts = vec![];
+        if self.files.is_empty() {
+            for name in book.accounts()? {
+                let records = book.read_records(&name)?;
+                accounts.push((name, records));
+            }
+        } else {
+            for file in &self.files {
+                // NOTE: 口座名はファイル名から拡張子を除いたもの
+                let name = file
+                    .file_stem()
+                    .map_or_else(|| file.to_string_lossy(), |stem| stem.to_string_lossy());
+                accounts.push((name.to_string(), read_records(file)?));
+            }
+        }
+        // NOTE: 取引ごとに、その日付の時点のレートで換算する
+        let rates = book.load_rates()?;
+        for (name, records) in accounts.iter_mut() {
+            rates.convert_records(records, config.currency(name), &currency)?;
+        }
+        // NOTE: 残高の推移は、タグで絞り込む前のすべての取引から求める
+        let all_accounts = accounts.clone();
+        if let Some(tag) = &self.tag {
+            for (_, records) in accounts.iter_mut() {
+                records.retain(|record| record.tags().any(|t| t == tag));
+            }
+        }
+        if let Some(path) = &self.html {
+            fs::write(
+                path,
+                chart::render_html(&accounts, &all_accounts, &currency),
+            )?;
+            println!("レポートを {} に書き出しました", path.display());
+            return Ok(());
+        }
+        let rows = report::summarize(&accounts, self.by, self.per_account);
+        print!("{}", report::render(&rows, self.by, self.format)?);
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct BalanceArgs {
+    account_name: String,
+}
+impl BalanceArgs {
+    /// balance サブコマンドの本体処理
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        println!("{}", book.balance(&self.account_name)?);
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct LedgerArgs {
+    account_name: String,
+    /// この日以降の取引を表示する
+    #[clap(long)]
+    from: Option<NaiveDate>,
+    /// この日以前の取引を表示する
+    #[clap(long)]
+    to: Option<NaiveDate>,
+}
+impl LedgerArgs {
+    /// ledger サブコマンドの本体処理
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let records = book.read_records(&self.account_name)?;
+        print!("{}", render_ledger(&records, self.from, self.to));
+        Ok(())
+    }
+}
+
+/// 取引を日付順に並べ、残高の推移とともに表形式で出力する
+fn render_ledger(records: &[Record], from: Option<NaiveDate>, to: Option<NaiveDate>) -> String {
+    let mut records: Vec<&Record> = records.iter().collect();
+    // NOTE: 同じ日付の取引は記録された順のままにする
+    records.sort_by_key(|record| record.日付);
+
+    // NOTE: 見出しは全角文字なので、文字数ではなく表示幅で行の列にそろえる
+    let pad = |text: &str| " ".repeat(10usize.saturating_sub(text.width()));
+    let mut output = String::new();
+    writeln!(
+        output,
+        "日付{}  {}金額  {}残高  用途",
+        pad("日付"),
+        pad("金額"),
+        pad("残高")
+    )
+    .unwrap();
+    let mut balance = Decimal::ZERO;
+    let mut carried_over = false;
+    for record in records {
+        if to.is_some_and(|to| record.日付 > to) {
+            break;
+        }
+        balance += record.金額;
+        if from.is_some_and(|from| record.日付 < from) {
+            continue;
+        }
+        if let (Some(from), false) = (from, carried_over) {
+            // NOTE: 期間の前の取引は残高にだけ反映し、繰越として1行にまとめる
+            let opening = balance - record.金額;
+            writeln!(output, "{:<10}  {:>10}  {:>10}  繰越", from, "", opening).unwrap();
+        }
+        carried_over = true;
+        writeln!(
+            output,
+            "{:<10}  {:>10}  {:>10}  {}",
+            record.日付, record.金額, balance, record.用途
+        )
+        .unwrap();
+    }
+    // NOTE: 期間内に取引がなくても、繰越の残高は表示する
+    if let (Some(from), false) = (from, carried_over) {
+        writeln!(output, "{:<10}  {:>10}  {:>10}  繰越", from, "", balance).unwrap();
+    }
+    output
+}
+
+#[derive(Args)]
+struct AccountsArgs {}
+impl AccountsArgs {
+    /// accounts サブコマンドの本体処理
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let config = book.load_config()?;
+        let mut balances = vec![];
+        for name in book.accounts()? {
+            let balance = book.balance(&name)?;
+            let currency = config.currency(&name).to_string();
+            balances.push((name, currency, balance));
+        }
+        print!("{}", render_accounts(&balances));
+        Ok(())
+    }
+}
+
+/// 口座と残高を表形式で出力する
+// NOTE: 通貨が異なる残高は足し合わせられないので、合計は通貨ごとに出す
+fn render_accounts(balances: &[(String, String, Decimal)]) -> String {
+    let mut totals: BTreeMap<&str, Decimal> = BTreeMap::new();
+    for (_, currency, balance) in balances {
+        *totals.entry(currency).or_default() += balance;
+    }
+    let labels: Vec<String> = totals
+        .keys()
+        .map(|currency| format!("合計（{}）", currency))
+        .collect();
+    let name_width = balances
+        .iter()
+        .map(|(name, _, _)| name.width())
+        .chain(labels.iter().map(|label| label.width()))
+        .chain(["口座".width()])
+        .max()
+        .unwrap_or_default();
+    let pad = |name: &str| format!("{}{}", name, " ".repeat(name_width - name.width()));
+
+    let mut output = String::new();
+    writeln!(output, "{}  {:>10}  通貨", pad("口座"), "残高").unwrap();
+    for (name, currency, balance) in balances {
+        writeln!(output, "{}  {:>12}  {}", pad(name), balance, currency).unwrap();
+    }
+    for (label, (currency, total)) in labels.iter().zip(totals) {
+        writeln!(output, "{}  {:>12}  {}", pad(label), total, currency).unwrap();
+    }
+    output
+}
+
+#[derive(Args)]
+struct TransferArgs {
+    from: String,
+    to: String,
+    date: NaiveDate,
+    /// 振替元の口座の通貨での金額
+    amount: Decimal,
+    /// 用途（省略した場合は振替元・振替先の口座名から決める）
+    #[clap(long)]
+    usage: Option<String>,
+}
+impl TransferArgs {
+    /// transfer サブコマンドの本体処理
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        book.transfer(
+            &self.from,
+            &self.to,
+            self.date,
+            self.amount,
+            self.usage.as_deref(),
+        )
+    }
+}
+
+#[derive(Args)]
+struct RuleArgs {
+    #[clap(subcommand)]
+    command: RuleCommand,
+}
+
+#[derive(Subcommand)]
+enum RuleCommand {
+    /// ルールを追加する（先に追加したルールが優先される）
+    Add {
+        /// 用途に対する正規表現
+        pattern: String,
+        category: String,
+    },
+    /// ルールを一覧表示する
+    List,
+    /// 番号を指定してルールを削除する
+    Remove { number: usize },
+}
+impl RuleArgs {
+    /// rule サブコマンドの本体処理
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let mut config = book.load_config()?;
+        match &self.command {
+            RuleCommand::Add { pattern, category } => {
+                config.add_rule(pattern, category)?;
+                book.save_config(&config)?;
+            }
+            RuleCommand::List => {
+                for (i, rule) in config.rules.iter().enumerate() {
+                    println!("{}: {} => {}", i + 1, rule.pattern, rule.category);
+                }
+            }
+            RuleCommand::Remove { number } => {
+                if *number == 0 || *number > config.rules.len() {
+                    return Err(MyError::UnknownRule(*number));
+                }
+                config.rules.remove(number - 1);
+                book.save_config(&config)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct BudgetArgs {
+    #[clap(subcommand)]
+    command: BudgetCommand,
+}
+
+#[derive(Subcommand)]
+enum BudgetCommand {
+    /// 分類の1か月の予算を基準通貨で設定する
+    Set { category: String, limit: Decimal },
+    /// 分類の予算を削除する
+    Remove { category: String },
+    /// すべての口座について、予算の消化状況を表示する
+    Status {
+        /// 対象の月（YYYY-MM、省略した場合は今月）
+        #[clap(long)]
+        month: Option<String>,
+    },
+}
+impl BudgetArgs {
+    /// budget サブコマンドの本体処理
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let mut config = book.load_config()?;
+        match &self.command {
+            BudgetCommand::Set { category, limit } => {
+                validate_amount(*limit, config.base_currency())?;
+                config.budgets.insert(category.clone(), *limit);
+                book.save_config(&config)?;
+            }
+            BudgetCommand::Remove { category } => {
+                if config.budgets.remove(category).is_none() {
+                    return Err(MyError::UnknownBudget(category.clone()));
+                }
+                book.save_config(&config)?;
+            }
+            BudgetCommand::Status { month } => {
+                let month = match month {
+                    Some(month) => parse_month(month)?,
+                    None => Local::now().date_naive(),
+                };
+                // NOTE: 予算は基準通貨で設定するので、ほかの通貨の口座の取引は換算して集計する
+                let rates = book.load_rates()?;
+                let mut records = vec![];
+                for name in book.accounts()? {
+                    let mut account_records = book.read_records(&name)?;
+                    rates.convert_records(
+                        &mut account_records,
+                        config.currency(&name),
+                        config.base_currency(),
+                    )?;
+                    records.extend(account_records);
+                }
+                let statuses = category::budget_status(&config, &records, month);
+                print!(
+                    "{}",
+                    category::render_budget_status(&statuses, config.base_currency())
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// rate サブコマンドの引数
+#[derive(Args)]
+struct RateArgs {
+    #[clap(subcommand)]
+    command: RateCommand,
+}
+
+#[derive(Subcommand)]
+enum RateCommand {
+    /// 為替レートを登録する（1 通貨 = レート 基準通貨）
+    Add {
+        date: NaiveDate,
+        currency: String,
+        base: String,
+        rate: Decimal,
+    },
+    /// 日付・通貨・基準通貨・レートの列がある CSV から為替レートを登録する
+    Import { file: PathBuf },
+    /// 登録されている為替レートを一覧表示する
+    List,
+    /// レポートや予算で金額をそろえる基準通貨を設定する
+    Base { currency: String },
+}
+impl RateArgs {
+    /// rate サブコマンドの本体処理
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let mut rates = book.load_rates()?;
+        match &self.command {
+            RateCommand::Add {
+                date,
+                currency,
+                base,
+                rate,
+            } => {
+                rates.add(new_rate(*date, currency, base, *rate)?);
+                book.save_rates(&rates)?;
+            }
+            RateCommand::Import { file } => {
+                let imported = Rates::load(file)?;
+                let mut count = 0;
+                for rate in imported.iter() {
+                    rates.add(new_rate(
+                        rate.日付,
+                        &rate.通貨,
+                        &rate.基準通貨,
+                        rate.レート,
+                    )?);
+                    count += 1;
+                }
+                book.save_rates(&rates)?;
+                println!("{} 件の為替レートを登録しました", count);
+            }
+            RateCommand::List => {
+                for rate in rates.iter() {
+                    println!(
+                        "{}  1 {} = {} {}",
+                        rate.日付, rate.通貨, rate.レート, rate.基準通貨
+                    );
+                }
+            }
+            RateCommand::Base { currency } => {
+                let currency = parse_currency(currency)?;
+                let mut config = book.load_config()?;
+                // NOTE: 通貨を指定せずに作った口座の通貨が、基準通貨と一緒に変わらないようにする
+                for name in book.accounts()? {
+                    let account_currency = config.currency(&name).to_string();
+                    if account_currency == currency {
+                        config.currencies.remove(&name);
+                    } else {
+                        config.currencies.insert(name, account_currency);
+                    }
+                }
+                config.base_currency = Some(currency);
+                book.save_config(&config)?;
             }
         }
-        println!("{:?}", map);
+        Ok(())
     }
 }
 
-fn main() {
+/// 通貨とレートを確認して、為替レートを作る
+fn new_rate(
+    date: NaiveDate,
+    currency: &str,
+    base: &str,
+    rate: Decimal,
+) -> Result<currency::Rate, MyError> {
+    let currency = parse_currency(currency)?;
+    let base = parse_currency(base)?;
+    if rate <= Decimal::ZERO {
+        return Err(MyError::InvalidAmount {
+            amount: rate,
+            reason: "レートには正の値を指定してください".to_string(),
+        });
+    }
+    Ok(currency::Rate {
+        日付: date,
+        通貨: currency,
+        基準通貨: base,
+        レート: rate,
+    })
+}
+
+#[derive(Args)]
+struct RecurringArgs {
+    #[clap(subcommand)]
+    command: RecurringCommand,
+}
+
+#[derive(Subcommand)]
+enum RecurringCommand {
+    /// 定期取引を登録する（同じ名前の定期取引は置き換える）
+    Add(RecurringAddArgs),
+    /// 定期取引を一覧表示する
+    List,
+    /// 定期取引を削除する（記帳済みの取引は残す）
+    Remove { name: String },
+}
+
+#[derive(Args)]
+#[clap(group(ArgGroup::new("schedule").required(true).args(["monthly", "weekly", "yearly"])))]
+struct RecurringAddArgs {
+    name: String,
+    account_name: String,
+    usage: String,
+    amount: Decimal,
+    /// 出金として記帳する
+    #[clap(long)]
+    withdraw: bool,
+    /// 毎月この日に記帳する（その月にない日は月末）
+    #[clap(long)]
+    monthly: Option<u32>,
+    /// 毎週この曜日に記帳する（mon、tue など）
+    #[clap(long)]
+    weekly: Option<Weekday>,
+    /// 毎年この日に記帳する（MM-DD）
+    #[clap(long)]
+    yearly: Option<String>,
+    /// この日以降に記帳を始める（省略した場合は今日）
+    #[clap(long)]
+    start: Option<NaiveDate>,
+    /// この日より後は記帳しない
+    #[clap(long)]
+    end: Option<NaiveDate>,
+    /// 分類（省略した場合はルールで決める）
+    #[clap(long)]
+    category: Option<String>,
+    /// タグ（複数指定できる）
+    #[clap(long = "tag")]
+    tags: Vec<String>,
+}
+impl RecurringAddArgs {
+    fn schedule(&self) -> Result<recurring::Schedule, MyError> {
+        let schedule = match (self.monthly, self.weekly, &self.yearly) {
+            (Some(day), _, _) => recurring::Schedule::Monthly { day },
+            (_, Some(weekday), _) => recurring::Schedule::Weekly { weekday },
+            (_, _, Some(yearly)) => {
+                let date = NaiveDate::parse_from_str(&format!("2000-{}", yearly), "%Y-%m-%d")
+                    .map_err(|_| MyError::InvalidSchedule(yearly.clone()))?;
+                recurring::Schedule::Yearly {
+                    month: date.month(),
+                    day: date.day(),
+                }
+            }
+            (None, None, None) => unreachable!("clap で必須にしている"),
+        };
+        schedule.validate()?;
+        Ok(schedule)
+    }
+}
+impl RecurringArgs {
+    /// recurring サブコマンドの本体処理
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let mut config = book.load_config()?;
+        match &self.command {
+            RecurringCommand::Add(args) => {
+                // NOTE: 口座がない・金額が不正などの誤りは、記帳するときではなく登録するときに知らせる
+                book.read_records(&args.account_name)?;
+                validate_amount(args.amount, config.currency(&args.account_name))?;
+                let recurring = recurring::Recurring {
+                    account: args.account_name.clone(),
+                    usage: args.usage.clone(),
+                    amount: if args.withdraw {
+                        -args.amount
+                    } else {
+                        args.amount
+                    },
+                    category: args.category.clone(),
+                    tags: args.tags.clone(),
+                    schedule: args.schedule()?,
+                    start: args.start.unwrap_or_else(|| Local::now().date_naive()),
+                    end: args.end,
+                    posted_until: None,
+                };
+                config.recurring.insert(args.name.clone(), recurring);
+                book.save_config(&config)?;
+            }
+            RecurringCommand::List => {
+                for (name, recurring) in &config.recurring {
+                    print!(
+                        "{}: {} {} {} {}",
+                        name,
+                        recurring.account,
+                        recurring.schedule,
+                        recurring.amount,
+                        recurring.usage
+                    );
+                    if let Some(posted_until) = recurring.posted_until {
+                        print!("（{} まで記帳済み）", posted_until);
+                    }
+                    println!();
+                }
+            }
+            RecurringCommand::Remove { name } => {
+                if config.recurring.remove(name).is_none() {
+                    return Err(MyError::UnknownRecurring(name.clone()));
+                }
+                book.save_config(&config)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct PostDueArgs {
+    /// この日までの分を記帳する（省略した場合は今日）
+    #[clap(long)]
+    date: Option<NaiveDate>,
+    /// 記帳する取引を表示するだけで、書き込まない
+    #[clap(long)]
+    dry_run: bool,
+}
+impl PostDueArgs {
+    /// post-due サブコマンドの本体処理
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let today = self.date.unwrap_or_else(|| Local::now().date_naive());
+        let mut config = book.load_config()?;
+        // NOTE: 記帳済みの日付を更新しながら保存するため、設定の写しを順に処理する
+        let snapshot = config.clone();
+        let rules = snapshot.compile_rules()?;
+        let mut count = 0;
+        for (name, recurring) in &snapshot.recurring {
+            // NOTE: 書き込んだ後、記帳済みの日付を保存する前に失敗した場合に備えて、取引番号でも確認する
+            let posted: HashSet<String> = book
+                .read_records(&recurring.account)?
+                .into_iter()
+                .map(|record| record.取引番号)
+                .collect();
+            let mut records = vec![];
+            for date in recurring.due_dates(today) {
+                let id = recurring::Recurring::transaction_id(name, date);
+                if posted.contains(&id) {
+                    continue;
+                }
+                let mut record = new_record(
+                    &snapshot,
+                    &rules,
+                    &recurring.account,
+                    date,
+                    &recurring.usage,
+                    recurring.amount,
+                    &recurring.category,
+                    &recurring.tags,
+                )?;
+                record.取引番号 = id;
+                println!(
+                    "{}  {}  {}  {}",
+                    record.日付, recurring.account, record.金額, record.用途
+                );
+                records.push(record);
+            }
+            count += records.len();
+            if self.dry_run {
+                continue;
+            }
+            book.append_records(&recurring.account, &records)?;
+            // NOTE: 過去の日付を --date に指定しても、記帳済みの日付は戻さない
+            config.recurring.insert(
+                name.clone(),
+                recurring::Recurring {
+                    posted_until: recurring.posted_until.max(Some(today)),
+                    ..recurring.clone()
+                },
+            );
+            book.save_config(&config)?;
+        }
+        if self.dry_run {
+            println!(
+                "{} 件の取引を記帳します（--dry-run のため書き込みません）",
+                count
+            );
+        } else {
+            println!("{} 件の取引を記帳しました", count);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct MigrateArgs {}
+impl MigrateArgs {
+    /// migrate サブコマンドの本体処理
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let database = book.dir.join(sqlite::DATABASE_FILE_NAME);
+        if database.exists() {
+            return Err(MyError::AlreadyMigrated(
+                database.to_string_lossy().to_string(),
+            ));
+        }
+        // NOTE: 途中で失敗した場合に移行済みと扱われないよう、一時ファイルに書き込んでから置き換える
+        let temporary = database.with_extension("db.tmp");
+        if temporary.exists() {
+            fs::remove_file(&temporary)?;
+        }
+        let csv = CsvStore::new(&book.dir);
+        let (accounts, count) = {
+            let store = SqliteStore::open(&temporary)?;
+            copy_accounts(&csv, &store, &csv.accounts()?, false)?
+        };
+        fs::rename(&temporary, &database)?;
+        println!(
+            "{} 口座、{} 件の取引を {} に移行しました（CSV ファイルは残してあります）",
+            accounts,
+            count,
+            database.display()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    /// CSV ファイルを書き出すディレクトリ
+    out_dir: PathBuf,
+    /// 書き出す口座（省略した場合はすべての口座）
+    #[clap(long = "account")]
+    accounts: Vec<String>,
+    /// 書き出し先の既存のファイルを上書きする
+    #[clap(long)]
+    force: bool,
+}
+impl ExportArgs {
+    /// export サブコマンドの本体処理
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let accounts = if self.accounts.is_empty() {
+            book.accounts()?
+        } else {
+            self.accounts.clone()
+        };
+        let (accounts, count) = copy_accounts(
+            book.store.as_ref(),
+            &CsvStore::new(&self.out_dir),
+            &accounts,
+            self.force,
+        )?;
+        println!(
+            "{} 口座、{} 件の取引を {} に書き出しました",
+            accounts,
+            count,
+            self.out_dir.display()
+        );
+        Ok(())
+    }
+}
+
+/// 口座を別の保存先に写す。写した口座の数と取引の件数を返す
+fn copy_accounts(
+    from: &dyn Store,
+    to: &dyn Store,
+    accounts: &[String],
+    force: bool,
+) -> Result<(usize, usize), MyError> {
+    let mut count = 0;
+    for name in accounts {
+        // NOTE: 読み取れない口座を、写し先に空の口座として作らない
+        let records = from.read_records(name)?;
+        to.create_account(name, force)?;
+        to.append_records(name, &records)?;
+        count += records.len();
+    }
+    Ok((accounts.len(), count))
+}
+
+#[derive(Args)]
+struct JournalArgs {
+    #[clap(subcommand)]
+    command: JournalCommand,
+}
+
+#[derive(Subcommand)]
+enum JournalCommand {
+    /// 複数の口座・分類にまたがる仕訳を記帳する
+    Add {
+        date: NaiveDate,
+        description: String,
+        /// 勘定と金額（借方は正、貸方は負。例：--posting bank=-3000 --posting 食費=3000）
+        #[clap(long = "posting", required = true)]
+        postings: Vec<String>,
+    },
+    /// 仕訳帳を表示する
+    List {
+        /// この日以降の仕訳を表示する
+        #[clap(long)]
+        from: Option<NaiveDate>,
+        /// この日までの仕訳を表示する
+        #[clap(long)]
+        to: Option<NaiveDate>,
+    },
+    /// すべての仕訳の借方と貸方が一致するかを確認する
+    Check,
+    /// 試算表を表示する
+    TrialBalance {
+        /// この日までの仕訳を集計する（省略した場合は今日）
+        #[clap(long)]
+        date: Option<NaiveDate>,
+    },
+    /// 貸借対照表と損益計算書を表示する
+    Statement {
+        /// 損益計算書の期間の初日（省略した場合は to の年の1月1日）
+        #[clap(long)]
+        from: Option<NaiveDate>,
+        /// 貸借対照表の日付、損益計算書の期間の末日（省略した場合は今日）
+        #[clap(long)]
+        to: Option<NaiveDate>,
+    },
+    /// 勘定（口座・分類）の種類を設定する
+    Kind {
+        account: String,
+        #[clap(value_enum)]
+        kind: journal::AccountKind,
+    },
+}
+impl JournalArgs {
+    /// journal サブコマンドの本体処理
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let today = Local::now().date_naive();
+        match &self.command {
+            JournalCommand::Add {
+                date,
+                description,
+                postings,
+            } => book.post_entry(*date, description, &parse_postings(postings)?),
+            JournalCommand::List { from, to } => {
+                let (_, entries) = book.journal()?;
+                let entries: Vec<_> = entries
+                    .into_iter()
+                    .filter(|entry| from.is_none_or(|from| entry.date >= from))
+                    .filter(|entry| to.is_none_or(|to| entry.date <= to))
+                    .collect();
+                print!("{}", journal::render_journal(&entries));
+                Ok(())
+            }
+            JournalCommand::Check => {
+                let (_, entries) = book.journal()?;
+                let unbalanced = journal::unbalanced(&entries);
+                if unbalanced.is_empty() {
+                    println!(
+                        "{} 件の仕訳は、すべて借方と貸方が一致しています",
+                        entries.len()
+                    );
+                    return Ok(());
+                }
+                for entry in &unbalanced {
+                    println!(
+                        "{} {}（振替番号：{}）：差額 {}",
+                        entry.date,
+                        entry.description,
+                        entry.id,
+                        entry.difference()
+                    );
+                }
+                Err(MyError::UnbalancedJournal(unbalanced.len()))
+            }
+            JournalCommand::TrialBalance { date } => {
+                let date = date.unwrap_or(today);
+                let (accounts, mut entries) = book.journal()?;
+                entries.retain(|entry| entry.date <= date);
+                let balances = journal::trial_balance(&book.load_config()?, &entries, &accounts);
+                print!("{}", journal::render_trial_balance(&balances));
+                Ok(())
+            }
+            JournalCommand::Statement { from, to } => {
+                let to = to.unwrap_or(today);
+                let from = from.unwrap_or_else(|| to.with_ordinal(1).unwrap());
+                let (accounts, entries) = book.journal()?;
+                print!(
+                    "{}",
+                    journal::render_statements(&book.load_config()?, &entries, &accounts, from, to)
+                );
+                Ok(())
+            }
+            JournalCommand::Kind { account, kind } => {
+                let mut config = book.load_config()?;
+                config.kinds.insert(account.clone(), *kind);
+                book.save_config(&config)
+            }
+        }
+    }
+}
+
+#[derive(Args)]
+struct ExportLedgerArgs {
+    /// 書き出すファイル（省略した場合は標準出力）
+    #[clap(long)]
+    output: Option<PathBuf>,
+}
+impl ExportLedgerArgs {
+    /// export-ledger サブコマンドの本体処理
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let config = book.load_config()?;
+        let mut accounts = vec![];
+        for name in book.accounts()? {
+            let records = book.read_records(&name)?;
+            accounts.push((name, records));
+        }
+        let journal = ledger::export(&config, &accounts);
+        match &self.output {
+            Some(path) => fs::write(path, journal)?,
+            None => print!("{}", journal),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct ImportLedgerArgs {
+    /// ledger 形式の仕訳帳のファイル
+    file: PathBuf,
+    /// 読み込む取引の件数を表示するだけで、書き込まない
+    #[clap(long)]
+    dry_run: bool,
+}
+impl ImportLedgerArgs {
+    /// import-ledger サブコマンドの本体処理
+    fn run(&self, book: &Book) -> Result<(), MyError> {
+        let content = fs::read_to_string(&self.file)?;
+        let imported = ledger::parse(&content, &self.file.to_string_lossy())?;
+        for warning in &imported.warnings {
+            eprintln!("警告：{}", warning);
+        }
+        let mut config = book.load_config()?;
+        let existing = book.accounts()?;
+        // NOTE: 書き込みを始める前に、既存の口座の通貨が仕訳帳と一致することを確認する
+        for (account, currency) in &imported.currencies {
+            if existing.contains(account) && config.currency(account) != currency {
+                return Err(MyError::InvalidPosting(format!(
+                    "口座「{}」の通貨は {} ですが、仕訳帳の金額は {} です",
+                    account,
+                    config.currency(account),
+                    currency
+                )));
+            }
+        }
+        let count: usize = imported.records.values().map(Vec::len).sum();
+        if self.dry_run {
+            println!(
+                "{} 口座に {} 件の取引を読み込みます（--dry-run のため書き込みません）",
+                imported.records.len(),
+                count
+            );
+            return Ok(());
+        }
+        for account in imported.records.keys() {
+            if !existing.contains(account) {
+                book.create_account(account, false)?;
+                if let Some(currency) = imported.currencies.get(account) {
+                    config.currencies.insert(account.clone(), currency.clone());
+                }
+            }
+        }
+        for (account, kind) in &imported.kinds {
+            config.kinds.entry(account.clone()).or_insert(*kind);
+        }
+        book.save_config(&config)?;
+        for (account, records) in &imported.records {
+            book.append_records(account, records)?;
+        }
+        println!(
+            "{} 口座に {} 件の取引を読み込みました",
+            imported.records.len(),
+            count
+        );
+        Ok(())
+    }
+}
+
+/// 「勘定=金額」の形式の仕訳の行を読み取る
+fn parse_postings(postings: &[String]) -> Result<Vec<(String, Decimal)>, MyError> {
+    postings
+        .iter()
+        .map(|posting| {
+            let invalid = || {
+                MyError::InvalidPosting(format!(
+                    "「{}」を読み取れません（勘定=金額 の形式で指定してください）",
+                    posting
+                ))
+            };
+            let (account, amount) = posting.rsplit_once('=').ok_or_else(invalid)?;
+            let amount: Decimal = amount.trim().parse().map_err(|_| invalid())?;
+            if account.trim().is_empty() || amount.is_zero() {
+                return Err(invalid());
+            }
+            Ok((account.trim().to_string(), amount))
+        })
+        .collect()
+}
+
+/// YYYY-MM 形式の月を、その月の1日として読み取る
+fn parse_month(month: &str) -> Result<NaiveDate, MyError> {
+    NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+        .map_err(|_| MyError::InvalidMonth(month.to_string()))
+}
+
+fn main() -> ExitCode {
     // 構造体 App で定義した形のサブコマンドを受け取ることを期待して parse を行う
     let args = App::parse();
-    match args.command {
-        Command::New(args) => args.run(),
-        Command::Deposit(args) => args.run(),
-        Command::Withdraw(args) => args.run(),
-        Command::Import(args) => args.run(),
-        Command::Report(args) => args.run(),
+    let result = Book::open(args.data_dir).and_then(|book| match args.command {
+        Command::New(args) => args.run(&book),
+        Command::Deposit(args) => args.run(&book),
+        Command::Withdraw(args) => args.run(&book),
+        Command::Import(args) => args.run(&book),
+        Command::Report(args) => args.run(&book),
+        Command::Balance(args) => args.run(&book),
+        Command::Ledger(args) => args.run(&book),
+        Command::Accounts(args) => args.run(&book),
+        Command::Transfer(args) => args.run(&book),
+        Command::Profile(args) => args.run(&book),
+        Command::Rule(args) => args.run(&book),
+        Command::Budget(args) => args.run(&book),
+        Command::Rate(args) => args.run(&book),
+        Command::Recurring(args) => args.run(&book),
+        Command::PostDue(args) => args.run(&book),
+        Command::Migrate(args) => args.run(&book),
+        Command::Export(args) => args.run(&book),
+        Command::Journal(args) => args.run(&book),
+        Command::ExportLedger(args) => args.run(&book),
+        Command::ImportLedger(args) => args.run(&book),
+    });
+    // NOTE: パニックのバックトレースではなく、原因の分かるメッセージと終了コードで失敗を伝える
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("エラー：{}", error);
+            ExitCode::from(error.exit_code())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_refuses_to_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let book = Book::open(dir.path().join("data")).unwrap();
+        let new_args = |force| NewArgs {
+            account_name: "wallet".to_string(),
+            force,
+            currency: None,
+        };
+        new_args(false).run(&book).unwrap();
+        let path = dir.path().join("data").join("wallet.csv");
+        fs::write(&path, "日付,用途,金額\n2024-01-01,給与,1000\n").unwrap();
+
+        let actual = new_args(false).run(&book);
+
+        assert!(matches!(actual, Err(MyError::AccountAlreadyExists(_))));
+        assert_eq!(
+            "日付,用途,金額\n2024-01-01,給与,1000\n",
+            fs::read_to_string(&path).unwrap()
+        );
+
+        new_args(true).run(&book).unwrap();
+        assert_eq!(
+            "日付,用途,金額,分類,タグ,振替番号,取引番号\n",
+            fs::read_to_string(&path).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_validate_account_name() {
+        for name in [
+            "",
+            " ",
+            "../wallet",
+            "a/b",
+            "a\\b",
+            ".hidden",
+            "con",
+            "LPT1",
+            "a:b",
+            "rates",
+        ] {
+            assert!(
+                matches!(
+                    validate_account_name(name),
+                    Err(MyError::InvalidAccountName { .. })
+                ),
+                "{:?}",
+                name
+            );
+        }
+        for name in ["wallet", "お財布", "console", "bank.main", "rates.old"] {
+            assert!(validate_account_name(name).is_ok(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_render_ledger() {
+        let records = vec![
+            Record::test("2024-01-25", "給与", 300000),
+            Record::test("2024-01-05", "家賃", -80000),
+            Record::test("2024-02-01", "食費", -20000),
+            Record::test("2024-03-01", "食費", -15000),
+        ];
+
+        let actual = render_ledger(
+            &records,
+            Some("2024-01-10".parse().unwrap()),
+            Some("2024-02-28".parse().unwrap()),
+        );
+
+        assert_eq!(
+            "日付              金額        残高  用途\n\
+             2024-01-10                  -80000  繰越\n\
+             2024-01-25      300000      220000  給与\n\
+             2024-02-01      -20000      200000  食費\n",
+            actual
+        );
+    }
+
+    #[test]
+    fn test_render_ledger_without_records_in_range() {
+        let records = vec![
+            Record::test("2024-01-25", "給与", 300000),
+            Record::test("2024-01-05", "家賃", -80000),
+            Record::test("2024-03-01", "食費", -15000),
+        ];
+
+        let actual = render_ledger(
+            &records,
+            Some("2024-02-01".parse().unwrap()),
+            Some("2024-02-28".parse().unwrap()),
+        );
+
+        assert_eq!(
+            "日付              金額        残高  用途\n\
+             2024-02-01                  220000  繰越\n",
+            actual
+        );
+    }
+
+    #[test]
+    fn test_accounts_and_balance() {
+        let dir = tempfile::tempdir().unwrap();
+        let book = Book::open(dir.path()).unwrap();
+        fs::write(
+            dir.path().join("wallet.csv"),
+            "日付,用途,金額\n2024-01-01,給与,1000\n2024-01-02,食費,-300\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("bank.csv"), "日付,用途,金額\n").unwrap();
+        fs::write(dir.path().join("memo.txt"), "").unwrap();
+
+        assert_eq!(vec!["bank", "wallet"], book.accounts().unwrap());
+        assert_eq!(Decimal::from(700), book.balance("wallet").unwrap());
+        let balance = |name: &str, currency: &str, balance: &str| {
+            (
+                name.to_string(),
+                currency.to_string(),
+                balance.parse().unwrap(),
+            )
+        };
+        assert_eq!(
+            "口座                 残高  通貨\n\
+             bank                    0  JPY\n\
+             wallet                700  JPY\n\
+             travel             120.50  USD\n\
+             合計（JPY）           700  JPY\n\
+             合計（USD）        120.50  USD\n",
+            render_accounts(&[
+                balance("bank", "JPY", "0"),
+                balance("wallet", "JPY", "700"),
+                balance("travel", "USD", "120.50"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_read_records_with_malformed_row() {
+        let dir = tempfile::tempdir().unwrap();
+        let book = Book::open(dir.path()).unwrap();
+        fs::write(
+            dir.path().join("wallet.csv"),
+            "日付,用途,金額\n2024-01-01,給与,1000\n2024-13-01,食費,-300\n",
+        )
+        .unwrap();
+
+        let actual = book.read_records("wallet");
+
+        assert!(matches!(actual, Err(MyError::Parse { line: 3, .. })));
+        assert!(matches!(
+            book.read_records("bank"),
+            Err(MyError::AccountNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_withdraw_upgrades_legacy_account() {
+        let dir = tempfile::tempdir().unwrap();
+        let book = Book::open(dir.path()).unwrap();
+        let path = dir.path().join("wallet.csv");
+        fs::write(&path, "日付,用途,金額\n2024-01-01,給与,1000\n").unwrap();
+        let mut config = Config::default();
+        config.add_rule("コンビニ", "食費").unwrap();
+        book.save_config(&config).unwrap();
+        let args = WithdrawArgs {
+            account_name: "wallet".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(),
+            usage: "コンビニ".to_string(),
+            amount: Decimal::from(300),
+            category: None,
+            tags: vec!["旅行".to_string(), "家族".to_string()],
+        };
+
+        args.run(&book).unwrap();
+
+        assert_eq!(
+            "日付,用途,金額,分類,タグ,振替番号,取引番号\n2024-01-01,給与,1000\n2024-01-02,コンビニ,-300,食費,旅行;家族,,\n",
+            fs::read_to_string(&path).unwrap()
+        );
+        let records = book.read_records("wallet").unwrap();
+        assert_eq!("", records[0].分類);
+        assert_eq!(vec!["旅行", "家族"], records[1].tags().collect::<Vec<_>>());
+    }
+
+    fn create_accounts(book: &Book, names: &[&str]) {
+        for name in names {
+            NewArgs {
+                account_name: name.to_string(),
+                force: false,
+                currency: None,
+            }
+            .run(book)
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_transfer() {
+        let dir = tempfile::tempdir().unwrap();
+        let book = Book::open(dir.path()).unwrap();
+        create_accounts(&book, &["savings", "wallet"]);
+
+        book.transfer(
+            "savings",
+            "wallet",
+            "2024-01-10".parse().unwrap(),
+            Decimal::from(5000),
+            None,
+        )
+        .unwrap();
+
+        let withdrawal = &book.read_records("savings").unwrap()[0];
+        let deposit = &book.read_records("wallet").unwrap()[0];
+        assert_eq!(
+            (Decimal::from(-5000), "振替（walletへ）"),
+            (withdrawal.金額, withdrawal.用途.as_str())
+        );
+        assert_eq!(
+            (Decimal::from(5000), "振替（savingsから）"),
+            (deposit.金額, deposit.用途.as_str())
+        );
+        assert!(withdrawal.is_transfer());
+        assert_eq!(withdrawal.振替番号, deposit.振替番号);
+
+        let actual = book.transfer(
+            "savings",
+            "bank",
+            "2024-01-10".parse().unwrap(),
+            Decimal::ONE,
+            None,
+        );
+
+        assert!(matches!(actual, Err(MyError::AccountNotFound(_))));
+        assert_eq!(1, book.read_records("savings").unwrap().len());
+        assert!(matches!(
+            book.transfer(
+                "wallet",
+                "wallet",
+                "2024-01-10".parse().unwrap(),
+                Decimal::ONE,
+                None
+            ),
+            Err(MyError::SameAccount(_))
+        ));
+    }
+
+    #[test]
+    fn test_transfer_between_currencies() {
+        let dir = tempfile::tempdir().unwrap();
+        let book = Book::open(dir.path()).unwrap();
+        create_accounts(&book, &["bank"]);
+        NewArgs {
+            account_name: "travel".to_string(),
+            force: false,
+            currency: Some("usd".to_string()),
+        }
+        .run(&book)
+        .unwrap();
+        let date = "2024-01-10".parse().unwrap();
+
+        let actual = book.transfer("travel", "bank", date, "10.5".parse().unwrap(), None);
+
+        assert!(matches!(actual, Err(MyError::MissingRate { .. })));
+        assert_eq!(6, actual.unwrap_err().exit_code());
+        RateArgs {
+            command: RateCommand::Add {
+                date: "2024-01-01".parse().unwrap(),
+                currency: "USD".to_string(),
+                base: "JPY".to_string(),
+                rate: "145.3".parse().unwrap(),
+            },
+        }
+        .run(&book)
+        .unwrap();
+        assert!(matches!(
+            book.transfer("travel", "bank", date, "10.505".parse().unwrap(), None),
+            Err(MyError::InvalidAmount { .. })
+        ));
+
+        book.transfer("travel", "bank", date, "10.5".parse().unwrap(), None)
+            .unwrap();
+
+        assert_eq!("-10.5", book.balance("travel").unwrap().to_string());
+        assert_eq!("1526", book.balance("bank").unwrap().to_string());
+    }
+
+    #[test]
+    fn test_post_due_posts_each_date_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let book = Book::open(dir.path()).unwrap();
+        create_accounts(&book, &["bank"]);
+        RecurringArgs {
+            command: RecurringCommand::Add(RecurringAddArgs {
+                name: "rent".to_string(),
+                account_name: "bank".to_string(),
+                usage: "家賃".to_string(),
+                amount: Decimal::from(80000),
+                withdraw: true,
+                monthly: Some(27),
+                weekly: None,
+                yearly: None,
+                start: Some("2024-01-01".parse().unwrap()),
+                end: None,
+                category: Some("住居費".to_string()),
+                tags: vec![],
+            }),
+        }
+        .run(&book)
+        .unwrap();
+        let post_due = |date: &str| PostDueArgs {
+            date: Some(date.parse().unwrap()),
+            dry_run: false,
+        };
+
+        post_due("2024-02-27").run(&book).unwrap();
+        post_due("2024-02-28").run(&book).unwrap();
+
+        let dates: Vec<String> = book
+            .read_records("bank")
+            .unwrap()
+            .iter()
+            .map(|record| record.日付.to_string())
+            .collect();
+        assert_eq!(vec!["2024-01-27", "2024-02-27"], dates);
+        assert_eq!(Decimal::from(-160000), book.balance("bank").unwrap());
+
+        // 過去の日付を指定しても、記帳済みの日付は戻らない
+        post_due("2024-01-30").run(&book).unwrap();
+        assert_eq!(
+            Some("2024-02-28".parse().unwrap()),
+            book.load_config().unwrap().recurring["rent"].posted_until
+        );
+
+        // NOTE: 記帳済みの日付を保存できなかった場合も、取引番号で二重の記帳を防ぐ
+        let mut config = book.load_config().unwrap();
+        config.recurring.get_mut("rent").unwrap().posted_until = None;
+        book.save_config(&config).unwrap();
+        post_due("2024-03-27").run(&book).unwrap();
+
+        assert_eq!(3, book.read_records("bank").unwrap().len());
+    }
+
+    #[test]
+    fn test_migrate_and_export() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = dir.path().join("data");
+        fs::create_dir(&data).unwrap();
+        fs::write(
+            data.join("wallet.csv"),
+            "日付,用途,金額\n2024-01-01,給与,1000\n2024-01-02,食費,-300\n",
+        )
+        .unwrap();
+        fs::write(data.join("bank.csv"), "日付,用途,金額\n").unwrap();
+        let csv_records = Book::open(&data).unwrap().read_records("wallet").unwrap();
+
+        MigrateArgs {}.run(&Book::open(&data).unwrap()).unwrap();
+
+        let book = Book::open(&data).unwrap();
+        assert!(matches!(
+            MigrateArgs {}.run(&book),
+            Err(MyError::AlreadyMigrated(_))
+        ));
+        assert_eq!(vec!["bank", "wallet"], book.accounts().unwrap());
+        assert_eq!(csv_records, book.read_records("wallet").unwrap());
+        book.transfer(
+            "wallet",
+            "bank",
+            "2024-01-03".parse().unwrap(),
+            Decimal::from(200),
+            None,
+        )
+        .unwrap();
+        assert_eq!(Decimal::from(500), book.balance("wallet").unwrap());
+
+        let export = |force| ExportArgs {
+            out_dir: dir.path().join("export"),
+            accounts: vec!["bank".to_string()],
+            force,
+        };
+        export(false).run(&book).unwrap();
+
+        assert!(matches!(
+            export(false).run(&book),
+            Err(MyError::AccountAlreadyExists(_))
+        ));
+        let content = fs::read_to_string(dir.path().join("export").join("bank.csv")).unwrap();
+        let (header, row) = content.split_once('\n').unwrap();
+        assert_eq!("日付,用途,金額,分類,タグ,振替番号,取引番号", header);
+        assert!(row.starts_with("2024-01-03,振替（walletから）,200,,,T"));
+    }
+
+    #[test]
+    fn test_post_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let book = Book::open(dir.path()).unwrap();
+        create_accounts(&book, &["bank", "wallet"]);
+        let postings = |postings: &[&str]| {
+            parse_postings(&postings.iter().map(|p| p.to_string()).collect::<Vec<_>>()).unwrap()
+        };
+        let date = "2024-01-10".parse().unwrap();
+
+        let actual = book.post_entry(date, "買い物", &postings(&["bank=-3000", "食費=2000"]));
+
+        assert!(matches!(actual, Err(MyError::InvalidPosting(_))));
+        assert!(book.read_records("bank").unwrap().is_empty());
+        assert!(parse_postings(&["食費".to_string()]).is_err());
+
+        book.post_entry(
+            date,
+            "買い物",
+            &postings(&["bank=-3000", "wallet=-500", "食費=2500", "日用品=1000"]),
+        )
+        .unwrap();
+
+        let (_, entries) = book.journal().unwrap();
+        assert_eq!(1, entries.len());
+        assert!(journal::unbalanced(&entries).is_empty());
+        let categories: Vec<(String, Decimal)> = book
+            .read_records("bank")
+            .unwrap()
+            .into_iter()
+            .chain(book.read_records("wallet").unwrap())
+            .map(|record| (record.分類, record.金額))
+            .collect();
+        assert_eq!(
+            vec![
+                ("食費".to_string(), Decimal::from(-2500)),
+                ("日用品".to_string(), Decimal::from(-500)),
+                ("日用品".to_string(), Decimal::from(-500)),
+            ],
+            categories
+        );
+    }
+
+    #[test]
+    fn test_deposit_to_missing_account() {
+        let dir = tempfile::tempdir().unwrap();
+        let args = DepositArgs {
+            account_name: "存在しない口座".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            usage: "給与".to_string(),
+            amount: Decimal::from(1000),
+            category: None,
+            tags: vec![],
+        };
+
+        let actual = args.run(&Book::open(dir.path()).unwrap());
+
+        assert!(
+            matches!(actual, Err(MyError::AccountNotFound(ref name)) if name == "存在しない口座")
+        );
+        assert_eq!(2, actual.unwrap_err().exit_code());
+    }
+
+    #[test]
+    fn test_budget_set_validates_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let book = Book::open(dir.path()).unwrap();
+        let set = |limit: &str| BudgetArgs {
+            command: BudgetCommand::Set {
+                category: "食費".to_string(),
+                limit: limit.parse().unwrap(),
+            },
+        };
+
+        for limit in ["0", "-1000", "30000.5"] {
+            let actual = set(limit).run(&book);
+
+            assert!(
+                matches!(actual, Err(MyError::InvalidAmount { .. })),
+                "{}",
+                limit
+            );
+        }
+        assert!(book.load_config().unwrap().budgets.is_empty());
+
+        let mut config = book.load_config().unwrap();
+        config.base_currency = Some("USD".to_string());
+        book.save_config(&config).unwrap();
+        set("300.5").run(&book).unwrap();
+        assert_eq!(
+            Some(&"300.5".parse().unwrap()),
+            book.load_config().unwrap().budgets.get("食費")
+        );
+    }
+
+    #[test]
+    fn test_remove_missing_rule_and_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let book = Book::open(dir.path()).unwrap();
+        let mut config = Config::default();
+        config.add_rule("スーパー", "食費").unwrap();
+        book.save_config(&config).unwrap();
+
+        for number in [0, 2] {
+            let actual = RuleArgs {
+                command: RuleCommand::Remove { number },
+            }
+            .run(&book);
+
+            assert!(matches!(actual, Err(MyError::UnknownRule(n)) if n == number));
+            assert_eq!(5, actual.unwrap_err().exit_code());
+        }
+        let actual = BudgetArgs {
+            command: BudgetCommand::Remove {
+                category: "食費".to_string(),
+            },
+        }
+        .run(&book);
+
+        assert!(matches!(actual, Err(MyError::UnknownBudget(ref category)) if category == "食費"));
+        assert_eq!(5, actual.unwrap_err().exit_code());
+        assert_eq!(config, book.load_config().unwrap());
+    }
+
+    #[test]
+    fn test_report_with_malformed_row() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("wallet.csv");
+        fs::write(
+            &file,
+            "日付,用途,金額\n2024-01-01,給与,1000\n2024-01-02,食費,千円\n",
+        )
+        .unwrap();
+        let args = ReportArgs {
+            files: vec![file],
+            by: report::GroupBy::Month,
+            per_account: false,
+            format: report::Format::Table,
+            tag: None,
+            currency: None,
+            html: None,
+        };
+
+        let actual = args.run(&Book::open(dir.path()).unwrap());
+
+        assert!(matches!(actual, Err(MyError::Parse { line: 3, .. })));
+        assert_eq!(3, actual.unwrap_err().exit_code());
+    }
+
+    #[test]
+    fn test_report_html_conflicts() {
+        for options in [
+            &["--format", "json"][..],
+            &["--by", "year"],
+            &["--per-account"],
+        ] {
+            let mut args = vec!["budget-book", "report", "--html", "out.html"];
+            args.extend(options);
+
+            assert!(App::try_parse_from(args).is_err(), "{:?}", options);
+        }
+        assert!(App::try_parse_from(["budget-book", "report", "--html", "out.html"]).is_ok());
     }
 }
@@ -0,0 +1,218 @@
+use crate::MyError;
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// 定期的な取引を記帳する日の決め方
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(tag = "every", rename_all = "lowercase")]
+pub enum Schedule {
+    /// 毎月 day 日（その月にない日は月末）
+    Monthly { day: u32 },
+    /// 毎週 weekday 曜日
+    Weekly { weekday: Weekday },
+    /// 毎年 month 月 day 日（うるう年以外の 2 月 29 日は 2 月 28 日）
+    Yearly { month: u32, day: u32 },
+}
+impl Schedule {
+    /// 日付の指定が正しいかを確認する
+    pub fn validate(&self) -> Result<(), MyError> {
+        let valid = match *self {
+            Schedule::Monthly { day } => (1..=31).contains(&day),
+            Schedule::Weekly { .. } => true,
+            // NOTE: 2 月 29 日を許すため、うるう年で確認する
+            Schedule::Yearly { month, day } => NaiveDate::from_ymd_opt(2000, month, day).is_some(),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(MyError::InvalidSchedule(self.to_string()))
+        }
+    }
+
+    /// from から until までの、記帳する日付
+    fn dates(&self, from: NaiveDate, until: NaiveDate) -> Vec<NaiveDate> {
+        let mut dates = vec![];
+        match *self {
+            // NOTE: 表せる最後の日付を過ぎる場合は、次の日付がないので繰り返しを止める
+            Schedule::Monthly { day } => {
+                let mut month = from.with_day(1);
+                while let Some(current) = month.filter(|month| *month <= until) {
+                    dates.push(clamp_day(current.year(), current.month(), day));
+                    month = current.checked_add_months(Months::new(1));
+                }
+            }
+            Schedule::Weekly { weekday } => {
+                let offset = (7 + weekday.num_days_from_monday()
+                    - from.weekday().num_days_from_monday())
+                    % 7;
+                let mut date = from.checked_add_days(Days::new(u64::from(offset)));
+                while let Some(current) = date.filter(|date| *date <= until) {
+                    dates.push(current);
+                    date = current.checked_add_days(Days::new(7));
+                }
+            }
+            Schedule::Yearly { month, day } => {
+                for year in from.year()..=until.year() {
+                    dates.push(clamp_day(year, month, day));
+                }
+            }
+        }
+        dates.retain(|date| (from..=until).contains(date));
+        dates
+    }
+}
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schedule::Monthly { day } => write!(f, "毎月{}日", day),
+            Schedule::Weekly { weekday } => {
+                let names = ["月", "火", "水", "木", "金", "土", "日"];
+                write!(
+                    f,
+                    "毎週{}曜日",
+                    names[weekday.num_days_from_monday() as usize]
+                )
+            }
+            Schedule::Yearly { month, day } => write!(f, "毎年{}月{}日", month, day),
+        }
+    }
+}
+
+/// year 年 month 月 day 日。その月にない日の場合は月末
+fn clamp_day(year: i32, month: u32, day: u32) -> NaiveDate {
+    (1..=day)
+        .rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+        .unwrap()
+}
+
+/// 家賃や給与のように、決まった日に記帳する取引
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Recurring {
+    pub account: String,
+    pub usage: String,
+    /// 出金は負の値
+    pub amount: Decimal,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(flatten)]
+    pub schedule: Schedule,
+    /// この日以降に記帳を始める
+    pub start: NaiveDate,
+    /// この日より後は記帳しない
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<NaiveDate>,
+    /// この日までの分は記帳済み
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub posted_until: Option<NaiveDate>,
+}
+impl Recurring {
+    /// today までに記帳する日が来ていて、まだ記帳していない日付
+    pub fn due_dates(&self, today: NaiveDate) -> Vec<NaiveDate> {
+        let from = match self.posted_until.and_then(|date| date.succ_opt()) {
+            Some(next) => next.max(self.start),
+            None => self.start,
+        };
+        let until = self.end.map_or(today, |end| end.min(today));
+        if from > until {
+            return vec![];
+        }
+        self.schedule.dates(from, until)
+    }
+
+    /// 記帳した取引に付ける取引番号
+    // NOTE: 記帳の途中で失敗した場合に、同じ日の分を二重に記帳しないために使う
+    pub fn transaction_id(name: &str, date: NaiveDate) -> String {
+        format!("定期:{}:{}", name, date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn recurring(schedule: Schedule, start: &str) -> Recurring {
+        Recurring {
+            account: "bank".to_string(),
+            usage: "家賃".to_string(),
+            amount: Decimal::from(-80000),
+            category: None,
+            tags: vec![],
+            schedule,
+            start: start.parse().unwrap(),
+            end: None,
+            posted_until: None,
+        }
+    }
+
+    fn dates(dates: &[&str]) -> Vec<NaiveDate> {
+        dates.iter().map(|date| date.parse().unwrap()).collect()
+    }
+
+    #[rstest]
+    #[case(
+        Schedule::Monthly { day: 31 },
+        "2024-01-15",
+        &["2024-01-31", "2024-02-29", "2024-03-31"]
+    )]
+    #[case(Schedule::Monthly { day: 10 }, "2024-01-10", &["2024-01-10", "2024-02-10", "2024-03-10", "2024-04-10"])]
+    #[case(
+        Schedule::Weekly { weekday: Weekday::Mon },
+        "2024-04-01",
+        &["2024-04-01", "2024-04-08", "2024-04-15"]
+    )]
+    #[case(Schedule::Yearly { month: 2, day: 29 }, "2023-01-01", &["2023-02-28", "2024-02-29"])]
+    fn test_due_dates(#[case] schedule: Schedule, #[case] start: &str, #[case] expected: &[&str]) {
+        let actual = recurring(schedule, start).due_dates("2024-04-20".parse().unwrap());
+
+        assert_eq!(dates(expected), actual);
+    }
+
+    #[test]
+    fn test_due_dates_after_posting() {
+        let mut recurring = recurring(Schedule::Monthly { day: 27 }, "2024-01-01");
+        recurring.posted_until = Some("2024-02-27".parse().unwrap());
+        recurring.end = Some("2024-04-26".parse().unwrap());
+
+        let actual = recurring.due_dates("2024-05-31".parse().unwrap());
+
+        assert_eq!(dates(&["2024-03-27"]), actual);
+        recurring.posted_until = Some("2024-05-31".parse().unwrap());
+        assert_eq!(
+            Vec::<NaiveDate>::new(),
+            recurring.due_dates("2024-05-31".parse().unwrap())
+        );
+    }
+
+    // NOTE: NaiveDate::MAX は月曜日
+    #[rstest]
+    #[case(Schedule::Monthly { day: 31 }, true)]
+    #[case(Schedule::Weekly { weekday: Weekday::Mon }, true)]
+    #[case(Schedule::Weekly { weekday: Weekday::Tue }, false)]
+    #[case(Schedule::Yearly { month: 12, day: 31 }, true)]
+    fn test_due_dates_at_max_date(#[case] schedule: Schedule, #[case] expected: bool) {
+        let recurring = Recurring {
+            posted_until: NaiveDate::MAX.checked_sub_days(Days::new(1)),
+            ..recurring(schedule, "2024-01-01")
+        };
+
+        let actual = recurring.due_dates(NaiveDate::MAX);
+
+        assert_eq!(expected, actual == vec![NaiveDate::MAX]);
+        assert!(expected || actual.is_empty());
+    }
+
+    #[rstest]
+    #[case(Schedule::Monthly { day: 0 }, false)]
+    #[case(Schedule::Monthly { day: 31 }, true)]
+    #[case(Schedule::Yearly { month: 2, day: 29 }, true)]
+    #[case(Schedule::Yearly { month: 4, day: 31 }, false)]
+    fn test_validate(#[case] schedule: Schedule, #[case] expected: bool) {
+        assert_eq!(expected, schedule.validate().is_ok());
+    }
+}
@@ -0,0 +1,366 @@
+use crate::{category::UNCATEGORIZED, chart, MyError, Record};
+use clap::ValueEnum;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt::Write};
+use unicode_width::UnicodeWidthStr;
+
+/// 集計の単位
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupBy {
+    Month,
+    Year,
+    Week,
+    Usage,
+    Category,
+}
+impl GroupBy {
+    /// 取引が属する集計単位のキー
+    // NOTE: 期間のキーは文字列として並べ替えても時系列順になる形式にする
+    fn key(&self, record: &Record) -> String {
+        match self {
+            GroupBy::Month => record.日付.format("%Y-%m").to_string(),
+            GroupBy::Year => record.日付.format("%Y").to_string(),
+            GroupBy::Week => record.日付.format("%G-W%V").to_string(),
+            GroupBy::Usage => record.用途.clone(),
+            GroupBy::Category if record.分類.is_empty() => UNCATEGORIZED.to_string(),
+            GroupBy::Category => record.分類.clone(),
+        }
+    }
+
+    /// 表の見出し
+    fn label(&self) -> &'static str {
+        match self {
+            GroupBy::Month => "月",
+            GroupBy::Year => "年",
+            GroupBy::Week => "週",
+            GroupBy::Usage => "用途",
+            GroupBy::Category => "分類",
+        }
+    }
+}
+
+/// レポートの出力形式
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Table,
+    Csv,
+    Json,
+    Markdown,
+    /// 収入と支出の棒グラフ
+    Chart,
+}
+
+/// レポートの1行分の集計結果
+// NOTE: 小数の金額を誤差なく渡せるよう、JSON では金額を文字列で出力する
+#[derive(Serialize, Debug, Default, PartialEq, Eq)]
+pub struct ReportRow {
+    /// 口座ごとに集計しない場合は None
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    pub group: String,
+    /// 入金の合計
+    pub income: Decimal,
+    /// 出金の合計（正の値）
+    pub expense: Decimal,
+    pub net: Decimal,
+}
+impl ReportRow {
+    fn add(&mut self, amount: Decimal) {
+        if amount >= Decimal::ZERO {
+            self.income += amount;
+        } else {
+            self.expense -= amount;
+        }
+        self.net += amount;
+    }
+}
+
+/// 口座ごとの取引を集計する。per_account が false の場合はすべての口座を合算する
+// NOTE: 口座間の振替は収入・支出ではないので集計しない
+pub fn summarize(
+    accounts: &[(String, Vec<Record>)],
+    group_by: GroupBy,
+    per_account: bool,
+) -> Vec<ReportRow> {
+    let mut rows: BTreeMap<(Option<&str>, String), ReportRow> = BTreeMap::new();
+    for (account, records) in accounts {
+        let account = per_account.then_some(account.as_str());
+        for record in records.iter().filter(|record| !record.is_transfer()) {
+            let group = group_by.key(record);
+            rows.entry((account, group.clone()))
+                .or_insert_with(|| ReportRow {
+                    account: account.map(str::to_string),
+                    group,
+                    ..Default::default()
+                })
+                .add(record.金額);
+        }
+    }
+    rows.into_values().collect()
+}
+
+/// 集計結果を指定した形式で出力する
+pub fn render(rows: &[ReportRow], group_by: GroupBy, format: Format) -> Result<String, MyError> {
+    match format {
+        Format::Table => Ok(render_table(rows, group_by)),
+        Format::Csv => render_csv(rows, group_by),
+        Format::Json => Ok(serde_json::to_string_pretty(rows)? + "\n"),
+        Format::Markdown => Ok(render_markdown(rows, group_by)),
+        Format::Chart => Ok(chart::render_bars(rows)),
+    }
+}
+
+/// 見出しと各行のセル（合計行を含む）
+fn cells(rows: &[ReportRow], group_by: GroupBy, with_total: bool) -> Vec<Vec<String>> {
+    let per_account = rows.iter().any(|row| row.account.is_some());
+    let header = [group_by.label(), "収入", "支出", "収支"];
+    let mut cells = vec![];
+    let row_cells = |account: Option<&str>, group: &str, income, expense, net: Decimal| {
+        let mut cells = vec![];
+        if per_account {
+            cells.push(account.unwrap_or_default().to_string());
+        }
+        cells.push(group.to_string());
+        cells.extend([income, expense, net].map(|amount: Decimal| amount.to_string()));
+        cells
+    };
+
+    let mut header: Vec<String> = header.map(str::to_string).to_vec();
+    if per_account {
+        header.insert(0, "口座".to_string());
+    }
+    cells.push(header);
+    for row in rows {
+        cells.push(row_cells(
+            row.account.as_deref(),
+            &row.group,
+            row.income,
+            row.expense,
+            row.net,
+        ));
+    }
+    if with_total {
+        let sum = |amount: fn(&ReportRow) -> Decimal| rows.iter().map(amount).sum();
+        cells.push(row_cells(
+            Some("合計"),
+            if per_account { "" } else { "合計" },
+            sum(|row| row.income),
+            sum(|row| row.expense),
+            sum(|row| row.net),
+        ));
+    }
+    cells
+}
+
+/// 全角文字の幅を考慮して、列をそろえた表を出力する
+fn render_table(rows: &[ReportRow], group_by: GroupBy) -> String {
+    let cells = cells(rows, group_by, true);
+    let columns = cells[0].len();
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            cells
+                .iter()
+                .map(|row| row[column].width())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    // NOTE: 金額の列（後ろの3列）は右寄せにする
+    let amount_columns = columns - 3;
+
+    let mut output = String::new();
+    for row in &cells {
+        let line: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(column, cell)| {
+                let padding = " ".repeat(widths[column] - cell.width());
+                if column >= amount_columns {
+                    format!("{}{}", padding, cell)
+                } else {
+                    format!("{}{}", cell, padding)
+                }
+            })
+            .collect();
+        writeln!(output, "{}", line.join("  ").trim_end()).unwrap();
+    }
+    output
+}
+
+fn render_markdown(rows: &[ReportRow], group_by: GroupBy) -> String {
+    let cells = cells(rows, group_by, true);
+    let amount_columns = cells[0].len() - 3;
+
+    let mut output = String::new();
+    writeln!(output, "| {} |", cells[0].join(" | ")).unwrap();
+    let separators: Vec<&str> = (0..cells[0].len())
+        .map(|column| {
+            if column >= amount_columns {
+                "---:"
+            } else {
+                "---"
+            }
+        })
+        .collect();
+    writeln!(output, "| {} |", separators.join(" | ")).unwrap();
+    for row in &cells[1..] {
+        // NOTE: セル内の「|」は表の区切りと解釈されるのでエスケープする
+        let row: Vec<String> = row.iter().map(|cell| cell.replace('|', "\\|")).collect();
+        writeln!(output, "| {} |", row.join(" | ")).unwrap();
+    }
+    output
+}
+
+fn render_csv(rows: &[ReportRow], group_by: GroupBy) -> Result<String, MyError> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for row in cells(rows, group_by, false) {
+        writer.write_record(row)?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|error| MyError::Io(error.into_error()))?;
+    // NOTE: 書き込んだのは String から作ったセルだけなので、UTF-8 として正しい
+    Ok(String::from_utf8(bytes).expect("CSV の出力が UTF-8 ではありません"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_accounts() -> Vec<(String, Vec<Record>)> {
+        vec![
+            (
+                "wallet".to_string(),
+                vec![
+                    Record::test("2024-02-03", "食費", -3000),
+                    Record::test("2023-12-31", "お年玉", 10000),
+                    Record::test("2024-01-01", "食費", -1000),
+                ],
+            ),
+            (
+                "bank".to_string(),
+                vec![
+                    Record::test("2024-01-25", "給与", 200000),
+                    Record::test("2024-01-27", "家賃", -80000),
+                ],
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_summarize_by_month() {
+        let actual = summarize(&sample_accounts(), GroupBy::Month, false);
+
+        let row = |group: &str, income: i32, expense: i32, net: i32| ReportRow {
+            account: None,
+            group: group.to_string(),
+            income: Decimal::from(income),
+            expense: Decimal::from(expense),
+            net: Decimal::from(net),
+        };
+        assert_eq!(
+            vec![
+                row("2023-12", 10000, 0, 10000),
+                row("2024-01", 200000, 81000, 119000),
+                row("2024-02", 0, 3000, -3000),
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn test_summarize_by_week_per_account() {
+        let actual = summarize(&sample_accounts(), GroupBy::Week, true);
+
+        let keys: Vec<(Option<&str>, &str)> = actual
+            .iter()
+            .map(|row| (row.account.as_deref(), row.group.as_str()))
+            .collect();
+        assert_eq!(
+            vec![
+                (Some("bank"), "2024-W04"),
+                // NOTE: ISO 週では 2023-12-31（日曜日）と 2024-01-01（月曜日）は別の週になる
+                (Some("wallet"), "2023-W52"),
+                (Some("wallet"), "2024-W01"),
+                (Some("wallet"), "2024-W05"),
+            ],
+            keys
+        );
+    }
+
+    #[test]
+    fn test_summarize_without_transfers() {
+        let mut accounts = sample_accounts();
+        for (account, amount) in [(0, 50000), (1, -50000)] {
+            accounts[account].1.push(Record {
+                振替番号: "T1".to_string(),
+                ..Record::test("2024-01-26", "振替", amount)
+            });
+        }
+
+        assert_eq!(
+            summarize(&sample_accounts(), GroupBy::Month, true),
+            summarize(&accounts, GroupBy::Month, true)
+        );
+    }
+
+    #[test]
+    fn test_summarize_by_category() {
+        let mut accounts = sample_accounts();
+        accounts[0].1[0].分類 = "食費".to_string();
+        accounts[0].1[2].分類 = "食費".to_string();
+
+        let actual = summarize(&accounts, GroupBy::Category, false);
+
+        let groups: Vec<(&str, String)> = actual
+            .iter()
+            .map(|row| (row.group.as_str(), row.net.to_string()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("未分類", "130000".to_string()),
+                ("食費", "-4000".to_string())
+            ],
+            groups
+        );
+    }
+
+    #[test]
+    fn test_render_table() {
+        let rows = summarize(&sample_accounts(), GroupBy::Usage, false);
+
+        let actual = render(&rows, GroupBy::Usage, Format::Table).unwrap();
+
+        assert_eq!(
+            "用途      収入   支出    収支\n\
+             お年玉   10000      0   10000\n\
+             家賃         0  80000  -80000\n\
+             給与    200000      0  200000\n\
+             食費         0   4000   -4000\n\
+             合計    210000  84000  126000\n",
+            actual
+        );
+    }
+
+    #[test]
+    fn test_render_csv_json_markdown() {
+        let rows = summarize(&sample_accounts()[1..], GroupBy::Year, true);
+
+        assert_eq!(
+            "口座,年,収入,支出,収支\nbank,2024,200000,80000,120000\n",
+            render(&rows, GroupBy::Year, Format::Csv).unwrap()
+        );
+        assert_eq!(
+            "[\n  {\n    \"account\": \"bank\",\n    \"group\": \"2024\",\n    \"income\": \"200000\",\n    \"expense\": \"80000\",\n    \"net\": \"120000\"\n  }\n]\n",
+            render(&rows, GroupBy::Year, Format::Json).unwrap()
+        );
+        assert_eq!(
+            "| 口座 | 年 | 収入 | 支出 | 収支 |\n\
+             | --- | --- | ---: | ---: | ---: |\n\
+             | bank | 2024 | 200000 | 80000 | 120000 |\n\
+             | 合計 |  | 200000 | 80000 | 120000 |\n",
+            render(&rows, GroupBy::Year, Format::Markdown).unwrap()
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use crate::{store::Store, validate_account_name, MyError, Record};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use rust_decimal::Decimal;
+use std::{path::Path, time::Duration};
+
+/// データディレクトリに置く SQLite のデータベースの名前
+pub const DATABASE_FILE_NAME: &str = "budget-book.db";
+
+/// 口座と取引のテーブル
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS accounts (
+        name TEXT PRIMARY KEY NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS records (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        account TEXT NOT NULL REFERENCES accounts (name) ON DELETE CASCADE,
+        date TEXT NOT NULL,
+        usage TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        category TEXT NOT NULL DEFAULT '',
+        tags TEXT NOT NULL DEFAULT '',
+        transfer_id TEXT NOT NULL DEFAULT '',
+        transaction_id TEXT NOT NULL DEFAULT ''
+    );
+    CREATE INDEX IF NOT EXISTS records_account_date ON records (account, date);
+";
+
+/// 口座と取引を SQLite のデータベースに置く保存先
+pub struct SqliteStore {
+    connection: Connection,
+}
+impl SqliteStore {
+    /// データベースを開く。ファイルがない場合は作る
+    pub fn open(path: &Path) -> Result<Self, MyError> {
+        let connection = Connection::open(path)?;
+        // NOTE: 別のプロセスが書き込み中の場合は、すぐに失敗せず少し待つ
+        connection.busy_timeout(Duration::from_secs(5))?;
+        connection.execute_batch("PRAGMA foreign_keys = ON;")?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(SqliteStore { connection })
+    }
+
+    /// 口座があることを確認する
+    fn ensure_account(transaction: &Transaction, account_name: &str) -> Result<(), MyError> {
+        transaction
+            .query_row(
+                "SELECT 1 FROM accounts WHERE name = ?1",
+                [account_name],
+                |_| Ok(()),
+            )
+            .optional()?
+            .ok_or_else(|| MyError::AccountNotFound(account_name.to_string()))
+    }
+
+    fn insert(
+        transaction: &Transaction,
+        account_name: &str,
+        record: &Record,
+    ) -> Result<(), MyError> {
+        transaction.execute(
+            "INSERT INTO records
+                 (account, date, usage, amount, category, tags, transfer_id, transaction_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                account_name,
+                record.日付,
+                record.用途,
+                // NOTE: 小数の金額を誤差なく保存するため、文字列で保存する
+                record.金額.to_string(),
+                record.分類,
+                record.タグ,
+                record.振替番号,
+                record.取引番号,
+            ],
+        )?;
+        Ok(())
+    }
+}
+impl Store for SqliteStore {
+    fn create_account(&self, account_name: &str, force: bool) -> Result<(), MyError> {
+        validate_account_name(account_name)?;
+        let transaction = self.connection.unchecked_transaction()?;
+        let inserted = transaction.execute(
+            "INSERT OR IGNORE INTO accounts (name) VALUES (?1)",
+            [account_name],
+        )?;
+        if inserted == 0 {
+            if !force {
+                return Err(MyError::AccountAlreadyExists(account_name.to_string()));
+            }
+            transaction.execute("DELETE FROM records WHERE account = ?1", [account_name])?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn accounts(&self) -> Result<Vec<String>, MyError> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT name FROM accounts ORDER BY name")?;
+        let names = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        Ok(names)
+    }
+
+    fn read_records(&self, account_name: &str) -> Result<Vec<Record>, MyError> {
+        validate_account_name(account_name)?;
+        let transaction = self.connection.unchecked_transaction()?;
+        Self::ensure_account(&transaction, account_name)?;
+        let mut statement = transaction.prepare(
+            "SELECT id, date, usage, amount, category, tags, transfer_id, transaction_id
+             FROM records WHERE account = ?1 ORDER BY id",
+        )?;
+        let mut rows = statement.query([account_name])?;
+        let mut records = vec![];
+        while let Some(row) = rows.next()? {
+            let amount: String = row.get(3)?;
+            let amount = amount.parse::<Decimal>().map_err(|error| MyError::Parse {
+                file: DATABASE_FILE_NAME.to_string(),
+                line: row.get(0).unwrap_or_default(),
+                reason: format!("金額「{}」が不正です：{}", amount, error),
+            })?;
+            records.push(Record {
+                日付: row.get(1)?,
+                用途: row.get(2)?,
+                金額: amount,
+                分類: row.get(4)?,
+                タグ: row.get(5)?,
+                振替番号: row.get(6)?,
+                取引番号: row.get(7)?,
+            });
+        }
+        Ok(records)
+    }
+
+    fn append_records(&self, account_name: &str, records: &[Record]) -> Result<(), MyError> {
+        let transaction = self.connection.unchecked_transaction()?;
+        Self::ensure_account(&transaction, account_name)?;
+        for record in records {
+            Self::insert(&transaction, account_name, record)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn append_linked(&self, entries: &[(&str, Record)]) -> Result<(), MyError> {
+        // NOTE: コミットする前に失敗した場合は、トランザクションを破棄するときにすべて取り消される
+        let transaction = self.connection.unchecked_transaction()?;
+        for (account_name, record) in entries {
+            Self::ensure_account(&transaction, account_name)?;
+            Self::insert(&transaction, account_name, record)?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accounts_and_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SqliteStore::open(&dir.path().join(DATABASE_FILE_NAME)).unwrap();
+        store.create_account("wallet", false).unwrap();
+        store.create_account("bank", false).unwrap();
+        // NOTE: 分類・タグ・取引番号も保存されることを確かめる
+        let records = vec![
+            Record {
+                タグ: "旅行;家族".to_string(),
+                取引番号: "A001".to_string(),
+                ..Record::test_categorized(
+                    "2024-01-10",
+                    "カフェ",
+                    "-4.50".parse::<Decimal>().unwrap(),
+                    "食費",
+                )
+            },
+            Record::test_categorized("2024-01-10", "給与", 1000, "収入"),
+        ];
+
+        store.append_records("wallet", &records).unwrap();
+
+        assert_eq!(vec!["bank", "wallet"], store.accounts().unwrap());
+        assert_eq!(records, store.read_records("wallet").unwrap());
+        assert!(matches!(
+            store.create_account("wallet", false),
+            Err(MyError::AccountAlreadyExists(_))
+        ));
+        store.create_account("wallet", true).unwrap();
+        assert_eq!(Vec::<Record>::new(), store.read_records("wallet").unwrap());
+    }
+
+    #[test]
+    fn test_append_linked_to_missing_account() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SqliteStore::open(&dir.path().join(DATABASE_FILE_NAME)).unwrap();
+        store.create_account("savings", false).unwrap();
+
+        let actual = store.append_linked(&[
+            ("savings", Record::test("2024-01-10", "振替", -100)),
+            ("wallet", Record::test("2024-01-10", "振替", 100)),
+        ]);
+
+        assert!(matches!(actual, Err(MyError::AccountNotFound(_))));
+        assert_eq!(0, store.read_records("savings").unwrap().len());
+        assert!(matches!(
+            store.read_records("wallet"),
+            Err(MyError::AccountNotFound(_))
+        ));
+    }
+}
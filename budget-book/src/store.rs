@@ -0,0 +1,213 @@
+use crate::{read_records, validate_account_name, MyError, Record};
+use csv::{Writer, WriterBuilder};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+/// 口座のファイルのヘッダー
+pub const HEADER: [&str; 7] = [
+    "日付",
+    "用途",
+    "金額",
+    "分類",
+    "タグ",
+    "振替番号",
+    "取引番号",
+];
+
+/// 口座と取引の保存先
+pub trait Store {
+    /// 口座を作る。既存の口座は force が指定された場合のみ空にする
+    fn create_account(&self, account_name: &str, force: bool) -> Result<(), MyError>;
+
+    /// 口座の名前（名前順）
+    fn accounts(&self) -> Result<Vec<String>, MyError>;
+
+    /// 口座の取引をすべて読み取る
+    fn read_records(&self, account_name: &str) -> Result<Vec<Record>, MyError>;
+
+    /// 口座の末尾に取引を書き込む
+    fn append_records(&self, account_name: &str, records: &[Record]) -> Result<(), MyError>;
+
+    /// 複数の口座に取引を書き込む。途中で失敗した場合は、どの口座にも書き込まない
+    fn append_linked(&self, entries: &[(&str, Record)]) -> Result<(), MyError>;
+}
+
+/// 口座ごとに `<口座名>.csv` を置く保存先
+pub struct CsvStore {
+    dir: PathBuf,
+}
+impl CsvStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        CsvStore { dir: dir.into() }
+    }
+
+    /// 口座のファイルのパス
+    pub fn account_path(&self, account_name: &str) -> Result<PathBuf, MyError> {
+        validate_account_name(account_name)?;
+        Ok(self.dir.join(format!("{}.csv", account_name)))
+    }
+
+    /// 既存の口座のファイルを追記用に開く
+    fn open_account(&self, account_name: &str) -> Result<File, MyError> {
+        let path = self.account_path(account_name)?;
+        // NOTE: ヘッダーの書き換えはファイルを置き換えるので、追記用に開く前に行う
+        // NOTE: 追記用に開く場合はファイルを作らないので、存在しない口座は NotFound になる
+        upgrade_header(&path)
+            .and_then(|_| OpenOptions::new().append(true).open(&path))
+            .map_err(|error| match error.kind() {
+                ErrorKind::NotFound => MyError::AccountNotFound(account_name.to_string()),
+                _ => MyError::Io(error),
+            })
+    }
+}
+impl Store for CsvStore {
+    fn create_account(&self, account_name: &str, force: bool) -> Result<(), MyError> {
+        let path = self.account_path(account_name)?;
+        fs::create_dir_all(&self.dir)?;
+        let mut options = OpenOptions::new();
+        options.write(true);
+        if force {
+            options.create(true).truncate(true);
+        } else {
+            // NOTE: 存在確認と作成の間に別のプロセスが作った場合にも上書きしないよう、create_new で開く
+            options.create_new(true);
+        }
+        let file = options.open(path).map_err(|error| match error.kind() {
+            ErrorKind::AlreadyExists => MyError::AccountAlreadyExists(account_name.to_string()),
+            _ => MyError::Io(error),
+        })?;
+        let mut writer = Writer::from_writer(file);
+        writer.write_record(HEADER)?; // ヘッダーを書き込む
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn accounts(&self) -> Result<Vec<String>, MyError> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            // NOTE: まだ口座を1つも作っていない場合はディレクトリが存在しない
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(error.into()),
+        };
+        let mut names = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "csv") {
+                if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    if validate_account_name(name).is_ok() {
+                        names.push(name.to_string());
+                    }
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn read_records(&self, account_name: &str) -> Result<Vec<Record>, MyError> {
+        let path = self.account_path(account_name)?;
+        read_records(&path).map_err(|error| match error {
+            MyError::Csv(error)
+                if matches!(error.kind(), csv::ErrorKind::Io(io) if io.kind() == ErrorKind::NotFound) =>
+            {
+                MyError::AccountNotFound(account_name.to_string())
+            }
+            error => error,
+        })
+    }
+
+    fn append_records(&self, account_name: &str, records: &[Record]) -> Result<(), MyError> {
+        let mut open_option = self.open_account(account_name)?;
+        write_records(&mut open_option, records)
+    }
+
+    fn append_linked(&self, entries: &[(&str, Record)]) -> Result<(), MyError> {
+        // NOTE: 書き込みを始める前にすべての口座を開き、存在しない口座があれば何も書き込まない
+        let mut files = vec![];
+        for (account_name, record) in entries {
+            files.push((self.open_account(account_name)?, record));
+        }
+        append_linked(files)
+    }
+}
+
+/// 取引をヘッダーなしで書き込む
+fn write_records(file: &mut File, records: &[Record]) -> Result<(), MyError> {
+    let mut writer = WriterBuilder::new()
+        .has_headers(false) // 1行目のヘッダーをスキップ
+        .from_writer(file);
+    for record in records {
+        writer.serialize(record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// 複数のファイルに取引を書き込む。途中で失敗した場合は、すべてのファイルを書き込む前の長さに戻す
+fn append_linked(mut entries: Vec<(File, &Record)>) -> Result<(), MyError> {
+    let mut lengths = vec![];
+    for (file, _) in &entries {
+        lengths.push(file.metadata()?.len());
+    }
+    for i in 0..entries.len() {
+        let (file, record) = &mut entries[i];
+        if let Err(error) = write_records(file, std::slice::from_ref(*record)) {
+            for (j, length) in lengths.iter().enumerate().take(i) {
+                entries[j].0.set_len(*length)?;
+            }
+            // NOTE: 失敗したファイルは書き込めない状態かもしれないので、戻せなくてもエラーにしない
+            let _ = entries[i].0.set_len(lengths[i]);
+            return Err(error);
+        }
+    }
+    Ok(())
+}
+
+/// 列が足りない古い形式の口座のヘッダーを、現在の形式に書き換える
+// NOTE: 追記する行は現在の形式になるので、ヘッダーだけ先に列を増やしておけば読み取れる
+fn upgrade_header(path: &Path) -> std::io::Result<()> {
+    let content = fs::read_to_string(path)?;
+    let (header, rest) = content.split_once('\n').unwrap_or((&content, ""));
+    let columns: Vec<&str> = header.trim_end().split(',').collect();
+    if columns.len() >= HEADER.len() || columns != HEADER[..columns.len()] {
+        return Ok(());
+    }
+    let temporary = path.with_extension("csv.tmp");
+    fs::write(&temporary, format!("{}\n{}", HEADER.join(","), rest))?;
+    fs::rename(temporary, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_linked_rolls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CsvStore::new(dir.path());
+        store.create_account("savings", false).unwrap();
+        store.create_account("wallet", false).unwrap();
+        let before = fs::read_to_string(store.account_path("savings").unwrap()).unwrap();
+        // NOTE: 読み取り専用で開いたファイルへの書き込みは必ず失敗する
+        let read_only = File::open(store.account_path("wallet").unwrap()).unwrap();
+        let (withdrawal, deposit) = (
+            Record::test("2024-01-10", "振替", -100),
+            Record::test("2024-01-10", "振替", 100),
+        );
+
+        let actual = append_linked(vec![
+            (store.open_account("savings").unwrap(), &withdrawal),
+            (read_only, &deposit),
+        ]);
+
+        assert!(matches!(actual, Err(MyError::Csv(_)) | Err(MyError::Io(_))));
+        assert_eq!(
+            before,
+            fs::read_to_string(store.account_path("savings").unwrap()).unwrap()
+        );
+    }
+}
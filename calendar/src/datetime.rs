@@ -0,0 +1,220 @@
+use crate::MyError;
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+/// 現在時刻の取得元
+// NOTE: 相対的な日時（明日、来週など）の解釈を現在時刻に依存させず、テストで固定できるようにする
+pub trait Clock {
+    fn now(&self) -> NaiveDateTime;
+}
+
+/// 実際の時計（ローカル時刻）
+pub struct SystemClock;
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        Local::now().naive_local()
+    }
+}
+
+/// 常に同じ時刻を返す時計
+#[cfg(test)]
+pub struct FixedClock(pub NaiveDateTime);
+#[cfg(test)]
+impl Clock for FixedClock {
+    fn now(&self) -> NaiveDateTime {
+        self.0
+    }
+}
+
+/// 日時の入力を解釈する
+///
+/// 次の形式を受け付ける（日付と時刻は空白で区切る）
+/// - `2024-01-01T19:00:00`、`2024-01-01 19:00`、`2024/01/01 19:00`、`01/15 19:00`（年は現在の年）
+/// - `today 19:00`、`tomorrow 19:00`、`今日 19:00`、`明日 19:00`、`明後日 19:00`
+/// - `tue 18:30`（今日以降で最も近い火曜日）、`next tue 18:30`（来週の火曜日）
+/// - `19:00`（今日）
+pub fn parse_date_time(input: &str, clock: &dyn Clock) -> Result<NaiveDateTime, MyError> {
+    let input = input.trim();
+    if let Ok(date_time) = input.parse::<NaiveDateTime>() {
+        return Ok(date_time);
+    }
+    let invalid = || MyError::InvalidDateTime(input.to_string());
+
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let (time, date_words) = words.split_last().ok_or_else(invalid)?;
+    let time = parse_time(time).ok_or_else(invalid)?;
+    let today = clock.now().date();
+    let date = match date_words {
+        [] => today,
+        [word] => parse_date(word, today).ok_or_else(invalid)?,
+        [next, word] if next.eq_ignore_ascii_case("next") => {
+            let weekday = parse_weekday(word).ok_or_else(invalid)?;
+            // NOTE: 来週の月曜日から数えて該当する曜日にする
+            let next_monday =
+                today + Duration::days(7 - today.weekday().num_days_from_monday() as i64);
+            next_monday + Duration::days(weekday.num_days_from_monday() as i64)
+        }
+        _ => return Err(invalid()),
+    };
+    Ok(date.and_time(time))
+}
+
+/// 終了時刻の入力を解釈する（時刻だけの場合は開始日の時刻とする）
+pub fn parse_end(
+    input: &str,
+    start: NaiveDateTime,
+    clock: &dyn Clock,
+) -> Result<NaiveDateTime, MyError> {
+    match parse_time(input.trim()) {
+        Some(time) => Ok(start.date().and_time(time)),
+        None => parse_date_time(input, clock),
+    }
+}
+
+/// 期間の入力を解釈する（`90m`、`2h`、`1h30m`、`90`（分））
+pub fn parse_duration(input: &str) -> Result<Duration, MyError> {
+    let invalid = || MyError::InvalidDuration(input.to_string());
+    let input = input.trim();
+    if let Ok(minutes) = input.parse::<u32>() {
+        return match minutes {
+            0 => Err(invalid()),
+            minutes => Ok(Duration::minutes(minutes as i64)),
+        };
+    }
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let value: i64 = number.parse().map_err(|_| invalid())?;
+        // NOTE: 表せないほど長い期間は、パニックせずに不正な入力として扱う
+        let duration = match c {
+            'h' => Duration::try_hours(value),
+            'm' => Duration::try_minutes(value),
+            _ => return Err(invalid()),
+        };
+        total = duration
+            .and_then(|duration| total.checked_add(&duration))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+    if !number.is_empty() || total.is_zero() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+fn parse_time(input: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(input, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(input, "%H:%M"))
+        .ok()
+}
+
+fn parse_date(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match input.to_ascii_lowercase().as_str() {
+        "today" | "今日" => return Some(today),
+        "tomorrow" | "明日" => return Some(today + Duration::days(1)),
+        "明後日" => return Some(today + Duration::days(2)),
+        _ => {}
+    }
+    if let Some(weekday) = parse_weekday(input) {
+        let days =
+            (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+        return Some(today + Duration::days(days as i64));
+    }
+    NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(input, "%Y/%m/%d"))
+        .or_else(|_| NaiveDate::parse_from_str(&format!("{}/{}", today.year(), input), "%Y/%m/%d"))
+        .ok()
+}
+
+fn parse_weekday(input: &str) -> Option<Weekday> {
+    // NOTE: chrono の Weekday は "tue" や "Tuesday" などの英語表記を解釈できる
+    input.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::naive_date_time;
+    use rstest::rstest;
+
+    /// 2024-01-03（水）12:00
+    fn clock() -> FixedClock {
+        FixedClock(naive_date_time(2024, 1, 3, 12, 0, 0))
+    }
+
+    #[rstest]
+    #[case("2024-01-01T19:00:00", naive_date_time(2024, 1, 1, 19, 0, 0))]
+    #[case("2024-01-01 19:00", naive_date_time(2024, 1, 1, 19, 0, 0))]
+    #[case("2024/02/10 09:15:30", naive_date_time(2024, 2, 10, 9, 15, 30))]
+    #[case("03/15 19:00", naive_date_time(2024, 3, 15, 19, 0, 0))]
+    #[case("19:00", naive_date_time(2024, 1, 3, 19, 0, 0))]
+    #[case("today 8:00", naive_date_time(2024, 1, 3, 8, 0, 0))]
+    #[case("tomorrow 19:00", naive_date_time(2024, 1, 4, 19, 0, 0))]
+    #[case("明日 19:00", naive_date_time(2024, 1, 4, 19, 0, 0))]
+    #[case("明後日 19:00", naive_date_time(2024, 1, 5, 19, 0, 0))]
+    #[case("wed 18:30", naive_date_time(2024, 1, 3, 18, 30, 0))]
+    #[case("tue 18:30", naive_date_time(2024, 1, 9, 18, 30, 0))]
+    #[case("Friday 18:30", naive_date_time(2024, 1, 5, 18, 30, 0))]
+    #[case("next tue 18:30", naive_date_time(2024, 1, 9, 18, 30, 0))]
+    #[case("next fri 18:30", naive_date_time(2024, 1, 12, 18, 30, 0))]
+    #[case("next mon 9:00", naive_date_time(2024, 1, 8, 9, 0, 0))]
+    fn test_parse_date_time(#[case] input: &str, #[case] expected: NaiveDateTime) {
+        assert_eq!(expected, parse_date_time(input, &clock()).unwrap());
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("tomorrow")]
+    #[case("someday 19:00")]
+    #[case("next tomorrow 19:00")]
+    #[case("2024-13-01 19:00")]
+    #[case("tomorrow 25:00")]
+    fn test_parse_date_time_invalid(#[case] input: &str) {
+        assert!(matches!(
+            parse_date_time(input, &clock()),
+            Err(MyError::InvalidDateTime(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_end() {
+        let start = naive_date_time(2024, 1, 4, 19, 0, 0);
+        assert_eq!(
+            naive_date_time(2024, 1, 4, 20, 30, 0),
+            parse_end("20:30", start, &clock()).unwrap()
+        );
+        assert_eq!(
+            naive_date_time(2024, 1, 5, 1, 0, 0),
+            parse_end("fri 1:00", start, &clock()).unwrap()
+        );
+    }
+
+    #[rstest]
+    #[case("90m", Duration::minutes(90))]
+    #[case("90", Duration::minutes(90))]
+    #[case("2h", Duration::hours(2))]
+    #[case("1h30m", Duration::minutes(90))]
+    fn test_parse_duration(#[case] input: &str, #[case] expected: Duration) {
+        assert_eq!(expected, parse_duration(input).unwrap());
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("0")]
+    #[case("0m")]
+    #[case("h")]
+    #[case("1h30")]
+    #[case("1d")]
+    #[case("99999999999999h")]
+    #[case("99999999999999999999m")]
+    fn test_parse_duration_invalid(#[case] input: &str) {
+        assert!(matches!(
+            parse_duration(input),
+            Err(MyError::InvalidDuration(_))
+        ));
+    }
+}
@@ -0,0 +1,617 @@
+use crate::{
+    ics, server::SharedStore, storage::WriteOutcome, Calendar, ConflictScope, MyError, Schedule,
+    DEFAULT_CALENDAR,
+};
+use actix_web::{http::Method, web, HttpRequest, HttpResponse};
+use chrono::{NaiveDateTime, Utc};
+use std::{collections::BTreeSet, fmt::Write};
+
+/// CalDAV のルート（プリンシパルを兼ねる）
+const ROOT: &str = "/dav/";
+/// カレンダーの一覧を持つコレクション
+const HOME: &str = "/dav/calendars/";
+
+/// CalDAV (RFC 4791) の最小限のエンドポイントを登録する
+pub fn configure(config: &mut web::ServiceConfig) {
+    let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+    let report = Method::from_bytes(b"REPORT").unwrap();
+
+    for path in ["/dav", ROOT] {
+        config.service(
+            web::resource(path)
+                .route(web::route().method(Method::OPTIONS).to(options))
+                .route(web::route().method(propfind.clone()).to(propfind_root)),
+        );
+    }
+    for path in ["/dav/calendars", HOME] {
+        config.service(
+            web::resource(path)
+                .route(web::route().method(Method::OPTIONS).to(options))
+                .route(web::route().method(propfind.clone()).to(propfind_home)),
+        );
+    }
+    for path in ["/dav/calendars/{calendar}", "/dav/calendars/{calendar}/"] {
+        config.service(
+            web::resource(path)
+                .route(web::route().method(Method::OPTIONS).to(options))
+                .route(web::route().method(propfind.clone()).to(propfind_calendar))
+                .route(web::route().method(report.clone()).to(report_calendar)),
+        );
+    }
+    config.service(
+        web::resource("/dav/calendars/{calendar}/{resource}")
+            .route(web::route().method(Method::OPTIONS).to(options))
+            .route(web::get().to(get_event))
+            .route(web::put().to(put_event))
+            .route(web::delete().to(delete_event)),
+    );
+}
+
+async fn options() -> HttpResponse {
+    HttpResponse::Ok()
+        .append_header(("DAV", "1, calendar-access"))
+        .append_header(("Allow", "OPTIONS, GET, PUT, DELETE, PROPFIND, REPORT"))
+        .finish()
+}
+
+async fn propfind_root(request: HttpRequest) -> HttpResponse {
+    let mut responses = vec![root_response()];
+    if depth(&request) > 0 {
+        responses.push(home_response());
+    }
+    multistatus(&responses)
+}
+
+async fn propfind_home(store: web::Data<SharedStore>, request: HttpRequest) -> HttpResponse {
+    let mut responses = vec![home_response()];
+    if depth(&request) > 0 {
+        let calendar = match read_calendar(&store) {
+            Ok(calendar) => calendar,
+            Err(error) => return internal_error(error),
+        };
+        for name in calendar_names(&calendar) {
+            responses.push(collection_response(&calendar, &name));
+        }
+    }
+    multistatus(&responses)
+}
+
+async fn propfind_calendar(
+    store: web::Data<SharedStore>,
+    request: HttpRequest,
+    path: web::Path<String>,
+) -> HttpResponse {
+    let name = path.into_inner();
+    let calendar = match read_calendar(&store) {
+        Ok(calendar) => calendar,
+        Err(error) => return internal_error(error),
+    };
+    if !calendar_names(&calendar).contains(&name) {
+        return HttpResponse::NotFound().finish();
+    }
+
+    let mut responses = vec![collection_response(&calendar, &name)];
+    if depth(&request) > 0 {
+        for schedule in schedules_in(&calendar, &name) {
+            responses.push(event_response(schedule, false));
+        }
+    }
+    multistatus(&responses)
+}
+
+async fn report_calendar(
+    store: web::Data<SharedStore>,
+    path: web::Path<String>,
+    body: String,
+) -> HttpResponse {
+    let name = path.into_inner();
+    let calendar = match read_calendar(&store) {
+        Ok(calendar) => calendar,
+        Err(error) => return internal_error(error),
+    };
+    let include_data = body.contains("calendar-data");
+
+    let responses: Vec<String> = if body.contains("calendar-multiget") {
+        // 指定されたリソースだけを返す
+        elements(&body, "href")
+            .into_iter()
+            .map(|href| percent_decode(href.trim()))
+            .map(|href| {
+                let resource = href.rsplit('/').next().unwrap_or_default().to_string();
+                match find_event(&calendar, &name, &resource) {
+                    Some(schedule) => event_response(schedule, include_data),
+                    None => not_found_response(&href),
+                }
+            })
+            .collect()
+    } else {
+        // calendar-query：time-range の条件に合う予定を返す
+        let (start, end) = time_range(&body);
+        schedules_in(&calendar, &name)
+            .filter(|schedule| {
+                start.is_none_or(|start| start < schedule.end)
+                    && end.is_none_or(|end| schedule.start < end)
+            })
+            .map(|schedule| event_response(schedule, include_data))
+            .collect()
+    };
+    multistatus(&responses)
+}
+
+async fn get_event(
+    store: web::Data<SharedStore>,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let (name, resource) = path.into_inner();
+    let calendar = match read_calendar(&store) {
+        Ok(calendar) => calendar,
+        Err(error) => return internal_error(error),
+    };
+    match find_event(&calendar, &name, &resource) {
+        Some(schedule) => HttpResponse::Ok()
+            .content_type("text/calendar; charset=utf-8")
+            .append_header(("ETag", etag(schedule)))
+            .body(ics::render_calendar([schedule], Utc::now().naive_utc())),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+async fn put_event(
+    store: web::Data<SharedStore>,
+    request: HttpRequest,
+    path: web::Path<(String, String)>,
+    body: String,
+) -> HttpResponse {
+    let (name, resource) = path.into_inner();
+    let mut schedule = match ics::parse_event(&body) {
+        Ok(schedule) => schedule,
+        Err(error) => return HttpResponse::BadRequest().body(error.to_string()),
+    };
+    if schedule.end < schedule.start {
+        return HttpResponse::BadRequest().body("終了時刻が開始時刻より前です");
+    }
+    // NOTE: 書き出し時にカレンダー名を CATEGORIES の先頭に入れているので取り除く
+    schedule.tags.retain(|tag| tag != &name);
+    schedule.calendar = name.clone();
+    schedule.resource = Some(resource.clone());
+
+    let mut store = store.lock().unwrap();
+    let calendar = match store.read_calendar() {
+        Ok(calendar) => calendar,
+        Err(error) => return internal_error(error),
+    };
+    let existing = find_event(&calendar, &name, &resource);
+    if let Some(response) = check_preconditions(&request, existing) {
+        return response;
+    }
+
+    // NOTE: REST API や add と同じく、同じ会議室を予約した予定とだけ重複を確認する
+    let (created, result) = match existing {
+        Some(existing) => {
+            schedule.id = existing.id;
+            schedule.remind_before = existing.remind_before;
+            schedule.rooms = existing.rooms.clone();
+            (false, store.update_schedule(schedule, ConflictScope::Room))
+        }
+        None => (true, store.add_schedule(schedule, ConflictScope::Room)),
+    };
+    match result {
+        Ok(WriteOutcome::Saved(schedule)) => {
+            let mut response = if created {
+                HttpResponse::Created()
+            } else {
+                HttpResponse::NoContent()
+            };
+            response.append_header(("ETag", etag(&schedule))).finish()
+        }
+        Ok(WriteOutcome::Conflict(conflicts)) => {
+            let subjects: Vec<String> = conflicts
+                .iter()
+                .map(|schedule| {
+                    format!(
+                        "{} ({}〜{})",
+                        schedule.subject, schedule.start, schedule.end
+                    )
+                })
+                .collect();
+            HttpResponse::Conflict().body(format!("予定が重複しています：{}", subjects.join(", ")))
+        }
+        Ok(WriteOutcome::NotFound) => HttpResponse::NotFound().finish(),
+        Err(error) => internal_error(error),
+    }
+}
+
+async fn delete_event(
+    store: web::Data<SharedStore>,
+    request: HttpRequest,
+    path: web::Path<(String, String)>,
+) -> HttpResponse {
+    let (name, resource) = path.into_inner();
+    let mut store = store.lock().unwrap();
+    let calendar = match store.read_calendar() {
+        Ok(calendar) => calendar,
+        Err(error) => return internal_error(error),
+    };
+    let Some(existing) = find_event(&calendar, &name, &resource) else {
+        return HttpResponse::NotFound().finish();
+    };
+    if let Some(response) = check_preconditions(&request, Some(existing)) {
+        return response;
+    }
+    match store.delete_schedule(existing.id) {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().finish(),
+        Err(error) => internal_error(error),
+    }
+}
+
+/// If-Match / If-None-Match を満たさない場合は 412 を返す
+fn check_preconditions(request: &HttpRequest, existing: Option<&Schedule>) -> Option<HttpResponse> {
+    let header = |name: &str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    };
+    let current = existing.map(etag);
+    let failed = match (header("If-Match"), header("If-None-Match")) {
+        (Some("*"), _) => current.is_none(),
+        (Some(expected), _) => current.as_deref() != Some(expected),
+        (None, Some("*")) => current.is_some(),
+        (None, Some(expected)) => current.as_deref() == Some(expected),
+        (None, None) => false,
+    };
+    failed.then(|| HttpResponse::PreconditionFailed().finish())
+}
+
+fn read_calendar(store: &SharedStore) -> Result<Calendar, MyError> {
+    store.lock().unwrap().read_calendar()
+}
+
+fn internal_error(error: MyError) -> HttpResponse {
+    HttpResponse::InternalServerError().body(error.to_string())
+}
+
+/// 予定が1件もないカレンダーも含めたカレンダー名の一覧
+fn calendar_names(calendar: &Calendar) -> BTreeSet<String> {
+    calendar
+        .schedules
+        .iter()
+        .map(|schedule| schedule.calendar.clone())
+        .chain([DEFAULT_CALENDAR.to_string()])
+        .collect()
+}
+
+fn schedules_in<'a>(calendar: &'a Calendar, name: &'a str) -> impl Iterator<Item = &'a Schedule> {
+    calendar
+        .schedules
+        .iter()
+        .filter(move |schedule| schedule.calendar == name)
+}
+
+fn find_event<'a>(calendar: &'a Calendar, name: &'a str, resource: &str) -> Option<&'a Schedule> {
+    schedules_in(calendar, name).find(|schedule| schedule.resource_name() == resource)
+}
+
+/// 予定の内容から ETag を作る
+fn etag(schedule: &Schedule) -> String {
+    let json = serde_json::to_string(schedule).unwrap_or_default();
+    format!("\"{:016x}\"", fnv1a(FNV_OFFSET_BASIS, json.as_bytes()))
+}
+
+/// カレンダー全体の変更を表すタグ（予定の ETag をまとめたもの）
+fn ctag(calendar: &Calendar, name: &str) -> String {
+    let hash = schedules_in(calendar, name).fold(FNV_OFFSET_BASIS, |hash, schedule| {
+        fnv1a(hash, etag(schedule).as_bytes())
+    });
+    format!("\"{:016x}\"", hash)
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// 64 ビットの FNV-1a ハッシュ
+// NOTE: DefaultHasher は Rust のバージョンによって結果が変わり、更新のたびに ETag が変わってしまうため使わない
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn depth(request: &HttpRequest) -> u32 {
+    // NOTE: infinity は 1 と同じ扱いにする
+    match request
+        .headers()
+        .get("Depth")
+        .and_then(|value| value.to_str().ok())
+    {
+        Some("0") => 0,
+        _ => 1,
+    }
+}
+
+fn calendar_href(name: &str) -> String {
+    format!("{}{}/", HOME, percent_encode(name))
+}
+
+fn event_href(schedule: &Schedule) -> String {
+    format!(
+        "{}{}",
+        calendar_href(&schedule.calendar),
+        percent_encode(&schedule.resource_name())
+    )
+}
+
+fn root_response() -> String {
+    response(
+        ROOT,
+        &format!(
+            "<d:resourcetype><d:collection/></d:resourcetype>\
+             <d:current-user-principal><d:href>{ROOT}</d:href></d:current-user-principal>\
+             <c:calendar-home-set><d:href>{HOME}</d:href></c:calendar-home-set>"
+        ),
+    )
+}
+
+fn home_response() -> String {
+    response(
+        HOME,
+        &format!(
+            "<d:resourcetype><d:collection/></d:resourcetype>\
+             <d:current-user-principal><d:href>{ROOT}</d:href></d:current-user-principal>"
+        ),
+    )
+}
+
+fn collection_response(calendar: &Calendar, name: &str) -> String {
+    let ctag = ctag(calendar, name);
+    response(
+        &calendar_href(name),
+        &format!(
+            "<d:resourcetype><d:collection/><c:calendar/></d:resourcetype>\
+             <d:displayname>{}</d:displayname>\
+             <c:supported-calendar-component-set><c:comp name=\"VEVENT\"/></c:supported-calendar-component-set>\
+             <cs:getctag>{}</cs:getctag>\
+             <d:sync-token>{}</d:sync-token>",
+            escape_xml(name),
+            escape_xml(&ctag),
+            escape_xml(&ctag)
+        ),
+    )
+}
+
+fn event_response(schedule: &Schedule, include_data: bool) -> String {
+    let mut props = format!(
+        "<d:getetag>{}</d:getetag>\
+         <d:getcontenttype>text/calendar; charset=utf-8; component=vevent</d:getcontenttype>\
+         <d:resourcetype/>",
+        escape_xml(&etag(schedule))
+    );
+    if include_data {
+        let data = ics::render_calendar([schedule], Utc::now().naive_utc());
+        write!(
+            props,
+            "<c:calendar-data>{}</c:calendar-data>",
+            escape_xml(&data)
+        )
+        .unwrap();
+    }
+    response(&event_href(schedule), &props)
+}
+
+fn response(href: &str, props: &str) -> String {
+    format!(
+        "<d:response><d:href>{}</d:href><d:propstat><d:prop>{}</d:prop>\
+         <d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+        escape_xml(href),
+        props
+    )
+}
+
+fn not_found_response(href: &str) -> String {
+    format!(
+        "<d:response><d:href>{}</d:href><d:status>HTTP/1.1 404 Not Found</d:status></d:response>",
+        escape_xml(href)
+    )
+}
+
+fn multistatus(responses: &[String]) -> HttpResponse {
+    let body = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <d:multistatus xmlns:d=\"DAV:\" xmlns:c=\"urn:ietf:params:xml:ns:caldav\" \
+         xmlns:cs=\"http://calendarserver.org/ns/\">{}</d:multistatus>\n",
+        responses.concat()
+    );
+    HttpResponse::build(actix_web::http::StatusCode::MULTI_STATUS)
+        .content_type("application/xml; charset=utf-8")
+        .body(body)
+}
+
+/// calendar-query の time-range から期間を読み取る
+fn time_range(body: &str) -> (Option<NaiveDateTime>, Option<NaiveDateTime>) {
+    let Some(tag) = start_tags(body, "time-range").into_iter().next() else {
+        return (None, None);
+    };
+    // NOTE: PUT された予定と同じく、UTC の指定は手元のタイムゾーンの時刻に変換して比較する
+    let parse = |name: &str| {
+        attribute(tag, name)
+            .and_then(|value| ics::parse_date_time(value).ok())
+            .map(|(date_time, _)| date_time)
+    };
+    (parse("start"), parse("end"))
+}
+
+/// 名前空間の接頭辞を問わず、指定した要素の中身を返す
+fn elements<'a>(xml: &'a str, local_name: &str) -> Vec<&'a str> {
+    let mut contents = Vec::new();
+    let mut rest = xml;
+    while let Some((tag, after)) = next_start_tag(rest, local_name) {
+        rest = after;
+        if tag.ends_with('/') {
+            contents.push("");
+            continue;
+        }
+        let Some(close) = find_end_tag(rest, local_name) else {
+            break;
+        };
+        contents.push(&rest[..close]);
+        rest = &rest[close..];
+    }
+    contents
+}
+
+/// 名前空間の接頭辞を問わず、指定した要素の開始タグ（< と > の間）を返す
+fn start_tags<'a>(xml: &'a str, local_name: &str) -> Vec<&'a str> {
+    let mut tags = Vec::new();
+    let mut rest = xml;
+    while let Some((tag, after)) = next_start_tag(rest, local_name) {
+        tags.push(tag);
+        rest = after;
+    }
+    tags
+}
+
+fn next_start_tag<'a>(xml: &'a str, local_name: &str) -> Option<(&'a str, &'a str)> {
+    let mut rest = xml;
+    while let Some(open) = rest.find('<') {
+        let after_open = &rest[open + 1..];
+        let close = after_open.find('>')?;
+        let tag = &after_open[..close];
+        let name = tag.split(|c: char| c.is_whitespace() || c == '/').next()?;
+        if !tag.starts_with('/') && name.rsplit(':').next() == Some(local_name) {
+            return Some((tag, &after_open[close + 1..]));
+        }
+        rest = &after_open[close + 1..];
+    }
+    None
+}
+
+fn find_end_tag(xml: &str, local_name: &str) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(open) = xml[offset..].find("</") {
+        let start = offset + open;
+        let close = xml[start..].find('>')? + start;
+        let name = xml[start + 2..close].trim();
+        if name.rsplit(':').next() == Some(local_name) {
+            return Some(start);
+        }
+        offset = close + 1;
+    }
+    None
+}
+
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let position = tag.find(&format!("{}=", name))?;
+    let rest = &tag[position + name.len() + 1..];
+    // NOTE: 属性値は引用符で囲む。それ以外の文字（マルチバイト文字を含む）で始まる値は読み取らない
+    let quote = rest.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+    let rest = &rest[quote.len_utf8()..];
+    rest.find(quote).map(|end| &rest[..end])
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn percent_encode(text: &str) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'@' => {
+                encoded.push(byte as char)
+            }
+            _ => write!(encoded, "%{:02X}", byte).unwrap(),
+        }
+    }
+    encoded
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            if let Some(byte) = text
+                .get(index + 1..index + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                decoded.push(byte);
+                index += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn test_elements_ignore_namespace_prefix() {
+        let xml = r#"<C:calendar-multiget xmlns:D="DAV:" xmlns:C="urn:ietf:params:xml:ns:caldav">
+            <D:prop><D:getetag/><C:calendar-data/></D:prop>
+            <D:href>/dav/calendars/team/a.ics</D:href>
+            <href xmlns="DAV:">/dav/calendars/team/b%20c.ics</href>
+        </C:calendar-multiget>"#;
+
+        let hrefs = elements(xml, "href");
+
+        assert_eq!(
+            vec!["/dav/calendars/team/a.ics", "/dav/calendars/team/b%20c.ics"],
+            hrefs
+        );
+        assert_eq!(vec![""], elements(xml, "calendar-data"));
+        assert_eq!("b c.ics", percent_decode("b%20c.ics"));
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        // NOTE: FNV-1a の公開されているテストベクトル
+        assert_eq!(0xcbf2_9ce4_8422_2325, fnv1a(FNV_OFFSET_BASIS, b""));
+        assert_eq!(0xaf63_dc4c_8601_ec8c, fnv1a(FNV_OFFSET_BASIS, b"a"));
+        assert_eq!(0x8594_4171_f739_67e8, fnv1a(FNV_OFFSET_BASIS, b"foobar"));
+    }
+
+    #[test]
+    fn test_time_range() {
+        let xml = r#"<c:time-range start="20241201T000000Z" end="20241208T000000"/>"#;
+
+        let (start, end) = time_range(xml);
+
+        let utc = crate::tests::naive_date_time(2024, 12, 1, 0, 0, 0);
+        assert_eq!(
+            Some(
+                Utc.from_utc_datetime(&utc)
+                    .with_timezone(&Local)
+                    .naive_local()
+            ),
+            start
+        );
+        assert_eq!(
+            Some(crate::tests::naive_date_time(2024, 12, 8, 0, 0, 0)),
+            end
+        );
+    }
+
+    #[test]
+    fn test_time_range_with_non_ascii_attribute() {
+        let xml = r#"<c:time-range start=あ20241201T000000Z" end='20241208T000000'/>"#;
+
+        let (start, end) = time_range(xml);
+
+        assert_eq!(None, start);
+        assert_eq!(
+            Some(crate::tests::naive_date_time(2024, 12, 8, 0, 0, 0)),
+            end
+        );
+    }
+}
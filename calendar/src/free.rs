@@ -0,0 +1,191 @@
+use crate::{MyError, Schedule};
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+/// 空き時間を探す条件
+#[derive(Debug)]
+pub struct FreeSlotQuery {
+    /// 探索を始める日
+    pub from: NaiveDate,
+    /// 探索を終える日（この日を含む）
+    pub to: NaiveDate,
+    /// 1日のうち探索する時間帯の開始時刻
+    pub day_start: NaiveTime,
+    /// 1日のうち探索する時間帯の終了時刻（None の場合は日付が変わるまで）
+    pub day_end: Option<NaiveTime>,
+    /// 空き時間として扱う最小の長さ
+    pub min_duration: Duration,
+    /// 土日を探索対象から外す
+    pub weekdays_only: bool,
+}
+
+/// 空き時間
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FreeSlot {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+}
+impl FreeSlot {
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+/// 条件に合う空き時間を開始時刻順に返す（日付が変わるまで探す日の翌日を表せない場合はエラー）
+pub fn find_free_slots(
+    schedules: &[Schedule],
+    query: &FreeSlotQuery,
+) -> Result<Vec<FreeSlot>, MyError> {
+    let mut slots = Vec::new();
+    let days = std::iter::successors(Some(query.from), |day| day.succ_opt());
+    for day in days.take_while(|day| *day <= query.to) {
+        if query.weekdays_only && matches!(day.weekday(), Weekday::Sat | Weekday::Sun) {
+            continue;
+        }
+        let window_start = day.and_time(query.day_start);
+        let window_end = match query.day_end {
+            Some(day_end) => day.and_time(day_end),
+            None => day
+                .succ_opt()
+                .ok_or_else(|| MyError::InvalidDateTime(format!("{} の翌日を表せません", day)))?
+                .and_time(NaiveTime::MIN),
+        };
+        if window_end <= window_start {
+            continue;
+        }
+        slots.extend(
+            free_slots_in(schedules, window_start, window_end)
+                .into_iter()
+                .filter(|slot| slot.duration() >= query.min_duration),
+        );
+    }
+    Ok(slots)
+}
+
+/// 指定した時間帯から予定の入っている部分を取り除いた残りを返す
+fn free_slots_in(
+    schedules: &[Schedule],
+    window_start: NaiveDateTime,
+    window_end: NaiveDateTime,
+) -> Vec<FreeSlot> {
+    let mut busy: Vec<&Schedule> = schedules
+        .iter()
+        .filter(|schedule| schedule.intersects_range(window_start, window_end))
+        .collect();
+    busy.sort_by_key(|schedule| schedule.start);
+
+    let mut slots = Vec::new();
+    let mut cursor = window_start;
+    for schedule in busy {
+        if cursor < schedule.start {
+            slots.push(FreeSlot {
+                start: cursor,
+                end: schedule.start,
+            });
+        }
+        cursor = cursor.max(schedule.end);
+    }
+    if cursor < window_end {
+        slots.push(FreeSlot {
+            start: cursor,
+            end: window_end,
+        });
+    }
+    slots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::naive_date_time;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_find_free_slots() {
+        // 2024-12-06 は金曜日、2024-12-07 は土曜日
+        let schedules = vec![
+            Schedule::test(
+                0,
+                "予定0",
+                naive_date_time(2024, 12, 6, 18, 30, 0),
+                naive_date_time(2024, 12, 6, 19, 30, 0),
+            ),
+            Schedule::test(
+                1,
+                "予定1",
+                naive_date_time(2024, 12, 6, 19, 0, 0),
+                naive_date_time(2024, 12, 6, 20, 0, 0),
+            ),
+            Schedule::test(
+                2,
+                "予定2",
+                naive_date_time(2024, 12, 9, 17, 0, 0),
+                naive_date_time(2024, 12, 9, 21, 0, 0),
+            ),
+        ];
+        let query = FreeSlotQuery {
+            from: NaiveDate::from_ymd_opt(2024, 12, 6).unwrap(),
+            to: NaiveDate::from_ymd_opt(2024, 12, 9).unwrap(),
+            day_start: time(18, 0),
+            day_end: Some(time(22, 0)),
+            min_duration: Duration::minutes(90),
+            weekdays_only: true,
+        };
+
+        let actual = find_free_slots(&schedules, &query).unwrap();
+
+        assert_eq!(
+            vec![FreeSlot {
+                start: naive_date_time(2024, 12, 6, 20, 0, 0),
+                end: naive_date_time(2024, 12, 6, 22, 0, 0),
+            }],
+            actual
+        );
+    }
+
+    #[test]
+    fn test_find_free_slots_whole_day() {
+        let schedules = vec![Schedule::test(
+            0,
+            "予定0",
+            naive_date_time(2024, 12, 1, 22, 0, 0),
+            naive_date_time(2024, 12, 2, 9, 0, 0),
+        )];
+        let query = FreeSlotQuery {
+            from: NaiveDate::from_ymd_opt(2024, 12, 2).unwrap(),
+            to: NaiveDate::from_ymd_opt(2024, 12, 2).unwrap(),
+            day_start: NaiveTime::MIN,
+            day_end: None,
+            min_duration: Duration::zero(),
+            weekdays_only: false,
+        };
+
+        let actual = find_free_slots(&schedules, &query).unwrap();
+
+        assert_eq!(
+            vec![FreeSlot {
+                start: naive_date_time(2024, 12, 2, 9, 0, 0),
+                end: naive_date_time(2024, 12, 3, 0, 0, 0),
+            }],
+            actual
+        );
+    }
+
+    #[test]
+    fn test_find_free_slots_on_last_day() {
+        let query = FreeSlotQuery {
+            from: NaiveDate::MAX,
+            to: NaiveDate::MAX,
+            day_start: time(9, 0),
+            day_end: None,
+            min_duration: Duration::minutes(30),
+            weekdays_only: false,
+        };
+
+        let actual = find_free_slots(&[], &query);
+
+        assert!(matches!(actual, Err(MyError::InvalidDateTime(_))));
+    }
+}
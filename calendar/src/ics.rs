@@ -0,0 +1,377 @@
+use crate::{MyError, Schedule};
+use chrono::{DateTime, Days, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+const DATE_TIME_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// 予定一覧を iCalendar (RFC 5545) 形式に変換する
+pub fn render_calendar<'a>(
+    schedules: impl IntoIterator<Item = &'a Schedule>,
+    stamp: NaiveDateTime,
+) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//hands-on-rust-for-backend//calendar//JA".to_string(),
+    ];
+    for schedule in schedules {
+        lines.extend(event_lines(schedule, stamp));
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    // NOTE: iCalendar の改行は CRLF と定められている
+    let mut output = lines.join("\r\n");
+    output.push_str("\r\n");
+    output
+}
+
+fn event_lines(schedule: &Schedule, stamp: NaiveDateTime) -> Vec<String> {
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", escape_text(&schedule.ical_uid())),
+        format!("DTSTAMP:{}Z", stamp.format(DATE_TIME_FORMAT)),
+        format!("DTSTART:{}", schedule.start.format(DATE_TIME_FORMAT)),
+        format!("DTEND:{}", schedule.end.format(DATE_TIME_FORMAT)),
+        format!("SUMMARY:{}", escape_text(&schedule.subject)),
+    ];
+    if let Some(location) = &schedule.location {
+        lines.push(format!("LOCATION:{}", escape_text(location)));
+    }
+    if let Some(description) = &schedule.description {
+        lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+    }
+    // NOTE: カレンダー名もタグと同じく CATEGORIES として出力する
+    let categories: Vec<String> = std::iter::once(&schedule.calendar)
+        .chain(&schedule.tags)
+        .map(|category| escape_text(category))
+        .collect();
+    lines.push(format!("CATEGORIES:{}", categories.join(",")));
+    for attendee in &schedule.attendees {
+        if attendee.contains('@') {
+            lines.push(format!("ATTENDEE:mailto:{}", attendee));
+        } else {
+            // NOTE: メールアドレスを持たない参加者は名前だけを CN パラメータで表す
+            lines.push(format!(
+                "ATTENDEE;CN=\"{}\":invalid:nomail",
+                attendee.replace('"', "'")
+            ));
+        }
+    }
+    lines.push("END:VEVENT".to_string());
+    lines
+}
+
+/// TEXT 型の値に含まれる特殊文字をエスケープする
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// iCalendar の最初の VEVENT を予定に変換する（ID とカレンダー名は呼び出し側で設定する）
+pub fn parse_event(text: &str) -> Result<Schedule, MyError> {
+    // NOTE: 75 バイトを超える行は「改行 + 空白」で折り返されているので元に戻す
+    let unfolded = text
+        .replace("\r\n ", "")
+        .replace("\r\n\t", "")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+
+    let mut schedule = Schedule::default();
+    let mut start = None;
+    let mut end = None;
+    let mut duration = None;
+    let mut in_event = false;
+    // NOTE: VEVENT の中の VALARM などのコンポーネントは読み飛ばす
+    let mut nested = 0;
+    for line in unfolded.lines().map(str::trim_end) {
+        let Some((name, params, value)) = split_property(line) else {
+            continue;
+        };
+        match (name.as_str(), value) {
+            ("BEGIN", "VEVENT") if !in_event => in_event = true,
+            ("END", "VEVENT") if in_event && nested == 0 => break,
+            ("BEGIN", _) if in_event => nested += 1,
+            // NOTE: 対応する BEGIN のない END は無視し、VEVENT の終わりを見失わないようにする
+            ("END", _) if in_event => nested = (nested - 1).max(0),
+            _ if !in_event || nested > 0 => {}
+            ("UID", value) => schedule.uid = Some(unescape_text(value)),
+            ("SUMMARY", value) => schedule.subject = unescape_text(value),
+            ("LOCATION", value) => schedule.location = Some(unescape_text(value)),
+            ("DESCRIPTION", value) => schedule.description = Some(unescape_text(value)),
+            ("DTSTART", value) => start = Some(parse_date_time(value)?),
+            ("DTEND", value) => end = Some(parse_date_time(value)?),
+            ("DURATION", value) => duration = Some(parse_duration(value)?),
+            ("CATEGORIES", value) => schedule.tags.extend(split_list(value)),
+            ("ATTENDEE", value) => schedule.attendees.push(attendee(&params, value)),
+            _ => {}
+        }
+    }
+    if !in_event {
+        return Err(MyError::InvalidIcs("VEVENT がありません".to_string()));
+    }
+
+    let start = start.ok_or_else(|| MyError::InvalidIcs("DTSTART がありません".to_string()))?;
+    let (start, all_day) = start;
+    schedule.start = start;
+    let out_of_range = || MyError::InvalidIcs("終了日時が範囲外です".to_string());
+    schedule.end = match (end, duration) {
+        (Some((end, _)), _) => end,
+        (None, Some(duration)) => start
+            .checked_add_signed(duration)
+            .ok_or_else(out_of_range)?,
+        // NOTE: 終了時刻のない終日予定は1日、それ以外は開始時刻と同時に終わるものとして扱う
+        (None, None) if all_day => start
+            .checked_add_days(Days::new(1))
+            .ok_or_else(out_of_range)?,
+        (None, None) => start,
+    };
+    Ok(schedule)
+}
+
+/// プロパティのパラメータ（名前と値の組）
+type Params = Vec<(String, String)>;
+
+/// 「名前;パラメータ:値」の形式の行を分解する
+fn split_property(line: &str) -> Option<(String, Params, &str)> {
+    // NOTE: パラメータの値はダブルクォートで囲まれている場合があり、その中の : は区切りではない
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(index, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(index),
+        _ => None,
+    })?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next()?.to_ascii_uppercase();
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| {
+            (
+                key.to_ascii_uppercase(),
+                value.trim_matches('"').to_string(),
+            )
+        })
+        .collect();
+    Some((name, params, value))
+}
+
+/// DATE または DATE-TIME の値を読み取り、終日予定かどうかと合わせて返す
+pub fn parse_date_time(value: &str) -> Result<(NaiveDateTime, bool), MyError> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+        return Ok((date.and_time(NaiveTime::MIN), true));
+    }
+    // NOTE: UTC の時刻は手元のタイムゾーンに変換し、TZID 付きや浮動時刻はそのまま扱う
+    if let Some(utc) = value.strip_suffix('Z') {
+        let utc = NaiveDateTime::parse_from_str(utc, DATE_TIME_FORMAT)
+            .map_err(|_| MyError::InvalidIcs(format!("日時の形式が不正です：{}", value)))?;
+        let local = DateTime::<Utc>::from_naive_utc_and_offset(utc, Utc).with_timezone(&Local);
+        return Ok((local.naive_local(), false));
+    }
+    NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT)
+        .map(|date_time| (date_time, false))
+        .map_err(|_| MyError::InvalidIcs(format!("日時の形式が不正です：{}", value)))
+}
+
+/// DURATION の値（例：PT1H30M、P1D）を読み取る
+fn parse_duration(value: &str) -> Result<Duration, MyError> {
+    let invalid = || MyError::InvalidIcs(format!("期間の形式が不正です：{}", value));
+    let (negative, rest) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => continue,
+            unit => {
+                let amount: i64 = number.parse().map_err(|_| invalid())?;
+                number.clear();
+                // NOTE: 表せないほど長い期間は、パニックせずに不正な値として扱う
+                let part = match unit {
+                    'W' => Duration::try_weeks(amount),
+                    'D' => Duration::try_days(amount),
+                    'H' => Duration::try_hours(amount),
+                    'M' => Duration::try_minutes(amount),
+                    'S' => Duration::try_seconds(amount),
+                    _ => return Err(invalid()),
+                };
+                duration = part
+                    .and_then(|part| duration.checked_add(&part))
+                    .ok_or_else(invalid)?;
+            }
+        }
+    }
+    Ok(if negative { -duration } else { duration })
+}
+
+/// ATTENDEE の値からメールアドレスまたは名前を取り出す
+fn attendee(params: &[(String, String)], value: &str) -> String {
+    let common_name = params
+        .iter()
+        .find(|(key, _)| key == "CN")
+        .map(|(_, name)| name.clone());
+    match value.split_once(':') {
+        Some((scheme, address)) if scheme.eq_ignore_ascii_case("mailto") => address.to_string(),
+        _ => common_name.unwrap_or_else(|| value.to_string()),
+    }
+}
+
+/// カンマ区切りの TEXT 型の値を分割する
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut current = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                current.push('\\');
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            ',' => items.push(unescape_text(&std::mem::take(&mut current))),
+            _ => current.push(c),
+        }
+    }
+    items.push(unescape_text(&current));
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+/// TEXT 型の値のエスケープを元に戻す
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(next) => unescaped.push(next),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::naive_date_time;
+
+    #[test]
+    fn test_parse_event() {
+        let text = "BEGIN:VCALENDAR\r\n\
+                    VERSION:2.0\r\n\
+                    BEGIN:VEVENT\r\n\
+                    UID:3F2504E0-4F89-11D3-9A0C-0305E82C3301\r\n\
+                    DTSTART;TZID=Asia/Tokyo:20241201T190000\r\n\
+                    DURATION:PT1H30M\r\n\
+                    SUMMARY:Rust入門\\, 第1回\r\n\
+                    DESCRIPTION:1行目\\n2行\r\n \
+                    目\r\n\
+                    CATEGORIES:rust,online\r\n\
+                    ATTENDEE;CN=\"Alice\";ROLE=REQ-PARTICIPANT:mailto:alice@example.com\r\n\
+                    ATTENDEE;CN=\"Bob\":invalid:nomail\r\n\
+                    BEGIN:VALARM\r\n\
+                    DESCRIPTION:通知\r\n\
+                    END:VALARM\r\n\
+                    END:VEVENT\r\n\
+                    END:VCALENDAR\r\n";
+
+        let actual = parse_event(text).unwrap();
+
+        assert_eq!(
+            Schedule {
+                subject: "Rust入門, 第1回".to_string(),
+                start: naive_date_time(2024, 12, 1, 19, 0, 0),
+                end: naive_date_time(2024, 12, 1, 20, 30, 0),
+                description: Some("1行目\n2行目".to_string()),
+                tags: vec!["rust".to_string(), "online".to_string()],
+                attendees: vec!["alice@example.com".to_string(), "Bob".to_string()],
+                uid: Some("3F2504E0-4F89-11D3-9A0C-0305E82C3301".to_string()),
+                ..Default::default()
+            },
+            actual
+        );
+    }
+
+    #[test]
+    fn test_parse_all_day_event() {
+        let text = "BEGIN:VEVENT\nDTSTART;VALUE=DATE:20241201\nSUMMARY:合宿\nEND:VEVENT\n";
+
+        let actual = parse_event(text).unwrap();
+
+        assert_eq!(naive_date_time(2024, 12, 1, 0, 0, 0), actual.start);
+        assert_eq!(naive_date_time(2024, 12, 2, 0, 0, 0), actual.end);
+    }
+
+    #[test]
+    fn test_parse_event_rejects_huge_duration() {
+        let text = "BEGIN:VEVENT\nDTSTART:20241201T190000\nDURATION:P99999999999999W\nEND:VEVENT\n";
+
+        let actual = parse_event(text);
+
+        assert!(matches!(actual, Err(MyError::InvalidIcs(_))));
+    }
+
+    #[test]
+    fn test_parse_event_rejects_end_out_of_range() {
+        let text = "BEGIN:VEVENT\nDTSTART:20241201T190000\nDURATION:P99999999D\nEND:VEVENT\n";
+
+        let actual = parse_event(text);
+
+        assert!(matches!(actual, Err(MyError::InvalidIcs(_))));
+    }
+
+    #[test]
+    fn test_parse_event_ignores_unmatched_end() {
+        let text = "BEGIN:VEVENT\nDTSTART:20241201T190000\nEND:VALARM\nSUMMARY:定例\nEND:VEVENT\nSUMMARY:外側\n";
+
+        let actual = parse_event(text).unwrap();
+
+        assert_eq!("定例", actual.subject);
+    }
+
+    #[test]
+    fn test_render_and_parse_round_trip() {
+        let schedule = Schedule {
+            id: 3,
+            calendar: "team".to_string(),
+            subject: "定例; 週次".to_string(),
+            start: naive_date_time(2024, 12, 2, 10, 0, 0),
+            end: naive_date_time(2024, 12, 2, 11, 0, 0),
+            location: Some("会議室A".to_string()),
+            tags: vec!["weekly".to_string()],
+            ..Default::default()
+        };
+
+        let text = render_calendar([&schedule], naive_date_time(2024, 1, 1, 0, 0, 0));
+        let actual = parse_event(&text).unwrap();
+
+        assert_eq!(schedule.subject, actual.subject);
+        assert_eq!(schedule.start, actual.start);
+        assert_eq!(schedule.end, actual.end);
+        assert_eq!(schedule.location, actual.location);
+        assert_eq!(vec!["team".to_string(), "weekly".to_string()], actual.tags);
+        assert_eq!(Some("schedule-3@calendar".to_string()), actual.uid);
+    }
+}
@@ -0,0 +1,475 @@
+use crate::{ics, Schedule};
+use chrono::{Datelike, Days, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use clap::ValueEnum;
+use std::fmt::Write;
+use unicode_width::UnicodeWidthStr;
+
+/// 一覧の出力形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// 桁揃えした表形式
+    Table,
+    /// JSON 形式
+    Json,
+    /// CSV 形式
+    Csv,
+    /// iCalendar 形式
+    Ics,
+}
+
+/// カレンダー形式の表示単位
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GridView {
+    /// 月表示
+    Month,
+    /// 週表示
+    Week,
+}
+
+/// 一覧表示の絞り込み条件
+#[derive(Debug, Default)]
+pub struct ListFilter {
+    /// この日以降に終わる予定に限定する
+    pub from: Option<NaiveDate>,
+    /// この日以前に始まる予定に限定する
+    pub to: Option<NaiveDate>,
+    /// 勉強会の名前に含まれる文字列（大文字・小文字は区別しない）
+    pub search: Option<String>,
+    /// 所属するカレンダーの名前
+    pub calendar: Option<String>,
+    /// 予定が持つべきタグ（すべてを含む予定に限定する）
+    pub tags: Vec<String>,
+}
+impl ListFilter {
+    /// 指定した日だけを対象とする条件
+    pub fn day(date: NaiveDate) -> Self {
+        Self {
+            from: Some(date),
+            to: Some(date),
+            ..Default::default()
+        }
+    }
+
+    /// 指定した日を含む週（月曜始まり）を対象とする条件
+    pub fn week_of(date: NaiveDate) -> Self {
+        let week = date.week(Weekday::Mon);
+        Self {
+            from: Some(week.first_day()),
+            to: Some(week.last_day()),
+            ..Default::default()
+        }
+    }
+
+    fn matches(&self, schedule: &Schedule) -> bool {
+        // NOTE: 期間との重なり判定は Schedule::intersects と同じ考え方
+        if let Some(from) = self.from {
+            if schedule.end <= start_of_day(from) {
+                return false;
+            }
+        }
+        if let Some(to) = self.to {
+            if next_day(to).is_some_and(|next| start_of_day(next) <= schedule.start) {
+                return false;
+            }
+        }
+        if let Some(search) = &self.search {
+            if !schedule
+                .subject
+                .to_lowercase()
+                .contains(&search.to_lowercase())
+            {
+                return false;
+            }
+        }
+        if let Some(calendar) = &self.calendar {
+            if &schedule.calendar != calendar {
+                return false;
+            }
+        }
+        self.tags.iter().all(|tag| schedule.tags.contains(tag))
+    }
+}
+
+/// 条件に合う予定を開始時刻順に並べて返す
+pub fn filter_schedules<'a>(schedules: &'a [Schedule], filter: &ListFilter) -> Vec<&'a Schedule> {
+    let mut matched: Vec<&Schedule> = schedules
+        .iter()
+        .filter(|schedule| filter.matches(schedule))
+        .collect();
+    matched.sort_by_key(|schedule| (schedule.start, schedule.id));
+    matched
+}
+
+/// 予定一覧を指定した形式の文字列に変換する
+pub fn render(
+    schedules: &[&Schedule],
+    format: OutputFormat,
+    stamp: NaiveDateTime,
+) -> Result<String, serde_json::Error> {
+    let output = match format {
+        OutputFormat::Table => render_table(schedules),
+        OutputFormat::Json => {
+            let mut json = serde_json::to_string_pretty(schedules)?;
+            json.push('\n');
+            json
+        }
+        OutputFormat::Csv => render_csv(schedules),
+        OutputFormat::Ics => ics::render_calendar(schedules.iter().copied(), stamp),
+    };
+    Ok(output)
+}
+
+fn render_table(schedules: &[&Schedule]) -> String {
+    let id_width = schedules
+        .iter()
+        .map(|schedule| schedule.id.to_string().len())
+        .chain(["ID".len()])
+        .max()
+        .unwrap_or_default();
+    // NOTE: NaiveDateTime の既定の表示形式は "YYYY-MM-DD HH:MM:SS" で固定長
+    let time_width = "YYYY-MM-DD HH:MM:SS".len();
+
+    // NOTE: 日本語のカレンダー名でも列がそろうよう、文字数ではなく表示幅でそろえる
+    let calendar_width = schedules
+        .iter()
+        .map(|schedule| schedule.calendar.width())
+        .chain(["CALENDAR".width()])
+        .max()
+        .unwrap_or_default();
+    let pad = |name: &str| format!("{}{}", name, " ".repeat(calendar_width - name.width()));
+
+    let mut output = String::new();
+    writeln!(
+        output,
+        "{:<id_width$}  {:<time_width$}  {:<time_width$}  {}  SUBJECT",
+        "ID",
+        "START",
+        "END",
+        pad("CALENDAR")
+    )
+    .unwrap();
+    for schedule in schedules {
+        let mut subject = schedule.subject.clone();
+        if !schedule.tags.is_empty() {
+            write!(subject, " #{}", schedule.tags.join(" #")).unwrap();
+        }
+        writeln!(
+            output,
+            "{:<id_width$}  {:<time_width$}  {:<time_width$}  {}  {}",
+            schedule.id,
+            schedule.start.to_string(),
+            schedule.end.to_string(),
+            pad(&schedule.calendar),
+            subject
+        )
+        .unwrap();
+    }
+    output
+}
+
+fn render_csv(schedules: &[&Schedule]) -> String {
+    let mut output =
+        String::from("id,calendar,subject,start,end,location,description,tags,attendees\n");
+    for schedule in schedules {
+        // NOTE: 複数の値を持つ列はセミコロン区切りで1つのセルに入れる
+        writeln!(
+            output,
+            "{},{},{},{},{},{},{},{},{}",
+            schedule.id,
+            escape_csv(&schedule.calendar),
+            escape_csv(&schedule.subject),
+            schedule.start.format("%Y-%m-%dT%H:%M:%S"),
+            schedule.end.format("%Y-%m-%dT%H:%M:%S"),
+            escape_csv(schedule.location.as_deref().unwrap_or_default()),
+            escape_csv(schedule.description.as_deref().unwrap_or_default()),
+            escape_csv(&schedule.tags.join(";")),
+            escape_csv(&schedule.attendees.join(";"))
+        )
+        .unwrap();
+    }
+    output
+}
+
+/// カンマ・改行・ダブルクォートを含む値をダブルクォートで囲む
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// 指定した日を含む月または週の予定をカレンダー形式で表示する
+pub fn render_grid(schedules: &[&Schedule], view: GridView, date: NaiveDate) -> String {
+    match view {
+        GridView::Month => render_month(schedules, date),
+        GridView::Week => render_week(schedules, date),
+    }
+}
+
+fn render_month(schedules: &[&Schedule], date: NaiveDate) -> String {
+    let first = date.with_day(1).unwrap_or(date);
+    // NOTE: 表せる最後の月は、翌月の初日を求められないので月末までをそのまま表示する
+    let last = first
+        .checked_add_months(Months::new(1))
+        .and_then(|next| next.pred_opt())
+        .unwrap_or(NaiveDate::MAX);
+
+    let mut output = String::new();
+    let title = format!("{:^27}", first.format("%Y-%m").to_string());
+    writeln!(output, "{}", title.trim_end()).unwrap();
+    writeln!(output, " Mo  Tu  We  Th  Fr  Sa  Su").unwrap();
+
+    let mut line = "    ".repeat(first.weekday().num_days_from_monday() as usize);
+    // NOTE: iter_days は表せる最後の日を返さないので、翌日をたどって列挙する
+    let days = std::iter::successors(Some(first), |day| day.succ_opt());
+    for day in days.take_while(|day| *day <= last) {
+        // 予定がある日には * を付ける
+        let marker = if day_segments(schedules, day).is_empty() {
+            ' '
+        } else {
+            '*'
+        };
+        write!(line, " {:>2}{}", day.day(), marker).unwrap();
+        if day.weekday() == Weekday::Sun {
+            writeln!(output, "{}", line.trim_end()).unwrap();
+            line.clear();
+        }
+    }
+    if !line.is_empty() {
+        writeln!(output, "{}", line.trim_end()).unwrap();
+    }
+    output
+}
+
+fn render_week(schedules: &[&Schedule], date: NaiveDate) -> String {
+    // NOTE: 表せる日付の範囲の端の週は、範囲内の日だけを表示する
+    let offset = i64::from(date.weekday().num_days_from_monday());
+    let days = (0..7).filter_map(|index| date.checked_add_signed(Duration::days(index - offset)));
+
+    let mut output = String::new();
+    for day in days {
+        let label = day.format("%a %Y-%m-%d").to_string();
+        let segments = day_segments(schedules, day);
+        if segments.is_empty() {
+            writeln!(output, "{} |", label).unwrap();
+        }
+        for (index, (start, end, schedule)) in segments.into_iter().enumerate() {
+            let label = if index == 0 {
+                label.clone()
+            } else {
+                " ".repeat(label.len())
+            };
+            writeln!(
+                output,
+                "{} | {}-{} [{}] {}",
+                label, start, end, schedule.id, schedule.subject
+            )
+            .unwrap();
+        }
+    }
+    output
+}
+
+/// 指定した日に掛かる予定と、その日の中での開始・終了時刻を返す
+pub fn day_segments<'a>(
+    schedules: &[&'a Schedule],
+    day: NaiveDate,
+) -> Vec<(String, String, &'a Schedule)> {
+    let day_start = start_of_day(day);
+    let day_end = next_day(day).map_or(NaiveDateTime::MAX, start_of_day);
+    schedules
+        .iter()
+        .filter(|schedule| schedule.intersects_range(day_start, day_end))
+        .map(|schedule| {
+            let start = schedule.start.max(day_start).format("%H:%M").to_string();
+            let end = if schedule.end >= day_end {
+                "24:00".to_string()
+            } else {
+                schedule.end.format("%H:%M").to_string()
+            };
+            (start, end, *schedule)
+        })
+        .collect()
+}
+
+fn start_of_day(date: NaiveDate) -> NaiveDateTime {
+    date.and_time(NaiveTime::MIN)
+}
+
+/// 翌日（表せる最後の日の場合は None）
+fn next_day(date: NaiveDate) -> Option<NaiveDate> {
+    date.checked_add_days(Days::new(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::naive_date_time;
+
+    fn sample_schedules() -> Vec<Schedule> {
+        vec![
+            Schedule::test(
+                0,
+                "Rust入門",
+                naive_date_time(2024, 12, 3, 19, 0, 0),
+                naive_date_time(2024, 12, 3, 20, 30, 0),
+            ),
+            Schedule::test(
+                1,
+                "合宿",
+                naive_date_time(2024, 12, 1, 10, 0, 0),
+                naive_date_time(2024, 12, 2, 15, 0, 0),
+            ),
+            Schedule::test(
+                2,
+                "rust 応用",
+                naive_date_time(2024, 12, 10, 18, 0, 0),
+                naive_date_time(2024, 12, 10, 19, 0, 0),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_filter_schedules_sorts_by_start() {
+        let schedules = sample_schedules();
+
+        let actual = filter_schedules(&schedules, &ListFilter::default());
+
+        let ids: Vec<u64> = actual.iter().map(|schedule| schedule.id).collect();
+        assert_eq!(vec![1, 0, 2], ids);
+    }
+
+    #[test]
+    fn test_filter_schedules_by_range_and_search() {
+        let schedules = sample_schedules();
+
+        let day = filter_schedules(
+            &schedules,
+            &ListFilter::day(NaiveDate::from_ymd_opt(2024, 12, 2).unwrap()),
+        );
+        let week = filter_schedules(
+            &schedules,
+            &ListFilter::week_of(NaiveDate::from_ymd_opt(2024, 12, 4).unwrap()),
+        );
+        let search = filter_schedules(
+            &schedules,
+            &ListFilter {
+                search: Some("RUST".to_string()),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(vec![1], day.iter().map(|s| s.id).collect::<Vec<_>>());
+        assert_eq!(vec![1, 0], week.iter().map(|s| s.id).collect::<Vec<_>>());
+        assert_eq!(vec![0, 2], search.iter().map(|s| s.id).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_filter_schedules_by_calendar_and_tags() {
+        let mut schedules = sample_schedules();
+        schedules[0].calendar = "rust-study".to_string();
+        schedules[0].tags = vec!["rust".to_string(), "online".to_string()];
+        schedules[2].calendar = "rust-study".to_string();
+        schedules[2].tags = vec!["rust".to_string()];
+
+        let calendar = filter_schedules(
+            &schedules,
+            &ListFilter {
+                calendar: Some("rust-study".to_string()),
+                ..Default::default()
+            },
+        );
+        let tags = filter_schedules(
+            &schedules,
+            &ListFilter {
+                tags: vec!["rust".to_string(), "online".to_string()],
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            vec![0, 2],
+            calendar.iter().map(|s| s.id).collect::<Vec<_>>()
+        );
+        assert_eq!(vec![0], tags.iter().map(|s| s.id).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_render_table_aligns_wide_calendar_names() {
+        let mut schedules = sample_schedules();
+        schedules.truncate(2);
+        schedules[0].calendar = "勉強会チーム".to_string();
+        let refs: Vec<&Schedule> = schedules.iter().collect();
+
+        let actual = render(
+            &refs,
+            OutputFormat::Table,
+            naive_date_time(2024, 1, 1, 0, 0, 0),
+        )
+        .unwrap();
+
+        assert_eq!(
+            "ID  START                END                  CALENDAR      SUBJECT\n\
+             0   2024-12-03 19:00:00  2024-12-03 20:30:00  勉強会チーム  Rust入門\n\
+             1   2024-12-01 10:00:00  2024-12-02 15:00:00  default       合宿\n",
+            actual
+        );
+    }
+
+    #[test]
+    fn test_render_csv_and_ics() {
+        let schedules = [Schedule::test(
+            3,
+            "読書会, 第1回",
+            naive_date_time(2024, 1, 1, 19, 0, 0),
+            naive_date_time(2024, 1, 1, 20, 0, 0),
+        )];
+        let refs: Vec<&Schedule> = schedules.iter().collect();
+        let stamp = naive_date_time(2024, 1, 1, 0, 0, 0);
+
+        let csv = render(&refs, OutputFormat::Csv, stamp).unwrap();
+        let ics = render(&refs, OutputFormat::Ics, stamp).unwrap();
+
+        assert_eq!(
+            "id,calendar,subject,start,end,location,description,tags,attendees\n\
+             3,default,\"読書会, 第1回\",2024-01-01T19:00:00,2024-01-01T20:00:00,,,,\n",
+            csv
+        );
+        assert!(ics.contains("DTSTART:20240101T190000\r\n"));
+        assert!(ics.contains("SUMMARY:読書会\\, 第1回\r\n"));
+    }
+
+    #[test]
+    fn test_render_week_splits_multi_day_schedule() {
+        let schedules = sample_schedules();
+        let refs: Vec<&Schedule> = schedules.iter().collect();
+
+        let actual = render_grid(
+            &refs,
+            GridView::Week,
+            NaiveDate::from_ymd_opt(2024, 12, 1).unwrap(),
+        );
+
+        assert_eq!(
+            "Mon 2024-11-25 |\n\
+             Tue 2024-11-26 |\n\
+             Wed 2024-11-27 |\n\
+             Thu 2024-11-28 |\n\
+             Fri 2024-11-29 |\n\
+             Sat 2024-11-30 |\n\
+             Sun 2024-12-01 | 10:00-24:00 [1] 合宿\n",
+            actual
+        );
+    }
+
+    #[test]
+    fn test_render_grid_at_date_range_bounds() {
+        let month = render_grid(&[], GridView::Month, NaiveDate::MAX);
+        let week = render_grid(&[], GridView::Week, NaiveDate::MIN);
+
+        assert!(month.trim_end().ends_with("31"));
+        assert!(week.starts_with(&NaiveDate::MIN.format("%a %Y-%m-%d |").to_string()));
+        assert!(week.lines().count() <= 7);
+    }
+}
@@ -1,36 +1,184 @@
-use chrono::NaiveDateTime;
-use clap::{Parser, Subcommand};
+mod datetime;
+mod dav;
+mod free;
+mod ics;
+mod list;
+mod migration;
+mod remind;
+mod room;
+mod server;
+mod storage;
+mod tui;
+
+use chrono::{Days, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use clap::{Parser, Subcommand, ValueEnum};
+use datetime::{Clock, SystemClock};
+use free::FreeSlotQuery;
+use list::{GridView, ListFilter, OutputFormat};
+use remind::{Notifier, RemindOptions};
 use serde::{Deserialize, Serialize};
-use std::{
-    fs::File,
-    io::{BufReader, BufWriter},
-};
+use std::path::{Path, PathBuf};
+use storage::{Action, CalendarStore, History, HistoryState, StoreKind, WriteOutcome};
+
+/// カレンダーファイルの形式のバージョン
+const CALENDAR_VERSION: u32 = 3;
+/// カレンダー名を指定しなかった予定の所属先
+const DEFAULT_CALENDAR: &str = "default";
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 struct Schedule {
     /// 予定のID
     id: u64,
+    /// 所属するカレンダーの名前
+    calendar: String,
     /// 勉強会の名前
     subject: String,
     /// 開始時刻
     start: NaiveDateTime,
     /// 終了時刻
     end: NaiveDateTime,
+    /// 場所
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    location: Option<String>,
+    /// 説明
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    /// タグ
+    tags: Vec<String>,
+    /// 参加者
+    attendees: Vec<String>,
+    /// 開始時刻の何分前に通知するか（None の場合はリマインダーの既定値）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    remind_before: Option<u32>,
+    /// iCalendar の UID（None の場合は予定のIDから生成する）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uid: Option<String>,
+    /// CalDAV クライアントが指定したリソース名（None の場合は "<ID>.ics"）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    resource: Option<String>,
+    /// 予約した会議室の名前
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    rooms: Vec<String>,
+}
+impl Default for Schedule {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            calendar: DEFAULT_CALENDAR.to_string(),
+            subject: String::new(),
+            start: NaiveDateTime::default(),
+            end: NaiveDateTime::default(),
+            location: None,
+            description: None,
+            tags: Vec::new(),
+            attendees: Vec::new(),
+            remind_before: None,
+            uid: None,
+            resource: None,
+            rooms: Vec::new(),
+        }
+    }
 }
 impl Schedule {
     fn intersects(&self, other: &Schedule) -> bool {
-        self.start < other.end && other.start < self.end
+        self.intersects_range(other.start, other.end)
+    }
+
+    /// iCalendar の UID
+    fn ical_uid(&self) -> String {
+        self.uid
+            .clone()
+            .unwrap_or_else(|| format!("schedule-{}@calendar", self.id))
+    }
+
+    /// CalDAV のリソース名
+    fn resource_name(&self) -> String {
+        self.resource
+            .clone()
+            .unwrap_or_else(|| format!("{}.ics", self.id))
+    }
+
+    /// 指定した時間帯と重なるかどうか
+    fn intersects_range(&self, start: NaiveDateTime, end: NaiveDateTime) -> bool {
+        self.start < end && start < self.end
+    }
+
+    /// 同じ会議室を予約しているかどうか
+    fn shares_room(&self, other: &Schedule) -> bool {
+        self.rooms.iter().any(|room| other.rooms.contains(room))
+    }
+}
+#[cfg(test)]
+impl Schedule {
+    /// テスト用の予定（それ以外の項目は既定値）
+    pub(crate) fn test(id: u64, subject: &str, start: NaiveDateTime, end: NaiveDateTime) -> Self {
+        Self {
+            id,
+            subject: subject.to_string(),
+            start,
+            end,
+            ..Default::default()
+        }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 struct Calendar {
+    /// ファイル形式のバージョン
+    version: u32,
     /// 勉強会の予定一覧
     schedules: Vec<Schedule>,
+    /// 予約できる会議室
+    #[serde(default)]
+    rooms: Vec<Room>,
+}
+
+/// 予定で予約できる会議室
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Room {
+    /// 会議室の名前
+    name: String,
+    /// 定員
+    capacity: u32,
+    /// 場所
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    location: Option<String>,
+}
+
+/// 予定の重複を確認する範囲
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ConflictScope {
+    /// 同じ会議室を予約した予定とだけ重複を確認する
+    Room,
+    /// 同じカレンダーの予定とだけ重複を確認する
+    Calendar,
+    /// すべてのカレンダーの予定と重複を確認する
+    All,
+}
+impl ConflictScope {
+    /// 時間帯が重なったときに重複として扱うかどうか
+    // NOTE: 同じ会議室を予約した予定は、どの範囲でも重複として扱う
+    fn applies_to(&self, target: &Schedule, other: &Schedule) -> bool {
+        target.shares_room(other)
+            || match self {
+                ConflictScope::Room => false,
+                ConflictScope::Calendar => target.calendar == other.calendar,
+                ConflictScope::All => true,
+            }
+    }
 }
 
 #[derive(Parser)]
 struct Cli {
+    /// 予定の保存先（拡張子が .db / .sqlite / .sqlite3 の場合は SQLite、それ以外は JSON）
+    #[clap(
+        long,
+        global = true,
+        env = "CALENDAR_STORE",
+        default_value = "schedule.json"
+    )]
+    store: PathBuf,
     #[clap(subcommand)]
     command: Commands,
 }
@@ -38,21 +186,184 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// 予定の一覧表示
-    List,
+    List {
+        /// この日以降の予定に絞り込む
+        #[clap(long, conflicts_with_all = ["today", "week"])]
+        from: Option<NaiveDate>,
+        /// この日以前の予定に絞り込む
+        #[clap(long, conflicts_with_all = ["today", "week"])]
+        to: Option<NaiveDate>,
+        /// 今日の予定に絞り込む
+        #[clap(long, conflicts_with = "week")]
+        today: bool,
+        /// 今週の予定に絞り込む
+        #[clap(long)]
+        week: bool,
+        /// 勉強会の名前で絞り込む
+        #[clap(long)]
+        search: Option<String>,
+        /// カレンダーで絞り込む
+        #[clap(long)]
+        calendar: Option<String>,
+        /// タグで絞り込む（複数指定した場合はすべてを含む予定）
+        #[clap(long = "tag")]
+        tags: Vec<String>,
+        /// 出力形式
+        #[clap(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+        /// 月または週のカレンダー形式で表示する
+        #[clap(long, value_enum, conflicts_with = "format")]
+        view: Option<GridView>,
+    },
     /// 予定の追加
     Add {
         /// 勉強会の名前
         subject: String,
-        /// 開始時刻
-        start: NaiveDateTime,
-        /// 終了時刻
-        end: NaiveDateTime,
+        /// 開始時刻（例：2024-01-01T19:00:00、"2024-01-01 19:00"、"tomorrow 19:00"、"next tue 18:30"）
+        start: String,
+        /// 終了時刻（時刻だけの場合は開始日の時刻）
+        #[clap(required_unless_present = "duration")]
+        end: Option<String>,
+        /// 終了時刻の代わりに指定する長さ（例：90m、1h30m）
+        #[clap(long, conflicts_with = "end")]
+        duration: Option<String>,
+        /// 追加先のカレンダー
+        #[clap(long, default_value = DEFAULT_CALENDAR)]
+        calendar: String,
+        /// 場所
+        #[clap(long)]
+        location: Option<String>,
+        /// 説明
+        #[clap(long)]
+        description: Option<String>,
+        /// タグ（複数指定可）
+        #[clap(long = "tag")]
+        tags: Vec<String>,
+        /// 参加者（複数指定可）
+        #[clap(long = "attendee")]
+        attendees: Vec<String>,
+        /// 予約する会議室（複数指定可）
+        #[clap(long = "room")]
+        rooms: Vec<String>,
+        /// 予定の重複を確認する範囲（同じ会議室を予約した予定は常に重複として扱う）
+        #[clap(long, value_enum, default_value_t = ConflictScope::Room)]
+        conflict_scope: ConflictScope,
+        /// 開始時刻の何分前に通知するか
+        #[clap(long)]
+        remind: Option<u32>,
     },
     /// 予定の削除
     Delete {
         /// 予定のID
         id: u64,
     },
+    /// 空き時間の検索
+    Free {
+        /// 探索を始める日（省略時は今日）
+        #[clap(long)]
+        from: Option<NaiveDate>,
+        /// 探索を終える日（省略時は探索を始める日から7日間）
+        #[clap(long)]
+        to: Option<NaiveDate>,
+        /// 1日のうち探索する時間帯の開始時刻
+        #[clap(long, default_value = "00:00")]
+        day_start: NaiveTime,
+        /// 1日のうち探索する時間帯の終了時刻（省略時は日付が変わるまで）
+        #[clap(long)]
+        day_end: Option<NaiveTime>,
+        /// 空き時間として扱う最小の長さ（分）
+        #[clap(long, default_value_t = 30)]
+        min_minutes: u32,
+        /// 平日のみを探索する
+        #[clap(long)]
+        weekdays: bool,
+    },
+    /// 予定を別の保存先へ移行する
+    Migrate {
+        /// 移行先の種類
+        #[clap(long, value_enum)]
+        to: StoreKind,
+        /// 移行先のファイル（省略時は schedule.json または schedule.db）
+        #[clap(long)]
+        output: Option<PathBuf>,
+    },
+    /// 予定の開始前に通知する（保存先が更新されると読み込み直す）
+    Remind {
+        /// 予定ごとの指定がない場合に、開始時刻の何分前に通知するか
+        #[clap(long, default_value_t = 10)]
+        before: u32,
+        /// 保存先の更新と通知時刻を確認する間隔（秒）
+        #[clap(long, default_value_t = 30)]
+        interval: u64,
+        /// 通知時に実行するコマンド（予定の内容は SCHEDULE_* 環境変数で渡す）
+        #[clap(long, conflicts_with = "log")]
+        exec: Option<String>,
+        /// 通知を追記するログファイル
+        #[clap(long)]
+        log: Option<PathBuf>,
+        /// 1回だけ確認して終了する
+        #[clap(long)]
+        once: bool,
+    },
+    /// 予定を操作する REST API と CalDAV のサーバーを起動する
+    Serve {
+        /// 待ち受けるアドレス（ポート番号に 0 を指定すると空いているポートを使う）
+        #[clap(long, default_value = "127.0.0.1:8080")]
+        bind: String,
+    },
+    /// 会議室の管理
+    Rooms {
+        #[clap(subcommand)]
+        command: RoomCommands,
+    },
+    /// 端末上で予定を閲覧・編集する
+    Tui,
+    /// 直前の追加・更新・削除を取り消す
+    Undo,
+    /// 取り消した操作をやり直す
+    Redo,
+    /// 変更履歴の表示
+    History {
+        /// 表示する件数（新しいものから）
+        #[clap(long, default_value_t = 20)]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum RoomCommands {
+    /// 会議室の登録
+    Add {
+        /// 会議室の名前
+        name: String,
+        /// 定員
+        #[clap(long)]
+        capacity: u32,
+        /// 場所
+        #[clap(long)]
+        location: Option<String>,
+    },
+    /// 会議室の削除
+    Remove {
+        /// 会議室の名前
+        name: String,
+    },
+    /// 会議室の一覧
+    List,
+    /// 指定した時間帯の会議室の空き状況
+    Availability {
+        /// 開始時刻（予定の追加と同じ形式）
+        start: String,
+        /// 終了時刻（時刻だけの場合は開始日の時刻）
+        #[clap(required_unless_present = "duration")]
+        end: Option<String>,
+        /// 終了時刻の代わりに指定する長さ（例：90m、1h30m）
+        #[clap(long, conflicts_with = "end")]
+        duration: Option<String>,
+        /// 必要な定員（これより小さい会議室は表示しない）
+        #[clap(long, default_value_t = 0)]
+        capacity: u32,
+    },
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -62,108 +373,540 @@ enum MyError {
 
     #[error("json error: {0}")]
     Json(#[from] serde_json::Error),
+
+    #[error("sqlite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("unsupported calendar version: {0}")]
+    UnsupportedVersion(u64),
+
+    #[error("invalid iCalendar: {0}")]
+    InvalidIcs(String),
+
+    #[error("invalid date time: {0}")]
+    InvalidDateTime(String),
+
+    #[error("invalid duration: {0}")]
+    InvalidDuration(String),
+
+    #[error("calendar does not match history entry: {0}")]
+    HistoryMismatch(u64),
+
+    #[error("unknown room: {0}")]
+    UnknownRoom(String),
+
+    #[error("room capacity exceeded: {attendees} attendees for {capacity} seats")]
+    OverCapacity { capacity: u32, attendees: usize },
 }
 // NOTE: From トレイトが実装されている場合、? で独自エラー型に自動変換してくれる
 //   impl From<T> for MyError { ... }
 // NOTE: thiserror crate を使用する場合、#[from] を付けることで上記と同様の実装となる
 
 fn main() {
-    match read_calendar() {
-        Ok(calendar) => run_command(calendar),
+    let options = Cli::parse();
+    match storage::open(&options.store) {
+        Ok(mut store) => run_command(
+            store.as_mut(),
+            &options.store,
+            &SystemClock,
+            options.command,
+        ),
         Err(error) => println!("カレンダーの読み込みに失敗しました：{:?}", error),
     }
 }
 
-fn run_command(mut calendar: Calendar) {
-    let options = Cli::parse();
-    match options.command {
-        Commands::List => show_list(calendar),
+fn run_command(
+    store: &mut dyn CalendarStore,
+    store_path: &Path,
+    clock: &dyn Clock,
+    command: Commands,
+) {
+    match command {
+        Commands::List {
+            from,
+            to,
+            today,
+            week,
+            search,
+            calendar: calendar_name,
+            tags,
+            format,
+            view,
+        } => {
+            let today_date = clock.now().date();
+            let mut filter = if today {
+                ListFilter::day(today_date)
+            } else if week {
+                ListFilter::week_of(today_date)
+            } else {
+                ListFilter {
+                    from,
+                    to,
+                    ..Default::default()
+                }
+            };
+            filter.search = search;
+            filter.calendar = calendar_name;
+            filter.tags = tags;
+            let Some(calendar) = read_calendar(store) else {
+                return;
+            };
+            match view {
+                Some(view) => {
+                    let date = filter.from.unwrap_or(today_date);
+                    show_grid(&calendar, &filter, view, date)
+                }
+                None => show_list(&calendar, &filter, format, clock),
+            }
+        }
         Commands::Add {
             subject,
             start,
             end,
+            duration,
+            calendar: calendar_name,
+            location,
+            description,
+            tags,
+            attendees,
+            rooms,
+            conflict_scope,
+            remind,
         } => {
-            if add_schedule(&mut calendar, subject, start, end) {
-                match save_calendar(&calendar) {
-                    Ok(_) => println!("予定を追加しました。"),
-                    Err(error) => match error {
-                        MyError::Io(error) => {
-                            println!("カレンダーの読み込みに失敗しました：{:?}", error)
-                        }
-                        MyError::Json(error) => {
-                            println!("予定の追加に失敗しました：{:?}", error)
-                        }
-                    },
+            let (start, end) =
+                match resolve_period(&start, end.as_deref(), duration.as_deref(), clock) {
+                    Ok(period) => period,
+                    Err(MyError::InvalidDuration(input)) => {
+                        println!("エラー：長さを解釈できません：{}", input);
+                        return;
+                    }
+                    Err(MyError::InvalidDateTime(input)) => {
+                        println!("エラー：日時を解釈できません：{}", input);
+                        return;
+                    }
+                    Err(error) => {
+                        println!("エラー：予定の追加に失敗しました：{:?}", error);
+                        return;
+                    }
+                };
+            if end <= start {
+                println!("エラー：終了時刻は開始時刻より後にしてください");
+                return;
+            }
+            let new_schedule = Schedule {
+                calendar: calendar_name,
+                subject,
+                start,
+                end,
+                location,
+                description,
+                tags,
+                attendees,
+                remind_before: remind,
+                rooms,
+                ..Default::default()
+            };
+            if !new_schedule.rooms.is_empty() {
+                let Some(calendar) = read_calendar(store) else {
+                    return;
+                };
+                if let Err(error) = room::check_reservation(&calendar, &new_schedule) {
+                    print_room_error(error);
+                    return;
                 }
-            } else {
-                println!("エラー：予定が重複しています");
             }
-        }
-        Commands::Delete { id } => {
-            if delete_schedule(&mut calendar, id) {
-                match save_calendar(&calendar) {
-                    Ok(_) => println!("予定を削除しました。"),
-                    Err(_) => println!("エラー：予定の削除に失敗しました"),
+            match store.add_schedule(new_schedule, conflict_scope) {
+                Ok(WriteOutcome::Saved(_)) => println!("予定を追加しました。"),
+                Ok(WriteOutcome::Conflict(conflicts)) => {
+                    println!("エラー：予定が重複しています");
+                    for schedule in conflicts {
+                        println!(
+                            "  {}\t{}\t{}\t{}",
+                            schedule.id, schedule.start, schedule.end, schedule.subject
+                        );
+                    }
                 }
-            } else {
-                println!("エラー：IDが不正です");
+                Ok(WriteOutcome::NotFound) => println!("エラー：予定の追加に失敗しました"),
+                Err(error) => match error {
+                    MyError::Io(error) => {
+                        println!("カレンダーの読み込みに失敗しました：{:?}", error)
+                    }
+                    MyError::Json(error) => {
+                        println!("予定の追加に失敗しました：{:?}", error)
+                    }
+                    error => println!("予定の追加に失敗しました：{:?}", error),
+                },
             }
         }
+        Commands::Delete { id } => match store.delete_schedule(id) {
+            Ok(true) => println!("予定を削除しました。"),
+            Ok(false) => println!("エラー：IDが不正です"),
+            Err(_) => println!("エラー：予定の削除に失敗しました"),
+        },
+        Commands::Free {
+            from,
+            to,
+            day_start,
+            day_end,
+            min_minutes,
+            weekdays,
+        } => {
+            let from = from.unwrap_or_else(|| clock.now().date());
+            let query = FreeSlotQuery {
+                from,
+                // NOTE: 日付の上限付近では、表せる最後の日までを対象にする
+                to: to
+                    .or_else(|| from.checked_add_days(Days::new(6)))
+                    .unwrap_or(NaiveDate::MAX),
+                day_start,
+                day_end,
+                min_duration: Duration::minutes(min_minutes.into()),
+                weekdays_only: weekdays,
+            };
+            if let Some(calendar) = read_calendar(store) {
+                show_free_slots(&calendar, &query);
+            }
+        }
+        Commands::Migrate { to, output } => {
+            let Some(calendar) = read_calendar(store) else {
+                return;
+            };
+            let output = output.unwrap_or_else(|| to.default_path());
+            let result = match to {
+                StoreKind::Json => storage::JsonStore::new(&output).save_calendar(&calendar),
+                StoreKind::Sqlite => storage::SqliteStore::open(&output)
+                    .and_then(|mut target| target.save_calendar(&calendar)),
+            };
+            match result {
+                Ok(_) => println!(
+                    "{}件の予定を {} に移行しました。",
+                    calendar.schedules.len(),
+                    output.display()
+                ),
+                Err(error) => println!("エラー：予定の移行に失敗しました：{:?}", error),
+            }
+        }
+        Commands::Remind {
+            before,
+            interval,
+            exec,
+            log,
+            once,
+        } => {
+            let notifier = match (exec, log) {
+                (Some(command), _) => Notifier::Exec(command),
+                (None, Some(path)) => Notifier::Log(path),
+                (None, None) => Notifier::Stdout,
+            };
+            let options = RemindOptions {
+                default_before: Duration::minutes(before.into()),
+                interval: std::time::Duration::from_secs(interval),
+                once,
+                notifier,
+            };
+            remind::run(store, store_path, &options);
+        }
+        Commands::Serve { bind } => {
+            if let Err(error) = server::run(store_path, &bind) {
+                println!("エラー：サーバーの起動に失敗しました：{:?}", error);
+            }
+        }
+        Commands::Rooms { command } => run_room_command(store, clock, command),
+        Commands::Tui => {
+            if let Err(error) = tui::run(store, clock) {
+                println!("エラー：画面の表示に失敗しました：{:?}", error);
+            }
+        }
+        Commands::Undo => match History::for_store(store_path).undo(store) {
+            Ok(Some(change)) => println!(
+                "{}を取り消しました：{}",
+                change.action.name(),
+                describe(&change.action)
+            ),
+            Ok(None) => println!("取り消せる操作がありません"),
+            Err(MyError::HistoryMismatch(seq)) => {
+                println!(
+                    "エラー：予定が履歴（#{}）と一致しないため取り消せません",
+                    seq
+                )
+            }
+            Err(error) => println!("エラー：操作の取り消しに失敗しました：{:?}", error),
+        },
+        Commands::Redo => match History::for_store(store_path).redo(store) {
+            Ok(Some(change)) => println!(
+                "{}をやり直しました：{}",
+                change.action.name(),
+                describe(&change.action)
+            ),
+            Ok(None) => println!("やり直せる操作がありません"),
+            Err(MyError::HistoryMismatch(seq)) => {
+                println!(
+                    "エラー：予定が履歴（#{}）と一致しないためやり直せません",
+                    seq
+                )
+            }
+            Err(error) => println!("エラー：操作のやり直しに失敗しました：{:?}", error),
+        },
+        Commands::History { limit } => match History::for_store(store_path).state() {
+            Ok(state) => show_history(&state, limit),
+            Err(error) => println!("エラー：変更履歴の読み込みに失敗しました：{:?}", error),
+        },
     }
 }
 
-fn read_calendar() -> Result<Calendar, MyError> {
-    // NOTE: Result 型の後ろに ? を付けることで、Err が返る場合はそのまま返すことができる
-    let file = File::open("schedule.json")?;
-    let reader = BufReader::new(file);
-    let calendar = serde_json::from_reader(reader)?;
-    Ok(calendar)
+fn run_room_command(store: &mut dyn CalendarStore, clock: &dyn Clock, command: RoomCommands) {
+    let Some(mut calendar) = read_calendar(store) else {
+        return;
+    };
+    match command {
+        RoomCommands::Add {
+            name,
+            capacity,
+            location,
+        } => {
+            if room::find_room(&calendar, &name).is_some() {
+                println!("エラー：会議室「{}」は登録済みです", name);
+                return;
+            }
+            calendar.rooms.push(Room {
+                name,
+                capacity,
+                location,
+            });
+            match store.save_calendar(&calendar) {
+                Ok(_) => println!("会議室を追加しました。"),
+                Err(error) => println!("エラー：会議室の追加に失敗しました：{:?}", error),
+            }
+        }
+        RoomCommands::Remove { name } => {
+            if room::find_room(&calendar, &name).is_none() {
+                println!("エラー：会議室「{}」は登録されていません", name);
+                return;
+            }
+            // NOTE: 予約が残ったまま削除すると、予約の重複や定員を確認できなくなる
+            let reservations: Vec<&Schedule> = calendar
+                .schedules
+                .iter()
+                .filter(|schedule| schedule.rooms.contains(&name))
+                .collect();
+            if !reservations.is_empty() {
+                println!("エラー：会議室「{}」を予約している予定があります", name);
+                for schedule in reservations {
+                    println!(
+                        "  {}\t{}\t{}\t{}",
+                        schedule.id, schedule.start, schedule.end, schedule.subject
+                    );
+                }
+                return;
+            }
+            calendar.rooms.retain(|room| room.name != name);
+            match store.save_calendar(&calendar) {
+                Ok(_) => println!("会議室を削除しました。"),
+                Err(error) => println!("エラー：会議室の削除に失敗しました：{:?}", error),
+            }
+        }
+        RoomCommands::List => {
+            for room in &calendar.rooms {
+                match &room.location {
+                    Some(location) => {
+                        println!("{}\t定員{}人\t{}", room.name, room.capacity, location)
+                    }
+                    None => println!("{}\t定員{}人", room.name, room.capacity),
+                }
+            }
+        }
+        RoomCommands::Availability {
+            start,
+            end,
+            duration,
+            capacity,
+        } => {
+            let (start, end) =
+                match resolve_period(&start, end.as_deref(), duration.as_deref(), clock) {
+                    Ok(period) => period,
+                    Err(error) => {
+                        println!("エラー：時間帯を解釈できません：{}", error);
+                        return;
+                    }
+                };
+            let availability = room::availability(&calendar, start, end, capacity);
+            if availability.is_empty() {
+                println!("条件に合う会議室がありません");
+                return;
+            }
+            print!("{}", room::render_availability(&availability));
+        }
+    }
 }
 
-fn save_calendar(calendar: &Calendar) -> Result<(), MyError> {
-    // NOTE: map_err() によって独自のエラー型にマッピングできる
-    let file = File::create("schedule.json")?;
-    let writer = BufWriter::new(file);
-    serde_json::to_writer(writer, calendar)?;
-    Ok(())
+fn print_room_error(error: MyError) {
+    match error {
+        MyError::UnknownRoom(name) => println!("エラー：会議室「{}」は登録されていません", name),
+        MyError::OverCapacity {
+            capacity,
+            attendees,
+        } => println!(
+            "エラー：参加者（{}人）が会議室の定員（{}人）を超えています",
+            attendees, capacity
+        ),
+        error => println!("エラー：会議室を予約できません：{:?}", error),
+    }
 }
 
-fn show_list(calendar: Calendar) {
-    // 予定の表示
-    println!("ID\tSTART\tEND\tSUBJECT");
-    for schedule in calendar.schedules {
-        println!(
+fn describe(action: &Action) -> String {
+    match action.schedule() {
+        Some(schedule) => format!(
             "{}\t{}\t{}\t{}",
             schedule.id, schedule.start, schedule.end, schedule.subject
+        ),
+        None => String::new(),
+    }
+}
+
+fn show_history(state: &HistoryState, limit: usize) {
+    // NOTE: 取り消した操作は、やり直す順（次にやり直すものが先頭）で適用中の操作の上に表示する
+    let changes = state
+        .undone
+        .iter()
+        .map(|change| (change, true))
+        .chain(state.applied.iter().rev().map(|change| (change, false)))
+        .take(limit);
+    for (change, undone) in changes {
+        println!(
+            "#{}\t{}\t{}{}\t{}",
+            change.seq,
+            change.at.format("%Y-%m-%d %H:%M:%S"),
+            change.action.name(),
+            if undone {
+                "（取り消し済み）"
+            } else {
+                ""
+            },
+            describe(&change.action)
         );
     }
 }
 
+/// 開始時刻と、終了時刻または長さの入力から予定の期間を求める
+fn resolve_period(
+    start: &str,
+    end: Option<&str>,
+    duration: Option<&str>,
+    clock: &dyn Clock,
+) -> Result<(NaiveDateTime, NaiveDateTime), MyError> {
+    let start = datetime::parse_date_time(start, clock)?;
+    let end = match (end, duration) {
+        (Some(end), _) => datetime::parse_end(end, start, clock)?,
+        (None, Some(duration)) => start
+            .checked_add_signed(datetime::parse_duration(duration)?)
+            .ok_or_else(|| MyError::InvalidDuration(duration.to_string()))?,
+        // NOTE: clap で終了時刻か長さのどちらかを必須にしている
+        (None, None) => unreachable!(),
+    };
+    Ok((start, end))
+}
+
+fn read_calendar(store: &dyn CalendarStore) -> Option<Calendar> {
+    match store.read_calendar() {
+        Ok(calendar) => Some(calendar),
+        Err(error) => {
+            println!("カレンダーの読み込みに失敗しました：{:?}", error);
+            None
+        }
+    }
+}
+
+fn show_free_slots(calendar: &Calendar, query: &FreeSlotQuery) {
+    // 空き時間の表示
+    let slots = match free::find_free_slots(&calendar.schedules, query) {
+        Ok(slots) => slots,
+        Err(error) => {
+            println!("エラー：空き時間を探せませんでした：{:?}", error);
+            return;
+        }
+    };
+    println!("START\tEND\tMINUTES");
+    for slot in slots {
+        println!(
+            "{}\t{}\t{}",
+            slot.start,
+            slot.end,
+            slot.duration().num_minutes()
+        );
+    }
+}
+
+fn show_list(calendar: &Calendar, filter: &ListFilter, format: OutputFormat, clock: &dyn Clock) {
+    // 予定の表示
+    let schedules = list::filter_schedules(&calendar.schedules, filter);
+    // NOTE: ICS の DTSTAMP は UTC で書き出すので、時計の示すローカル時刻を変換する
+    let now = clock.now();
+    let stamp = Local
+        .from_local_datetime(&now)
+        .earliest()
+        .map_or(now, |now| now.naive_utc());
+    match list::render(&schedules, format, stamp) {
+        Ok(output) => print!("{}", output),
+        Err(error) => println!("エラー：予定の出力に失敗しました：{:?}", error),
+    }
+}
+
+fn show_grid(calendar: &Calendar, filter: &ListFilter, view: GridView, date: NaiveDate) {
+    // NOTE: カレンダー形式では期間の絞り込みは表示範囲で代替する
+    let filter = ListFilter {
+        search: filter.search.clone(),
+        calendar: filter.calendar.clone(),
+        tags: filter.tags.clone(),
+        ..Default::default()
+    };
+    let schedules = list::filter_schedules(&calendar.schedules, &filter);
+    print!("{}", list::render_grid(&schedules, view, date));
+}
+
+/// 予定を追加し、IDを割り当てた予定を返す。重複する予定がある場合は追加せず、重複する予定を返す
 fn add_schedule(
     calendar: &mut Calendar,
-    subject: String,
-    start: NaiveDateTime,
-    end: NaiveDateTime,
-) -> bool {
+    mut new_schedule: Schedule,
+    scope: ConflictScope,
+) -> Result<Schedule, Vec<Schedule>> {
     // 予定の作成
-    let id = calendar.schedules.len() as u64;
-    let new_schedule = Schedule {
-        id,
-        subject,
-        start,
-        end,
-    };
+    new_schedule.id = next_id(calendar);
 
     // 予定の重複判定
-    for schedule in &calendar.schedules {
-        if schedule.intersects(&new_schedule) {
-            return false;
-        }
+    let conflicts = find_conflicts(calendar, &new_schedule, scope);
+    if !conflicts.is_empty() {
+        return Err(conflicts.into_iter().cloned().collect());
     }
 
     // 予定の追加
-    calendar.schedules.push(new_schedule);
-    true
+    calendar.schedules.push(new_schedule.clone());
+    Ok(new_schedule)
+}
+
+/// 新しい予定に割り当てるID
+fn next_id(calendar: &Calendar) -> u64 {
+    // NOTE: 削除済みの予定とIDが重複しないよう、最大のID + 1 を割り当てる
+    calendar
+        .schedules
+        .iter()
+        .map(|schedule| schedule.id + 1)
+        .max()
+        .unwrap_or_default()
+}
+
+/// 指定した予定と重複する予定を返す（同じIDの予定は対象外）
+fn find_conflicts<'a>(
+    calendar: &'a Calendar,
+    target: &Schedule,
+    scope: ConflictScope,
+) -> Vec<&'a Schedule> {
+    calendar
+        .schedules
+        .iter()
+        .filter(|schedule| schedule.id != target.id)
+        .filter(|schedule| scope.applies_to(target, schedule))
+        .filter(|schedule| schedule.intersects(target))
+        .collect()
 }
 
 fn delete_schedule(calendar: &mut Calendar, id: u64) -> bool {
@@ -186,7 +929,7 @@ mod tests {
     use chrono::NaiveDate;
     use rstest::rstest;
 
-    fn naive_date_time(
+    pub fn naive_date_time(
         year: i32,
         month: u32,
         day: u32,
@@ -219,12 +962,14 @@ mod tests {
             subject: "既存予定".to_string(),
             start: naive_date_time(2024, 1, 1, h0, m0, 0),
             end: naive_date_time(2024, 1, 1, h1, m1, 0),
+            ..Default::default()
         };
         let new_schedule = Schedule {
             id: 999,
             subject: "新規予定".to_string(),
             start: naive_date_time(2024, 1, 1, 19, 0, 0),
             end: naive_date_time(2024, 1, 1, 20, 0, 0),
+            ..Default::default()
         };
         assert_eq!(should_intersect, schedule.intersects(&new_schedule));
     }
@@ -233,40 +978,124 @@ mod tests {
     fn test_add_schedule() {
         // Arrange
         let expected = Calendar {
+            version: CALENDAR_VERSION,
             schedules: vec![
                 Schedule {
                     id: 0,
                     subject: "テスト予定".to_string(),
                     start: naive_date_time(2023, 11, 19, 11, 22, 33),
                     end: naive_date_time(2023, 11, 19, 22, 33, 44),
+                    ..Default::default()
                 },
                 Schedule {
                     id: 1,
                     subject: "テスト予定2".to_string(),
                     start: naive_date_time(2023, 12, 8, 9, 0, 0),
                     end: naive_date_time(2023, 12, 8, 10, 30, 0),
+                    ..Default::default()
                 },
             ],
+            rooms: vec![],
         };
         let mut calendar = Calendar {
+            version: CALENDAR_VERSION,
             schedules: vec![Schedule {
                 id: 0,
                 subject: "テスト予定".to_string(),
                 start: naive_date_time(2023, 11, 19, 11, 22, 33),
                 end: naive_date_time(2023, 11, 19, 22, 33, 44),
+                ..Default::default()
             }],
+            rooms: vec![],
         };
 
         // Act
         let actual = add_schedule(
             &mut calendar,
-            "テスト予定2".to_string(),
-            naive_date_time(2023, 12, 8, 9, 0, 0),
-            naive_date_time(2023, 12, 8, 10, 30, 0),
+            Schedule {
+                subject: "テスト予定2".to_string(),
+                start: naive_date_time(2023, 12, 8, 9, 0, 0),
+                end: naive_date_time(2023, 12, 8, 10, 30, 0),
+                ..Default::default()
+            },
+            ConflictScope::All,
         );
 
         // Assert
-        assert!(actual);
+        assert_eq!(Ok(expected.schedules[1].clone()), actual);
         assert_eq!(expected, calendar);
     }
+
+    #[rstest]
+    #[case("rust-study", ConflictScope::Calendar, false)]
+    #[case("team", ConflictScope::Calendar, true)]
+    #[case("team", ConflictScope::All, false)]
+    fn test_add_schedule_conflict_scope(
+        #[case] calendar_name: &str,
+        #[case] scope: ConflictScope,
+        #[case] should_add: bool,
+    ) {
+        let mut calendar = Calendar {
+            version: CALENDAR_VERSION,
+            schedules: vec![Schedule {
+                id: 0,
+                calendar: "rust-study".to_string(),
+                subject: "既存予定".to_string(),
+                start: naive_date_time(2024, 1, 1, 19, 0, 0),
+                end: naive_date_time(2024, 1, 1, 20, 0, 0),
+                ..Default::default()
+            }],
+            rooms: vec![],
+        };
+
+        let actual = add_schedule(
+            &mut calendar,
+            Schedule {
+                calendar: calendar_name.to_string(),
+                subject: "新規予定".to_string(),
+                start: naive_date_time(2024, 1, 1, 19, 30, 0),
+                end: naive_date_time(2024, 1, 1, 20, 30, 0),
+                ..Default::default()
+            },
+            scope,
+        );
+
+        assert_eq!(should_add, actual.is_ok());
+    }
+
+    #[rstest]
+    #[case("tomorrow 19:00", Some("20:30"), None, (4, 19, 0), (4, 20, 30))]
+    #[case("tomorrow 19:00", None, Some("90m"), (4, 19, 0), (4, 20, 30))]
+    #[case("next tue 18:30", Some("next tue 20:00"), None, (9, 18, 30), (9, 20, 0))]
+    #[case("2024-01-05 23:00", None, Some("2h"), (5, 23, 0), (6, 1, 0))]
+    fn test_resolve_period(
+        #[case] start: &str,
+        #[case] end: Option<&str>,
+        #[case] duration: Option<&str>,
+        #[case] expected_start: (u32, u32, u32),
+        #[case] expected_end: (u32, u32, u32),
+    ) {
+        let clock = datetime::FixedClock(naive_date_time(2024, 1, 3, 12, 0, 0));
+        let (day, hour, minute) = expected_start;
+        let (end_day, end_hour, end_minute) = expected_end;
+
+        let actual = resolve_period(start, end, duration, &clock).unwrap();
+
+        assert_eq!(
+            (
+                naive_date_time(2024, 1, day, hour, minute, 0),
+                naive_date_time(2024, 1, end_day, end_hour, end_minute, 0)
+            ),
+            actual
+        );
+    }
+
+    #[test]
+    fn test_resolve_period_out_of_range() {
+        let clock = datetime::FixedClock(naive_date_time(2024, 1, 3, 12, 0, 0));
+
+        let actual = resolve_period("2024-01-05 23:00", None, Some("9999999999h"), &clock);
+
+        assert!(matches!(actual, Err(MyError::InvalidDuration(_))));
+    }
 }
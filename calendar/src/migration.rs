@@ -0,0 +1,91 @@
+use crate::{Calendar, MyError, CALENDAR_VERSION, DEFAULT_CALENDAR};
+use serde_json::{json, Value};
+
+/// 古い形式のカレンダーファイルを現在の形式に変換して読み込む
+pub fn migrate(mut value: Value) -> Result<Calendar, MyError> {
+    // NOTE: version を持たないファイルは最初の形式（version 1）として扱う
+    let mut version = value.get("version").and_then(Value::as_u64).unwrap_or(1);
+    if version > CALENDAR_VERSION as u64 {
+        return Err(MyError::UnsupportedVersion(version));
+    }
+
+    while version < CALENDAR_VERSION as u64 {
+        match version {
+            1 => migrate_v1_to_v2(&mut value),
+            2 => migrate_v2_to_v3(&mut value),
+            _ => return Err(MyError::UnsupportedVersion(version)),
+        }
+        version += 1;
+    }
+    value["version"] = json!(CALENDAR_VERSION);
+
+    Ok(serde_json::from_value(value)?)
+}
+
+/// version 2 で追加したカレンダー名・場所・説明・タグ・参加者を補完する
+fn migrate_v1_to_v2(value: &mut Value) {
+    let Some(schedules) = value.get_mut("schedules").and_then(Value::as_array_mut) else {
+        return;
+    };
+    for schedule in schedules.iter_mut().filter_map(Value::as_object_mut) {
+        schedule
+            .entry("calendar")
+            .or_insert_with(|| json!(DEFAULT_CALENDAR));
+        schedule.entry("tags").or_insert_with(|| json!([]));
+        schedule.entry("attendees").or_insert_with(|| json!([]));
+    }
+}
+
+/// version 3 で追加した会議室の一覧を補完する
+fn migrate_v2_to_v3(value: &mut Value) {
+    if let Some(calendar) = value.as_object_mut() {
+        calendar.entry("rooms").or_insert_with(|| json!([]));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::naive_date_time;
+    use crate::Schedule;
+
+    #[test]
+    fn test_migrate_v1() {
+        let value = json!({
+            "schedules": [
+                {
+                    "id": 0,
+                    "subject": "テスト予定",
+                    "start": "2024-11-30T11:00:00",
+                    "end": "2024-11-30T16:30:00"
+                }
+            ]
+        });
+
+        let actual = migrate(value).unwrap();
+
+        assert_eq!(
+            Calendar {
+                version: CALENDAR_VERSION,
+                schedules: vec![Schedule {
+                    id: 0,
+                    subject: "テスト予定".to_string(),
+                    start: naive_date_time(2024, 11, 30, 11, 0, 0),
+                    end: naive_date_time(2024, 11, 30, 16, 30, 0),
+                    ..Default::default()
+                }],
+                rooms: vec![],
+            },
+            actual
+        );
+    }
+
+    #[test]
+    fn test_migrate_rejects_newer_version() {
+        let value = json!({ "version": CALENDAR_VERSION + 1, "schedules": [] });
+
+        let actual = migrate(value);
+
+        assert!(matches!(actual, Err(MyError::UnsupportedVersion(_))));
+    }
+}
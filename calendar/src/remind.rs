@@ -0,0 +1,226 @@
+use crate::{storage::CalendarStore, Calendar, Schedule};
+use chrono::{Duration, Local, NaiveDateTime};
+use std::{
+    collections::HashSet,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::SystemTime,
+};
+
+/// リマインダーの通知先
+#[derive(Debug)]
+pub enum Notifier {
+    /// 標準出力に表示する
+    Stdout,
+    /// ファイルに追記する
+    Log(PathBuf),
+    /// 任意のコマンドを実行する（予定の内容は環境変数で渡す）
+    Exec(String),
+}
+impl Notifier {
+    fn notify(&self, schedule: &Schedule, now: NaiveDateTime) -> std::io::Result<()> {
+        let message = message(schedule, now);
+        match self {
+            Notifier::Stdout => println!("{}", message),
+            Notifier::Log(path) => {
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                writeln!(file, "{}", message)?;
+            }
+            Notifier::Exec(command) => {
+                let status = shell(command)
+                    .env("SCHEDULE_ID", schedule.id.to_string())
+                    .env("SCHEDULE_CALENDAR", &schedule.calendar)
+                    .env("SCHEDULE_SUBJECT", &schedule.subject)
+                    .env("SCHEDULE_START", schedule.start.to_string())
+                    .env("SCHEDULE_END", schedule.end.to_string())
+                    .env(
+                        "SCHEDULE_LOCATION",
+                        schedule.location.as_deref().unwrap_or_default(),
+                    )
+                    .env("SCHEDULE_MESSAGE", &message)
+                    .status()?;
+                if !status.success() {
+                    println!("エラー：通知コマンドが失敗しました：{}", status);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+/// リマインダーの設定
+#[derive(Debug)]
+pub struct RemindOptions {
+    /// 予定ごとの指定がない場合に、開始時刻の何分前に通知するか
+    pub default_before: Duration,
+    /// 保存先の更新と通知時刻を確認する間隔
+    pub interval: std::time::Duration,
+    /// 1回だけ確認して終了する
+    pub once: bool,
+    pub notifier: Notifier,
+}
+
+/// 予定の開始前に通知を送り続ける
+pub fn run(store: &dyn CalendarStore, store_path: &Path, options: &RemindOptions) {
+    let mut calendar: Option<Calendar> = None;
+    let mut modified: Option<SystemTime> = None;
+    // NOTE: 同じ予定を繰り返し通知しないよう、ID と開始時刻の組で通知済みを記録する
+    let mut notified = HashSet::new();
+
+    loop {
+        // 保存先が更新されていれば読み込み直す
+        let current = fs::metadata(store_path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if calendar.is_none() || current != modified {
+            match store.read_calendar() {
+                Ok(loaded) => {
+                    if calendar.is_some() {
+                        println!("カレンダーを読み込み直しました。");
+                    }
+                    calendar = Some(loaded);
+                    modified = current;
+                }
+                Err(error) => println!("カレンダーの読み込みに失敗しました：{:?}", error),
+            }
+        }
+
+        if let Some(calendar) = &calendar {
+            let now = Local::now().naive_local();
+            for schedule in due_reminders(&calendar.schedules, options.default_before, now) {
+                if !notified.insert((schedule.id, schedule.start)) {
+                    continue;
+                }
+                if let Err(error) = options.notifier.notify(schedule, now) {
+                    println!("エラー：通知に失敗しました：{:?}", error);
+                }
+            }
+        }
+
+        if options.once {
+            break;
+        }
+        thread::sleep(options.interval);
+    }
+}
+
+/// 通知時刻を過ぎていて、まだ始まっていない予定を開始時刻順に返す
+pub fn due_reminders(
+    schedules: &[Schedule],
+    default_before: Duration,
+    now: NaiveDateTime,
+) -> Vec<&Schedule> {
+    let mut due: Vec<&Schedule> = schedules
+        .iter()
+        .filter(|schedule| {
+            let before = schedule
+                .remind_before
+                .map(|minutes| Duration::minutes(minutes.into()))
+                .unwrap_or(default_before);
+            schedule.start - before <= now && now < schedule.start
+        })
+        .collect();
+    due.sort_by_key(|schedule| (schedule.start, schedule.id));
+    due
+}
+
+fn message(schedule: &Schedule, now: NaiveDateTime) -> String {
+    // NOTE: 秒以下は切り上げて「あと何分」を表示する
+    let minutes = (schedule.start - now + Duration::seconds(59)).num_minutes();
+    let mut message = format!(
+        "[{}] {}分後に「{}」が始まります（{}〜{}",
+        now.format("%Y-%m-%d %H:%M"),
+        minutes,
+        schedule.subject,
+        schedule.start.format("%H:%M"),
+        schedule.end.format("%H:%M")
+    );
+    if let Some(location) = &schedule.location {
+        message.push_str(&format!("、{}", location));
+    }
+    message.push('）');
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::naive_date_time;
+
+    #[test]
+    fn test_due_reminders() {
+        let schedules = vec![
+            Schedule::test(
+                0,
+                "勉強会0",
+                naive_date_time(2024, 1, 1, 19, 0, 0),
+                naive_date_time(2024, 1, 1, 20, 0, 0),
+            ),
+            Schedule {
+                remind_before: Some(90),
+                ..Schedule::test(
+                    1,
+                    "勉強会1",
+                    naive_date_time(2024, 1, 1, 20, 0, 0),
+                    naive_date_time(2024, 1, 1, 21, 0, 0),
+                )
+            },
+            Schedule::test(
+                2,
+                "勉強会2",
+                naive_date_time(2024, 1, 1, 20, 0, 0),
+                naive_date_time(2024, 1, 1, 21, 0, 0),
+            ),
+            Schedule::test(
+                3,
+                "勉強会3",
+                naive_date_time(2024, 1, 1, 18, 0, 0),
+                naive_date_time(2024, 1, 1, 19, 0, 0),
+            ),
+        ];
+
+        let actual = due_reminders(
+            &schedules,
+            Duration::minutes(10),
+            naive_date_time(2024, 1, 1, 18, 55, 0),
+        );
+
+        let ids: Vec<u64> = actual.iter().map(|schedule| schedule.id).collect();
+        assert_eq!(vec![0, 1], ids);
+    }
+
+    #[test]
+    fn test_message() {
+        let mut schedule = Schedule::test(
+            0,
+            "勉強会0",
+            naive_date_time(2024, 1, 1, 19, 0, 0),
+            naive_date_time(2024, 1, 1, 20, 0, 0),
+        );
+        schedule.location = Some("会議室A".to_string());
+
+        let actual = message(&schedule, naive_date_time(2024, 1, 1, 18, 50, 30));
+
+        assert_eq!(
+            "[2024-01-01 18:50] 10分後に「勉強会0」が始まります（19:00〜20:00、会議室A）",
+            actual
+        );
+    }
+}
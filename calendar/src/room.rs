@@ -0,0 +1,269 @@
+use crate::{Calendar, MyError, Room, Schedule};
+use chrono::NaiveDateTime;
+use std::{collections::HashSet, fmt::Write};
+use unicode_width::UnicodeWidthStr;
+
+/// 予定で予約する会議室が登録済みで、参加者が定員に収まるかを確認する
+// NOTE: 複数の会議室を予約した場合は、定員の合計に収まればよい（同じ会議室は1回だけ数える）
+pub fn check_reservation(calendar: &Calendar, schedule: &Schedule) -> Result<(), MyError> {
+    let mut capacity: u32 = 0;
+    let mut counted = HashSet::new();
+    for name in &schedule.rooms {
+        let room = find_room(calendar, name).ok_or_else(|| MyError::UnknownRoom(name.clone()))?;
+        if counted.insert(name) {
+            capacity = capacity.saturating_add(room.capacity);
+        }
+    }
+    if !schedule.rooms.is_empty() && schedule.attendees.len() > capacity as usize {
+        return Err(MyError::OverCapacity {
+            capacity,
+            attendees: schedule.attendees.len(),
+        });
+    }
+    Ok(())
+}
+
+pub fn find_room<'a>(calendar: &'a Calendar, name: &str) -> Option<&'a Room> {
+    calendar.rooms.iter().find(|room| room.name == name)
+}
+
+/// 時間帯ごとの会議室の空き状況
+#[derive(Debug, PartialEq, Eq)]
+pub struct RoomAvailability<'a> {
+    pub room: &'a Room,
+    /// 時間帯と重なる予約（開始時刻順）
+    pub reservations: Vec<&'a Schedule>,
+}
+impl RoomAvailability<'_> {
+    pub fn is_free(&self) -> bool {
+        self.reservations.is_empty()
+    }
+}
+
+/// 定員が capacity 人以上の会議室について、指定した時間帯の予約状況を求める
+pub fn availability(
+    calendar: &Calendar,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    capacity: u32,
+) -> Vec<RoomAvailability<'_>> {
+    let mut rooms: Vec<&Room> = calendar
+        .rooms
+        .iter()
+        .filter(|room| room.capacity >= capacity)
+        .collect();
+    // NOTE: 必要な人数に近い、小さい会議室から順に並べる
+    rooms.sort_by(|a, b| a.capacity.cmp(&b.capacity).then(a.name.cmp(&b.name)));
+
+    rooms
+        .into_iter()
+        .map(|room| {
+            let mut reservations: Vec<&Schedule> = calendar
+                .schedules
+                .iter()
+                .filter(|schedule| schedule.rooms.contains(&room.name))
+                .filter(|schedule| schedule.intersects_range(start, end))
+                .collect();
+            reservations.sort_by_key(|schedule| (schedule.start, schedule.id));
+            RoomAvailability { room, reservations }
+        })
+        .collect()
+}
+
+/// 空き状況を表形式で出力する
+pub fn render_availability(availability: &[RoomAvailability]) -> String {
+    let name_width = availability
+        .iter()
+        .map(|entry| entry.room.name.width())
+        .chain(["ROOM".width()])
+        .max()
+        .unwrap_or_default();
+    let pad = |name: &str| format!("{}{}", name, " ".repeat(name_width - name.width()));
+
+    let mut output = String::new();
+    writeln!(output, "{}  CAPACITY  STATUS", pad("ROOM")).unwrap();
+    for entry in availability {
+        let status = if entry.is_free() {
+            "空き".to_string()
+        } else {
+            let reservations: Vec<String> = entry
+                .reservations
+                .iter()
+                .map(|schedule| {
+                    format!(
+                        "{} {}〜{} {}",
+                        schedule.id,
+                        schedule.start.format("%Y-%m-%d %H:%M"),
+                        schedule.end.format("%H:%M"),
+                        schedule.subject
+                    )
+                })
+                .collect();
+            format!("予約あり（{}）", reservations.join("、"))
+        };
+        writeln!(
+            output,
+            "{}  {:<8}  {}",
+            pad(&entry.room.name),
+            entry.room.capacity,
+            status
+        )
+        .unwrap();
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::naive_date_time;
+    use crate::{add_schedule, ConflictScope, CALENDAR_VERSION};
+    use rstest::rstest;
+
+    fn room(name: &str, capacity: u32) -> Room {
+        Room {
+            name: name.to_string(),
+            capacity,
+            location: None,
+        }
+    }
+
+    fn sample_calendar() -> Calendar {
+        Calendar {
+            version: CALENDAR_VERSION,
+            schedules: vec![
+                Schedule {
+                    rooms: vec!["会議室A".to_string()],
+                    ..Schedule::test(
+                        0,
+                        "勉強会0",
+                        naive_date_time(2024, 1, 1, 19, 0, 0),
+                        naive_date_time(2024, 1, 1, 21, 0, 0),
+                    )
+                },
+                Schedule {
+                    rooms: vec!["会議室B".to_string(), "会議室C".to_string()],
+                    ..Schedule::test(
+                        1,
+                        "勉強会1",
+                        naive_date_time(2024, 1, 1, 18, 0, 0),
+                        naive_date_time(2024, 1, 1, 19, 0, 0),
+                    )
+                },
+            ],
+            rooms: vec![room("会議室A", 10), room("会議室B", 4), room("会議室C", 6)],
+        }
+    }
+
+    #[rstest]
+    #[case(&["会議室A"], 10, None)]
+    #[case(&["会議室A"], 11, Some(MyError::OverCapacity { capacity: 10, attendees: 11 }))]
+    #[case(&["会議室B", "会議室C"], 10, None)]
+    #[case(&["会議室A", "会議室A"], 11, Some(MyError::OverCapacity { capacity: 10, attendees: 11 }))]
+    #[case(&["会議室Z"], 1, Some(MyError::UnknownRoom("会議室Z".to_string())))]
+    #[case(&[], 100, None)]
+    fn test_check_reservation(
+        #[case] rooms: &[&str],
+        #[case] attendees: usize,
+        #[case] expected: Option<MyError>,
+    ) {
+        let schedule = Schedule {
+            attendees: (0..attendees).map(|i| format!("user{}", i)).collect(),
+            rooms: rooms.iter().map(|room| room.to_string()).collect(),
+            ..Schedule::test(
+                2,
+                "勉強会2",
+                naive_date_time(2024, 1, 1, 9, 0, 0),
+                naive_date_time(2024, 1, 1, 10, 0, 0),
+            )
+        };
+
+        let actual = check_reservation(&sample_calendar(), &schedule).err();
+
+        assert_eq!(
+            expected.map(|error| error.to_string()),
+            actual.map(|error| error.to_string())
+        );
+    }
+
+    #[test]
+    fn test_check_reservation_large_capacity() {
+        let mut calendar = sample_calendar();
+        calendar.rooms = vec![room("ホールA", u32::MAX), room("ホールB", u32::MAX)];
+
+        let actual = check_reservation(
+            &calendar,
+            &Schedule {
+                rooms: vec!["ホールA".to_string(), "ホールB".to_string()],
+                ..Schedule::test(
+                    2,
+                    "勉強会2",
+                    naive_date_time(2024, 1, 1, 9, 0, 0),
+                    naive_date_time(2024, 1, 1, 10, 0, 0),
+                )
+            },
+        );
+
+        assert!(actual.is_ok());
+    }
+
+    #[rstest]
+    #[case(&["会議室A"], ConflictScope::Room, false)]
+    #[case(&["会議室B"], ConflictScope::Room, true)]
+    #[case(&[], ConflictScope::Room, true)]
+    #[case(&[], ConflictScope::All, false)]
+    #[case(&["会議室A"], ConflictScope::Calendar, false)]
+    fn test_add_schedule_per_room(
+        #[case] rooms: &[&str],
+        #[case] scope: ConflictScope,
+        #[case] should_add: bool,
+    ) {
+        let mut calendar = sample_calendar();
+
+        let actual = add_schedule(
+            &mut calendar,
+            Schedule {
+                rooms: rooms.iter().map(|room| room.to_string()).collect(),
+                ..Schedule::test(
+                    0,
+                    "勉強会0",
+                    naive_date_time(2024, 1, 1, 20, 0, 0),
+                    naive_date_time(2024, 1, 1, 22, 0, 0),
+                )
+            },
+            scope,
+        );
+
+        assert_eq!(should_add, actual.is_ok());
+    }
+
+    #[test]
+    fn test_availability() {
+        let calendar = sample_calendar();
+        let start = naive_date_time(2024, 1, 1, 18, 30, 0);
+        let end = naive_date_time(2024, 1, 1, 19, 30, 0);
+
+        let actual = availability(&calendar, start, end, 5);
+
+        assert_eq!(
+            vec![
+                RoomAvailability {
+                    room: &calendar.rooms[2],
+                    reservations: vec![&calendar.schedules[1]],
+                },
+                RoomAvailability {
+                    room: &calendar.rooms[0],
+                    reservations: vec![&calendar.schedules[0]],
+                },
+            ],
+            actual
+        );
+        assert_eq!(
+            "ROOM     CAPACITY  STATUS\n\
+             会議室C  6         予約あり（1 2024-01-01 18:00〜19:00 勉強会1）\n\
+             会議室A  10        予約あり（0 2024-01-01 19:00〜21:00 勉強会0）\n",
+            render_availability(&actual)
+        );
+        assert!(availability(&calendar, start, end, 11).is_empty());
+    }
+}
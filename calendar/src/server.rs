@@ -0,0 +1,306 @@
+use crate::{
+    dav,
+    list::{self, ListFilter},
+    room,
+    storage::{self, CalendarStore, WriteOutcome},
+    ConflictScope, MyError, Schedule, DEFAULT_CALENDAR,
+};
+use actix_web::{delete, get, patch, post, web, App, HttpResponse, HttpServer};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+use serde_json::json;
+use std::{path::Path, sync::Mutex};
+
+pub type SharedStore = Mutex<Box<dyn CalendarStore>>;
+
+/// 予定を操作する REST API サーバーを起動する
+pub fn run(store_path: &Path, address: &str) -> Result<(), MyError> {
+    let store: web::Data<SharedStore> = web::Data::new(Mutex::new(storage::open(store_path)?));
+
+    actix_web::rt::System::new().block_on(async move {
+        // NOTE: サーバーに組み込みたい変数を web::Data に詰め込む
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(store.clone())
+                .app_data(web::JsonConfig::default().error_handler(|error, _| {
+                    let response = error_response(
+                        HttpResponse::BadRequest(),
+                        format!("リクエストの形式が不正です：{}", error),
+                    );
+                    actix_web::error::InternalError::from_response(error, response).into()
+                }))
+                .service(get_schedules)
+                .service(create_schedule)
+                .service(update_schedule)
+                .service(delete_schedule)
+                .configure(dav::configure)
+        })
+        .bind(address)?;
+
+        // NOTE: ポート番号に 0 を指定した場合に割り当てられたポートが分かるよう、実際のアドレスを表示する
+        for address in server.addrs() {
+            println!("サーバーを起動しました：http://{}", address);
+        }
+        server.run().await
+    })?;
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct ScheduleQuery {
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    calendar: Option<String>,
+    search: Option<String>,
+}
+
+#[get("/schedules")]
+async fn get_schedules(
+    store: web::Data<SharedStore>,
+    query: web::Query<ScheduleQuery>,
+) -> HttpResponse {
+    let query = query.into_inner();
+    let filter = ListFilter {
+        from: query.from,
+        to: query.to,
+        search: query.search,
+        calendar: query.calendar,
+        ..Default::default()
+    };
+    match store.lock().unwrap().read_calendar() {
+        Ok(calendar) => {
+            HttpResponse::Ok().json(list::filter_schedules(&calendar.schedules, &filter))
+        }
+        Err(error) => internal_error(error),
+    }
+}
+
+/// POST /schedules の本文（予定から ID を除いたもの）
+#[derive(Deserialize)]
+struct NewSchedule {
+    #[serde(default = "default_calendar")]
+    calendar: String,
+    subject: String,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    #[serde(default)]
+    location: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    attendees: Vec<String>,
+    #[serde(default)]
+    remind_before: Option<u32>,
+    #[serde(default)]
+    rooms: Vec<String>,
+}
+
+fn default_calendar() -> String {
+    DEFAULT_CALENDAR.to_string()
+}
+
+/// PATCH /schedules/{id} の本文（指定した項目だけを更新する）
+#[derive(Deserialize)]
+struct SchedulePatch {
+    calendar: Option<String>,
+    subject: Option<String>,
+    start: Option<NaiveDateTime>,
+    end: Option<NaiveDateTime>,
+    location: Option<String>,
+    description: Option<String>,
+    tags: Option<Vec<String>>,
+    attendees: Option<Vec<String>>,
+    remind_before: Option<u32>,
+    rooms: Option<Vec<String>>,
+}
+impl SchedulePatch {
+    fn apply(self, schedule: &mut Schedule) {
+        if let Some(calendar) = self.calendar {
+            schedule.calendar = calendar;
+        }
+        if let Some(subject) = self.subject {
+            schedule.subject = subject;
+        }
+        if let Some(start) = self.start {
+            schedule.start = start;
+        }
+        if let Some(end) = self.end {
+            schedule.end = end;
+        }
+        if self.location.is_some() {
+            schedule.location = self.location;
+        }
+        if self.description.is_some() {
+            schedule.description = self.description;
+        }
+        if let Some(tags) = self.tags {
+            schedule.tags = tags;
+        }
+        if let Some(attendees) = self.attendees {
+            schedule.attendees = attendees;
+        }
+        if self.remind_before.is_some() {
+            schedule.remind_before = self.remind_before;
+        }
+        if let Some(rooms) = self.rooms {
+            schedule.rooms = rooms;
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct WriteQuery {
+    /// 予定の重複を確認する範囲（省略時は同じ会議室を予約した予定だけ）
+    conflict_scope: Option<ConflictScope>,
+}
+impl WriteQuery {
+    fn scope(&self) -> ConflictScope {
+        self.conflict_scope.unwrap_or(ConflictScope::Room)
+    }
+}
+
+#[post("/schedules")]
+async fn create_schedule(
+    store: web::Data<SharedStore>,
+    query: web::Query<WriteQuery>,
+    body: web::Json<NewSchedule>,
+) -> HttpResponse {
+    let scope = query.scope();
+    let body = body.into_inner();
+    let schedule = Schedule {
+        id: 0,
+        calendar: body.calendar,
+        subject: body.subject,
+        start: body.start,
+        end: body.end,
+        location: body.location,
+        description: body.description,
+        tags: body.tags,
+        attendees: body.attendees,
+        remind_before: body.remind_before,
+        uid: None,
+        resource: None,
+        rooms: body.rooms,
+    };
+    if schedule.end <= schedule.start {
+        return invalid_range();
+    }
+
+    let mut store = store.lock().unwrap();
+    if let Err(error) = check_rooms(store.as_ref(), &schedule) {
+        return room_error(error);
+    }
+    let result = store.add_schedule(schedule, scope);
+    match result {
+        Ok(WriteOutcome::Saved(schedule)) => HttpResponse::Created()
+            .append_header(("Location", format!("/schedules/{}", schedule.id)))
+            .json(schedule),
+        Ok(outcome) => outcome_response(outcome),
+        Err(error) => internal_error(error),
+    }
+}
+
+#[patch("/schedules/{id}")]
+async fn update_schedule(
+    store: web::Data<SharedStore>,
+    id: web::Path<u64>,
+    query: web::Query<WriteQuery>,
+    body: web::Json<SchedulePatch>,
+) -> HttpResponse {
+    let scope = query.scope();
+    let id = id.into_inner();
+
+    // NOTE: 読み込みから書き込みまでロックを保持し、他のリクエストの更新と混ざらないようにする
+    let mut store = store.lock().unwrap();
+    let mut schedule = match store.read_calendar() {
+        Ok(calendar) => match calendar.schedules.into_iter().find(|s| s.id == id) {
+            Some(schedule) => schedule,
+            None => return outcome_response(WriteOutcome::NotFound),
+        },
+        Err(error) => return internal_error(error),
+    };
+    body.into_inner().apply(&mut schedule);
+    if schedule.end <= schedule.start {
+        return invalid_range();
+    }
+    if let Err(error) = check_rooms(store.as_ref(), &schedule) {
+        return room_error(error);
+    }
+
+    match store.update_schedule(schedule, scope) {
+        Ok(outcome) => outcome_response(outcome),
+        Err(error) => internal_error(error),
+    }
+}
+
+#[delete("/schedules/{id}")]
+async fn delete_schedule(store: web::Data<SharedStore>, id: web::Path<u64>) -> HttpResponse {
+    let result = store.lock().unwrap().delete_schedule(id.into_inner());
+    match result {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => outcome_response(WriteOutcome::NotFound),
+        Err(error) => internal_error(error),
+    }
+}
+
+fn outcome_response(outcome: WriteOutcome) -> HttpResponse {
+    match outcome {
+        WriteOutcome::Saved(schedule) => HttpResponse::Ok().json(schedule),
+        WriteOutcome::Conflict(conflicts) => HttpResponse::Conflict().json(json!({
+            "error": "予定が重複しています",
+            "conflicts": conflicts,
+        })),
+        WriteOutcome::NotFound => error_response(HttpResponse::NotFound(), "予定が見つかりません"),
+    }
+}
+
+/// 予約する会議室が登録済みで、参加者が定員に収まるかを確認する
+fn check_rooms(store: &dyn CalendarStore, schedule: &Schedule) -> Result<(), MyError> {
+    if schedule.rooms.is_empty() {
+        return Ok(());
+    }
+    room::check_reservation(&store.read_calendar()?, schedule)
+}
+
+fn room_error(error: MyError) -> HttpResponse {
+    match error {
+        MyError::UnknownRoom(name) => error_response(
+            HttpResponse::BadRequest(),
+            format!("会議室「{}」は登録されていません", name),
+        ),
+        MyError::OverCapacity {
+            capacity,
+            attendees,
+        } => error_response(
+            HttpResponse::BadRequest(),
+            format!(
+                "参加者（{}人）が会議室の定員（{}人）を超えています",
+                attendees, capacity
+            ),
+        ),
+        error => internal_error(error),
+    }
+}
+
+fn invalid_range() -> HttpResponse {
+    error_response(
+        HttpResponse::BadRequest(),
+        "終了時刻は開始時刻より後にしてください",
+    )
+}
+
+fn internal_error(error: MyError) -> HttpResponse {
+    error_response(
+        HttpResponse::InternalServerError(),
+        format!("カレンダーの読み書きに失敗しました：{}", error),
+    )
+}
+
+fn error_response(
+    mut builder: actix_web::HttpResponseBuilder,
+    message: impl Into<String>,
+) -> HttpResponse {
+    builder.json(json!({ "error": message.into() }))
+}
@@ -0,0 +1,89 @@
+mod history;
+mod json;
+mod sqlite;
+
+pub use history::{Action, History, HistoryState, RecordingStore};
+pub use json::JsonStore;
+pub use sqlite::SqliteStore;
+
+use crate::{Calendar, ConflictScope, MyError, Schedule};
+use clap::ValueEnum;
+use std::path::{Path, PathBuf};
+
+/// 予定の追加・更新の結果
+// NOTE: 戻り値として受け取ってすぐに分解するだけなので、Box で包まずに持つ
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum WriteOutcome {
+    /// 保存した予定
+    Saved(Schedule),
+    /// 重複する予定があるため保存しなかった
+    Conflict(Vec<Schedule>),
+    /// 更新対象の予定が存在しない
+    NotFound,
+}
+
+/// カレンダーの保存先
+// NOTE: サーバーのスレッド間で共有できるよう Send を要求する
+pub trait CalendarStore: Send {
+    /// すべての予定を読み込む
+    fn read_calendar(&self) -> Result<Calendar, MyError>;
+
+    /// カレンダー全体を書き込む（保存済みの予定はすべて置き換える）
+    fn save_calendar(&mut self, calendar: &Calendar) -> Result<(), MyError>;
+
+    /// 予定にIDを割り当てて追加する
+    fn add_schedule(
+        &mut self,
+        schedule: Schedule,
+        scope: ConflictScope,
+    ) -> Result<WriteOutcome, MyError>;
+
+    /// 同じIDの予定を置き換える
+    fn update_schedule(
+        &mut self,
+        schedule: Schedule,
+        scope: ConflictScope,
+    ) -> Result<WriteOutcome, MyError>;
+
+    /// 予定を削除する（該当する予定がない場合は false）
+    fn delete_schedule(&mut self, id: u64) -> Result<bool, MyError>;
+}
+
+/// 保存先の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StoreKind {
+    /// JSON ファイル
+    Json,
+    /// SQLite データベース
+    Sqlite,
+}
+impl StoreKind {
+    /// 拡張子から保存先の種類を判定する（既定は JSON）
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("db" | "sqlite" | "sqlite3") => StoreKind::Sqlite,
+            _ => StoreKind::Json,
+        }
+    }
+
+    /// 移行先を省略した場合のファイル名
+    pub fn default_path(&self) -> PathBuf {
+        match self {
+            StoreKind::Json => PathBuf::from("schedule.json"),
+            StoreKind::Sqlite => PathBuf::from("schedule.db"),
+        }
+    }
+}
+
+/// 指定したパスの保存先を開く（追加・更新・削除は変更履歴に記録する）
+pub fn open(path: &Path) -> Result<Box<dyn CalendarStore>, MyError> {
+    let store: Box<dyn CalendarStore> = match StoreKind::from_path(path) {
+        StoreKind::Json => Box::new(JsonStore::new(path)),
+        StoreKind::Sqlite => Box::new(SqliteStore::open(path)?),
+    };
+    Ok(Box::new(RecordingStore::new(
+        store,
+        History::for_store(path),
+    )))
+}
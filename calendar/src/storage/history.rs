@@ -0,0 +1,573 @@
+use super::{CalendarStore, WriteOutcome};
+use crate::{Calendar, ConflictScope, MyError, Schedule};
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// 取り消せる操作の数
+pub const HISTORY_LIMIT: usize = 100;
+
+/// 変更履歴の1行
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change {
+    /// 記録した順の通し番号
+    pub seq: u64,
+    /// 記録した日時
+    pub at: NaiveDateTime,
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+/// 記録する操作
+// NOTE: 操作を取り消せるよう、変更前と変更後の予定をそのまま持つ
+// NOTE: 履歴は上限件数までしか保持しないため、Box で包まずに持つ
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum Action {
+    Add {
+        after: Schedule,
+    },
+    Edit {
+        before: Schedule,
+        after: Schedule,
+    },
+    Delete {
+        before: Schedule,
+    },
+    /// 通し番号 target の操作を取り消した
+    Undo {
+        target: u64,
+    },
+    /// 通し番号 target の操作をやり直した
+    Redo {
+        target: u64,
+    },
+}
+impl Action {
+    /// 操作の対象となった予定
+    pub fn schedule(&self) -> Option<&Schedule> {
+        match self {
+            Action::Add { after } | Action::Edit { after, .. } => Some(after),
+            Action::Delete { before } => Some(before),
+            Action::Undo { .. } | Action::Redo { .. } => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Add { .. } => "追加",
+            Action::Edit { .. } => "更新",
+            Action::Delete { .. } => "削除",
+            Action::Undo { .. } => "取り消し",
+            Action::Redo { .. } => "やり直し",
+        }
+    }
+
+    /// カレンダーに操作を適用する（予定が記録と食い違う場合は false）
+    fn apply(&self, calendar: &mut Calendar) -> bool {
+        match self {
+            Action::Add { after } => insert(calendar, after),
+            Action::Edit { before, after } => replace(calendar, before, after),
+            Action::Delete { before } => remove(calendar, before),
+            Action::Undo { .. } | Action::Redo { .. } => false,
+        }
+    }
+
+    /// カレンダーに適用した操作を元に戻す（予定が記録と食い違う場合は false）
+    fn revert(&self, calendar: &mut Calendar) -> bool {
+        match self {
+            Action::Add { after } => remove(calendar, after),
+            Action::Edit { before, after } => replace(calendar, after, before),
+            Action::Delete { before } => insert(calendar, before),
+            Action::Undo { .. } | Action::Redo { .. } => false,
+        }
+    }
+}
+
+fn insert(calendar: &mut Calendar, schedule: &Schedule) -> bool {
+    if calendar.schedules.iter().any(|s| s.id == schedule.id) {
+        return false;
+    }
+    calendar.schedules.push(schedule.clone());
+    calendar.schedules.sort_by_key(|s| s.id);
+    true
+}
+
+fn remove(calendar: &mut Calendar, schedule: &Schedule) -> bool {
+    let length = calendar.schedules.len();
+    calendar.schedules.retain(|s| s != schedule);
+    calendar.schedules.len() != length
+}
+
+fn replace(calendar: &mut Calendar, current: &Schedule, next: &Schedule) -> bool {
+    match calendar.schedules.iter_mut().find(|s| *s == current) {
+        Some(schedule) => {
+            *schedule = next.clone();
+            true
+        }
+        None => false,
+    }
+}
+
+/// 履歴を先頭から再生した結果
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct HistoryState {
+    /// 適用中の操作（古い順）
+    pub applied: Vec<Change>,
+    /// 取り消した操作（次にやり直すものが末尾）
+    pub undone: Vec<Change>,
+}
+
+/// 予定の変更履歴（カレンダーと同じ場所に JSON Lines 形式で追記する）
+pub struct History {
+    path: PathBuf,
+    limit: usize,
+}
+impl History {
+    /// カレンダーの保存先に対応する履歴を開く（schedule.json なら schedule.json.history）
+    pub fn for_store(store_path: &Path) -> Self {
+        let mut path = store_path.as_os_str().to_owned();
+        path.push(".history");
+        Self {
+            path: PathBuf::from(path),
+            limit: HISTORY_LIMIT,
+        }
+    }
+
+    /// 記録されている変更をすべて読み込む
+    pub fn changes(&self) -> Result<Vec<Change>, MyError> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(error.into()),
+        };
+        let mut changes = vec![];
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                changes.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(changes)
+    }
+
+    /// 履歴を再生し、取り消し・やり直しできる操作を求める
+    pub fn state(&self) -> Result<HistoryState, MyError> {
+        Ok(replay(self.changes()?))
+    }
+
+    /// 操作を記録する
+    pub fn record(&self, action: Action) -> Result<Change, MyError> {
+        let changes = self.changes()?;
+        let change = Change {
+            seq: changes.last().map_or(0, |change| change.seq + 1),
+            at: Local::now().naive_local(),
+            action,
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&change)?)?;
+
+        // NOTE: 履歴が上限の2倍を超えたら、取り消せる範囲だけを残して書き直す
+        if changes.len() + 1 > self.limit * 2 {
+            self.compact()?;
+        }
+        Ok(change)
+    }
+
+    /// 直前の操作を取り消す（取り消せる操作がない場合は None）
+    pub fn undo(&self, store: &mut dyn CalendarStore) -> Result<Option<Change>, MyError> {
+        let Some(change) = self.state()?.applied.pop() else {
+            return Ok(None);
+        };
+        let mut calendar = store.read_calendar()?;
+        if !change.action.revert(&mut calendar) {
+            return Err(MyError::HistoryMismatch(change.seq));
+        }
+        store.save_calendar(&calendar)?;
+        self.record(Action::Undo { target: change.seq })?;
+        Ok(Some(change))
+    }
+
+    /// 直前に取り消した操作をやり直す（やり直せる操作がない場合は None）
+    pub fn redo(&self, store: &mut dyn CalendarStore) -> Result<Option<Change>, MyError> {
+        let Some(change) = self.state()?.undone.pop() else {
+            return Ok(None);
+        };
+        let mut calendar = store.read_calendar()?;
+        if !change.action.apply(&mut calendar) {
+            return Err(MyError::HistoryMismatch(change.seq));
+        }
+        store.save_calendar(&calendar)?;
+        self.record(Action::Redo { target: change.seq })?;
+        Ok(Some(change))
+    }
+
+    /// 取り消し・やり直しの結果だけを残すよう履歴を書き直す
+    pub fn compact(&self) -> Result<(), MyError> {
+        let state = self.state()?;
+        // NOTE: 上限を超える分は、適用中の古い操作から捨てる
+        //       取り消した操作が上限を超える場合は、やり直す順番が最も遠いもの（先に取り消したもの）を捨てる
+        let undone_skip = state.undone.len().saturating_sub(self.limit);
+        let undone = &state.undone[undone_skip..];
+        let applied_skip = (state.applied.len() + undone.len()).saturating_sub(self.limit);
+
+        // NOTE: 取り消した操作は、いったん適用した後に新しい順に取り消した形で書き出す
+        let mut changes: Vec<Change> = state
+            .applied
+            .into_iter()
+            .skip(applied_skip)
+            .chain(undone.iter().rev().cloned())
+            .collect();
+        let next_seq = changes
+            .iter()
+            .map(|change| change.seq + 1)
+            .max()
+            .unwrap_or(0);
+        let undos: Vec<Change> = (next_seq..)
+            .zip(undone)
+            .map(|(seq, undone)| Change {
+                seq,
+                at: undone.at,
+                action: Action::Undo { target: undone.seq },
+            })
+            .collect();
+        changes.extend(undos);
+
+        // NOTE: 書き込み途中で失敗しても元の履歴が壊れないよう、一時ファイルに書いてから置き換える
+        let mut temporary = self.path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let temporary = PathBuf::from(temporary);
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        for change in &changes {
+            writeln!(writer, "{}", serde_json::to_string(change)?)?;
+        }
+        writer.flush()?;
+        drop(writer);
+        fs::rename(&temporary, &self.path)?;
+        Ok(())
+    }
+}
+
+fn replay(changes: Vec<Change>) -> HistoryState {
+    let mut state = HistoryState::default();
+    for change in changes {
+        match change.action {
+            Action::Undo { target } => {
+                if let Some(index) = state.applied.iter().rposition(|c| c.seq == target) {
+                    let undone = state.applied.remove(index);
+                    state.undone.push(undone);
+                }
+            }
+            Action::Redo { target } => {
+                if let Some(index) = state.undone.iter().rposition(|c| c.seq == target) {
+                    let redone = state.undone.remove(index);
+                    state.applied.push(redone);
+                }
+            }
+            _ => {
+                // NOTE: 新しい操作を記録したら、取り消した操作はやり直せなくなる
+                state.undone.clear();
+                state.applied.push(change);
+            }
+        }
+    }
+    state
+}
+
+/// 追加・更新・削除を履歴に記録する保存先
+pub struct RecordingStore {
+    inner: Box<dyn CalendarStore>,
+    history: History,
+}
+impl RecordingStore {
+    pub fn new(inner: Box<dyn CalendarStore>, history: History) -> Self {
+        Self { inner, history }
+    }
+
+    fn find(&self, id: u64) -> Result<Option<Schedule>, MyError> {
+        let calendar = self.inner.read_calendar()?;
+        Ok(calendar.schedules.into_iter().find(|s| s.id == id))
+    }
+}
+impl CalendarStore for RecordingStore {
+    fn read_calendar(&self) -> Result<Calendar, MyError> {
+        self.inner.read_calendar()
+    }
+
+    // NOTE: カレンダー全体の書き込み（移行や取り消し）は個々の操作ではないため記録しない
+    fn save_calendar(&mut self, calendar: &Calendar) -> Result<(), MyError> {
+        self.inner.save_calendar(calendar)
+    }
+
+    fn add_schedule(
+        &mut self,
+        schedule: Schedule,
+        scope: ConflictScope,
+    ) -> Result<WriteOutcome, MyError> {
+        let outcome = self.inner.add_schedule(schedule, scope)?;
+        if let WriteOutcome::Saved(after) = &outcome {
+            self.history.record(Action::Add {
+                after: after.clone(),
+            })?;
+        }
+        Ok(outcome)
+    }
+
+    fn update_schedule(
+        &mut self,
+        schedule: Schedule,
+        scope: ConflictScope,
+    ) -> Result<WriteOutcome, MyError> {
+        let before = self.find(schedule.id)?;
+        let outcome = self.inner.update_schedule(schedule, scope)?;
+        if let (Some(before), WriteOutcome::Saved(after)) = (before, &outcome) {
+            self.history.record(Action::Edit {
+                before,
+                after: after.clone(),
+            })?;
+        }
+        Ok(outcome)
+    }
+
+    fn delete_schedule(&mut self, id: u64) -> Result<bool, MyError> {
+        let before = self.find(id)?;
+        let deleted = self.inner.delete_schedule(id)?;
+        if let (Some(before), true) = (before, deleted) {
+            self.history.record(Action::Delete { before })?;
+        }
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::JsonStore;
+    use crate::tests::naive_date_time;
+    use crate::CALENDAR_VERSION;
+
+    fn recording_store(dir: &Path, limit: usize) -> (RecordingStore, History) {
+        let path = dir.join("schedule.json");
+        let mut store = JsonStore::new(&path);
+        store
+            .save_calendar(&Calendar {
+                version: CALENDAR_VERSION,
+                schedules: vec![],
+                rooms: vec![],
+            })
+            .unwrap();
+        let history = || History {
+            path: dir.join("schedule.json.history"),
+            limit,
+        };
+        (RecordingStore::new(Box::new(store), history()), history())
+    }
+
+    fn subjects(store: &dyn CalendarStore) -> Vec<String> {
+        let calendar = store.read_calendar().unwrap();
+        calendar.schedules.into_iter().map(|s| s.subject).collect()
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut store, history) = recording_store(dir.path(), HISTORY_LIMIT);
+        store
+            .add_schedule(
+                Schedule::test(
+                    0,
+                    "Rust入門",
+                    naive_date_time(2024, 1, 1, 19, 0, 0),
+                    naive_date_time(2024, 1, 1, 20, 0, 0),
+                ),
+                ConflictScope::All,
+            )
+            .unwrap();
+        store
+            .add_schedule(
+                Schedule::test(
+                    0,
+                    "Rust応用",
+                    naive_date_time(2024, 1, 2, 19, 0, 0),
+                    naive_date_time(2024, 1, 2, 20, 0, 0),
+                ),
+                ConflictScope::All,
+            )
+            .unwrap();
+        store
+            .update_schedule(
+                Schedule::test(
+                    1,
+                    "Rust実践",
+                    naive_date_time(2024, 1, 2, 19, 0, 0),
+                    naive_date_time(2024, 1, 2, 20, 0, 0),
+                ),
+                ConflictScope::All,
+            )
+            .unwrap();
+        store.delete_schedule(0).unwrap();
+        assert_eq!(vec!["Rust実践"], subjects(&store));
+
+        // 削除の取り消し
+        let undone = history.undo(&mut store).unwrap().unwrap();
+        assert_eq!("削除", undone.action.name());
+        assert_eq!(vec!["Rust入門", "Rust実践"], subjects(&store));
+        // 更新の取り消し
+        history.undo(&mut store).unwrap();
+        assert_eq!(vec!["Rust入門", "Rust応用"], subjects(&store));
+
+        // やり直し
+        history.redo(&mut store).unwrap();
+        assert_eq!(vec!["Rust入門", "Rust実践"], subjects(&store));
+
+        // 新しい操作をするとやり直せなくなる
+        store
+            .add_schedule(
+                Schedule::test(
+                    0,
+                    "Rust発展",
+                    naive_date_time(2024, 1, 3, 19, 0, 0),
+                    naive_date_time(2024, 1, 3, 20, 0, 0),
+                ),
+                ConflictScope::All,
+            )
+            .unwrap();
+        assert_eq!(None, history.redo(&mut store).unwrap());
+        let state = history.state().unwrap();
+        assert_eq!(4, state.applied.len());
+        assert!(state.undone.is_empty());
+
+        // すべて取り消す
+        while history.undo(&mut store).unwrap().is_some() {}
+        assert!(subjects(&store).is_empty());
+    }
+
+    #[test]
+    fn test_undo_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut store, history) = recording_store(dir.path(), HISTORY_LIMIT);
+        store
+            .add_schedule(
+                Schedule::test(
+                    0,
+                    "Rust入門",
+                    naive_date_time(2024, 1, 1, 19, 0, 0),
+                    naive_date_time(2024, 1, 1, 20, 0, 0),
+                ),
+                ConflictScope::All,
+            )
+            .unwrap();
+
+        // NOTE: 履歴を通さずに書き換えた予定は取り消さない
+        let mut calendar = store.read_calendar().unwrap();
+        calendar.schedules[0].subject = "手で書き換えた予定".to_string();
+        store.save_calendar(&calendar).unwrap();
+
+        assert!(matches!(
+            history.undo(&mut store),
+            Err(MyError::HistoryMismatch(0))
+        ));
+        assert_eq!(vec!["手で書き換えた予定"], subjects(&store));
+    }
+
+    #[test]
+    fn test_compact() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut store, history) = recording_store(dir.path(), 3);
+        let titles = |changes: &[Change]| -> Vec<String> {
+            changes
+                .iter()
+                .map(|change| change.action.schedule().unwrap().subject.clone())
+                .collect()
+        };
+        for day in 1..=4 {
+            store
+                .add_schedule(
+                    Schedule::test(
+                        0,
+                        &format!("第{}回", day),
+                        naive_date_time(2024, 1, day, 19, 0, 0),
+                        naive_date_time(2024, 1, day, 20, 0, 0),
+                    ),
+                    ConflictScope::All,
+                )
+                .unwrap();
+        }
+        history.undo(&mut store).unwrap();
+        history.undo(&mut store).unwrap();
+
+        // 取り消した操作も、やり直せる状態のまま残る
+        history.compact().unwrap();
+        let state = history.state().unwrap();
+        assert_eq!(vec!["第2回"], titles(&state.applied));
+        assert_eq!(vec!["第4回", "第3回"], titles(&state.undone));
+        history.redo(&mut store).unwrap();
+        assert_eq!(vec!["第1回", "第2回", "第3回"], subjects(&store));
+
+        // 記録が上限の2倍を超えると、取り消せる範囲だけを残して書き直す
+        for day in 5..=9 {
+            store
+                .add_schedule(
+                    Schedule::test(
+                        0,
+                        &format!("第{}回", day),
+                        naive_date_time(2024, 1, day, 19, 0, 0),
+                        naive_date_time(2024, 1, day, 20, 0, 0),
+                    ),
+                    ConflictScope::All,
+                )
+                .unwrap();
+        }
+        let changes = history.changes().unwrap();
+        assert_eq!(vec!["第7回", "第8回", "第9回"], titles(&changes));
+        assert_eq!(
+            changes,
+            history.state().unwrap().applied,
+            "取り消し・やり直しの記録は残らない"
+        );
+    }
+
+    #[test]
+    fn test_compact_keeps_next_redo() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut store, history) = recording_store(dir.path(), HISTORY_LIMIT);
+        for day in 1..=4 {
+            store
+                .add_schedule(
+                    Schedule::test(
+                        0,
+                        &format!("第{}回", day),
+                        naive_date_time(2024, 1, day, 19, 0, 0),
+                        naive_date_time(2024, 1, day, 20, 0, 0),
+                    ),
+                    ConflictScope::All,
+                )
+                .unwrap();
+        }
+        history.undo(&mut store).unwrap();
+        history.undo(&mut store).unwrap();
+
+        // NOTE: 取り消した操作が上限を超えても、次にやり直す操作は残す
+        let history = History {
+            limit: 1,
+            ..history
+        };
+        history.compact().unwrap();
+        let state = history.state().unwrap();
+        assert!(state.applied.is_empty());
+        assert_eq!(1, state.undone.len());
+        assert_eq!("第3回", state.undone[0].action.schedule().unwrap().subject);
+
+        history.redo(&mut store).unwrap();
+        assert_eq!(vec!["第1回", "第2回", "第3回"], subjects(&store));
+        assert_eq!(None, history.redo(&mut store).unwrap());
+    }
+}
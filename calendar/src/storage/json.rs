@@ -0,0 +1,119 @@
+use super::{CalendarStore, WriteOutcome};
+use crate::{migration, Calendar, ConflictScope, MyError, Schedule};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+/// カレンダー全体を1つの JSON ファイルに保存する
+pub struct JsonStore {
+    path: PathBuf,
+}
+impl JsonStore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+impl CalendarStore for JsonStore {
+    fn read_calendar(&self) -> Result<Calendar, MyError> {
+        // NOTE: Result 型の後ろに ? を付けることで、Err が返る場合はそのまま返すことができる
+        let file = File::open(&self.path)?;
+        let reader = BufReader::new(file);
+        let value = serde_json::from_reader(reader)?;
+        migration::migrate(value)
+    }
+
+    fn save_calendar(&mut self, calendar: &Calendar) -> Result<(), MyError> {
+        let file = File::create(&self.path)?;
+        let writer = BufWriter::new(file);
+        serde_json::to_writer(writer, calendar)?;
+        Ok(())
+    }
+
+    fn add_schedule(
+        &mut self,
+        schedule: Schedule,
+        scope: ConflictScope,
+    ) -> Result<WriteOutcome, MyError> {
+        let mut calendar = self.read_calendar()?;
+        match crate::add_schedule(&mut calendar, schedule, scope) {
+            Ok(added) => {
+                self.save_calendar(&calendar)?;
+                Ok(WriteOutcome::Saved(added))
+            }
+            Err(conflicts) => Ok(WriteOutcome::Conflict(conflicts)),
+        }
+    }
+
+    fn update_schedule(
+        &mut self,
+        schedule: Schedule,
+        scope: ConflictScope,
+    ) -> Result<WriteOutcome, MyError> {
+        let mut calendar = self.read_calendar()?;
+        let Some(index) = calendar
+            .schedules
+            .iter()
+            .position(|existing| existing.id == schedule.id)
+        else {
+            return Ok(WriteOutcome::NotFound);
+        };
+        let conflicts = crate::find_conflicts(&calendar, &schedule, scope);
+        if !conflicts.is_empty() {
+            return Ok(WriteOutcome::Conflict(
+                conflicts.into_iter().cloned().collect(),
+            ));
+        }
+        calendar.schedules[index] = schedule.clone();
+        self.save_calendar(&calendar)?;
+        Ok(WriteOutcome::Saved(schedule))
+    }
+
+    fn delete_schedule(&mut self, id: u64) -> Result<bool, MyError> {
+        let mut calendar = self.read_calendar()?;
+        if !crate::delete_schedule(&mut calendar, id) {
+            return Ok(false);
+        }
+        self.save_calendar(&calendar)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::naive_date_time;
+    use crate::CALENDAR_VERSION;
+
+    #[test]
+    fn test_json_store_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = JsonStore::new(dir.path().join("schedule.json"));
+        store
+            .save_calendar(&Calendar {
+                version: CALENDAR_VERSION,
+                schedules: vec![],
+                rooms: vec![],
+            })
+            .unwrap();
+
+        let outcome = store
+            .add_schedule(
+                Schedule {
+                    subject: "Rust入門".to_string(),
+                    start: naive_date_time(2024, 1, 1, 19, 0, 0),
+                    end: naive_date_time(2024, 1, 1, 20, 0, 0),
+                    ..Default::default()
+                },
+                ConflictScope::All,
+            )
+            .unwrap();
+        let calendar = store.read_calendar().unwrap();
+
+        assert_eq!(WriteOutcome::Saved(calendar.schedules[0].clone()), outcome);
+        assert_eq!(1, calendar.schedules.len());
+    }
+}
@@ -0,0 +1,492 @@
+use super::{CalendarStore, WriteOutcome};
+use crate::{Calendar, ConflictScope, MyError, Room, Schedule, CALENDAR_VERSION};
+use rusqlite::{params, Connection, Row};
+use std::path::Path;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS schedules (
+    id            INTEGER PRIMARY KEY,
+    calendar      TEXT    NOT NULL,
+    subject       TEXT    NOT NULL,
+    start_at      TEXT    NOT NULL,
+    end_at        TEXT    NOT NULL,
+    location      TEXT,
+    description   TEXT,
+    tags          TEXT    NOT NULL,
+    attendees     TEXT    NOT NULL,
+    remind_before INTEGER,
+    uid           TEXT,
+    resource      TEXT,
+    rooms         TEXT    NOT NULL DEFAULT '[]'
+);
+CREATE INDEX IF NOT EXISTS schedules_range ON schedules (start_at, end_at);
+CREATE INDEX IF NOT EXISTS schedules_calendar_range ON schedules (calendar, start_at, end_at);
+CREATE TABLE IF NOT EXISTS rooms (
+    name     TEXT    PRIMARY KEY,
+    capacity INTEGER NOT NULL,
+    location TEXT
+);
+";
+
+const COLUMNS: &str = "id, calendar, subject, start_at, end_at, location, description, tags, \
+                       attendees, remind_before, uid, resource, rooms";
+
+/// データベースのスキーマのバージョン（PRAGMA user_version に保存する）
+const SCHEMA_VERSION: u32 = 3;
+
+/// 後から追加した列（追加したスキーマのバージョン、列名、型）
+const ADDED_COLUMNS: [(u32, &str, &str); 4] = [
+    (1, "remind_before", "INTEGER"),
+    (2, "uid", "TEXT"),
+    (2, "resource", "TEXT"),
+    (3, "rooms", "TEXT NOT NULL DEFAULT '[]'"),
+];
+
+/// 予定を SQLite データベースに保存する
+pub struct SqliteStore {
+    connection: Connection,
+}
+impl SqliteStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, MyError> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self, MyError> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, MyError> {
+        connection.execute_batch(SCHEMA)?;
+        migrate_schema(&connection)?;
+        Ok(Self { connection })
+    }
+}
+impl CalendarStore for SqliteStore {
+    fn read_calendar(&self) -> Result<Calendar, MyError> {
+        let mut statement = self
+            .connection
+            .prepare(&format!("SELECT {COLUMNS} FROM schedules ORDER BY id"))?;
+        let schedules = statement
+            .query_map([], |row| Ok(read_schedule(row)))?
+            .map(|result| result?)
+            .collect::<Result<Vec<_>, MyError>>()?;
+        let mut statement = self
+            .connection
+            .prepare("SELECT name, capacity, location FROM rooms ORDER BY rowid")?;
+        let rooms = statement
+            .query_map([], |row| {
+                Ok(Room {
+                    name: row.get(0)?,
+                    capacity: row.get(1)?,
+                    location: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Calendar {
+            version: CALENDAR_VERSION,
+            schedules,
+            rooms,
+        })
+    }
+
+    fn save_calendar(&mut self, calendar: &Calendar) -> Result<(), MyError> {
+        let transaction = self.connection.transaction()?;
+        transaction.execute("DELETE FROM schedules", [])?;
+        for schedule in &calendar.schedules {
+            insert_schedule(&transaction, schedule)?;
+        }
+        transaction.execute("DELETE FROM rooms", [])?;
+        for room in &calendar.rooms {
+            transaction.execute(
+                "INSERT INTO rooms (name, capacity, location) VALUES (?1, ?2, ?3)",
+                params![room.name, room.capacity, room.location],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn add_schedule(
+        &mut self,
+        mut schedule: Schedule,
+        scope: ConflictScope,
+    ) -> Result<WriteOutcome, MyError> {
+        let transaction = self.connection.transaction()?;
+        schedule.id = transaction.query_row(
+            "SELECT COALESCE(MAX(id) + 1, 0) FROM schedules",
+            [],
+            |row| row.get(0),
+        )?;
+
+        let conflicts = find_conflicts(&transaction, &schedule, scope)?;
+        if !conflicts.is_empty() {
+            return Ok(WriteOutcome::Conflict(conflicts));
+        }
+
+        insert_schedule(&transaction, &schedule)?;
+        transaction.commit()?;
+        Ok(WriteOutcome::Saved(schedule))
+    }
+
+    fn update_schedule(
+        &mut self,
+        schedule: Schedule,
+        scope: ConflictScope,
+    ) -> Result<WriteOutcome, MyError> {
+        let transaction = self.connection.transaction()?;
+        let exists: bool = transaction.query_row(
+            "SELECT COUNT(*) > 0 FROM schedules WHERE id = ?1",
+            [schedule.id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Ok(WriteOutcome::NotFound);
+        }
+
+        let conflicts = find_conflicts(&transaction, &schedule, scope)?;
+        if !conflicts.is_empty() {
+            return Ok(WriteOutcome::Conflict(conflicts));
+        }
+
+        transaction.execute("DELETE FROM schedules WHERE id = ?1", [schedule.id])?;
+        insert_schedule(&transaction, &schedule)?;
+        transaction.commit()?;
+        Ok(WriteOutcome::Saved(schedule))
+    }
+
+    fn delete_schedule(&mut self, id: u64) -> Result<bool, MyError> {
+        let deleted = self
+            .connection
+            .execute("DELETE FROM schedules WHERE id = ?1", [id])?;
+        Ok(deleted > 0)
+    }
+}
+
+/// 指定した予定と重複する予定を返す（開始・終了時刻のインデックスで範囲検索する）
+fn find_conflicts(
+    connection: &Connection,
+    target: &Schedule,
+    scope: ConflictScope,
+) -> Result<Vec<Schedule>, MyError> {
+    // NOTE: 判定条件は Schedule::intersects と同じ
+    // NOTE: 会議室は JSON 配列の文字列として保存しているため、範囲検索した後に絞り込む
+    let mut statement = connection.prepare(&format!(
+        "SELECT {COLUMNS} FROM schedules
+         WHERE start_at < ?2 AND ?1 < end_at AND id != ?3
+         ORDER BY start_at, id"
+    ))?;
+    let conflicts = statement
+        .query_map(params![target.start, target.end, target.id], |row| {
+            Ok(read_schedule(row))
+        })?
+        .map(|result| result?)
+        .filter(|result| match result {
+            Ok(schedule) => scope.applies_to(target, schedule),
+            Err(_) => true,
+        })
+        .collect::<Result<Vec<_>, MyError>>()?;
+    Ok(conflicts)
+}
+
+/// 古いスキーマのデータベースに不足している列を追加する
+fn migrate_schema(connection: &Connection) -> Result<(), MyError> {
+    let version: u32 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (added_in, column, column_type) in ADDED_COLUMNS {
+        if version >= added_in {
+            continue;
+        }
+        // NOTE: CREATE TABLE IF NOT EXISTS で作られたばかりの場合は既に列がある
+        let has_column: bool = connection.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('schedules') WHERE name = ?1",
+            [column],
+            |row| row.get(0),
+        )?;
+        if !has_column {
+            connection.execute(
+                &format!("ALTER TABLE schedules ADD COLUMN {column} {column_type}"),
+                [],
+            )?;
+        }
+    }
+    connection.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    Ok(())
+}
+
+fn insert_schedule(connection: &Connection, schedule: &Schedule) -> Result<(), MyError> {
+    // NOTE: タグと参加者、会議室は JSON 配列の文字列として1つの列に保存する
+    connection.execute(
+        &format!(
+            "INSERT INTO schedules ({COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)"
+        ),
+        params![
+            schedule.id,
+            schedule.calendar,
+            schedule.subject,
+            schedule.start,
+            schedule.end,
+            schedule.location,
+            schedule.description,
+            serde_json::to_string(&schedule.tags)?,
+            serde_json::to_string(&schedule.attendees)?,
+            schedule.remind_before,
+            schedule.uid,
+            schedule.resource,
+            serde_json::to_string(&schedule.rooms)?,
+        ],
+    )?;
+    Ok(())
+}
+
+fn read_schedule(row: &Row) -> Result<Schedule, MyError> {
+    let tags: String = row.get(7)?;
+    let attendees: String = row.get(8)?;
+    let rooms: String = row.get(12)?;
+    Ok(Schedule {
+        id: row.get(0)?,
+        calendar: row.get(1)?,
+        subject: row.get(2)?,
+        start: row.get(3)?,
+        end: row.get(4)?,
+        location: row.get(5)?,
+        description: row.get(6)?,
+        tags: serde_json::from_str(&tags)?,
+        attendees: serde_json::from_str(&attendees)?,
+        remind_before: row.get(9)?,
+        uid: row.get(10)?,
+        resource: row.get(11)?,
+        rooms: serde_json::from_str(&rooms)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::naive_date_time;
+
+    #[test]
+    fn test_sqlite_store_add_and_delete() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+
+        let first = store
+            .add_schedule(
+                Schedule {
+                    calendar: "rust-study".to_string(),
+                    ..Schedule::test(
+                        0,
+                        "勉強会",
+                        naive_date_time(2024, 1, 1, 19, 0, 0),
+                        naive_date_time(2024, 1, 1, 21, 0, 0),
+                    )
+                },
+                ConflictScope::All,
+            )
+            .unwrap();
+        let conflict = store
+            .add_schedule(
+                Schedule {
+                    calendar: "team".to_string(),
+                    ..Schedule::test(
+                        0,
+                        "勉強会",
+                        naive_date_time(2024, 1, 1, 20, 0, 0),
+                        naive_date_time(2024, 1, 1, 22, 0, 0),
+                    )
+                },
+                ConflictScope::All,
+            )
+            .unwrap();
+        let scoped = store
+            .add_schedule(
+                Schedule {
+                    calendar: "team".to_string(),
+                    ..Schedule::test(
+                        0,
+                        "勉強会",
+                        naive_date_time(2024, 1, 1, 20, 0, 0),
+                        naive_date_time(2024, 1, 1, 22, 0, 0),
+                    )
+                },
+                ConflictScope::Calendar,
+            )
+            .unwrap();
+        let deleted = store.delete_schedule(0).unwrap();
+
+        let WriteOutcome::Saved(first) = first else {
+            panic!("予定が追加されていません：{:?}", first);
+        };
+        let WriteOutcome::Saved(scoped) = scoped else {
+            panic!("予定が追加されていません：{:?}", scoped);
+        };
+        assert_eq!(0, first.id);
+        assert_eq!(WriteOutcome::Conflict(vec![first]), conflict);
+        assert_eq!(1, scoped.id);
+        assert!(deleted);
+        assert_eq!(vec![scoped], store.read_calendar().unwrap().schedules);
+    }
+
+    #[test]
+    fn test_sqlite_store_update() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        store
+            .add_schedule(
+                Schedule {
+                    calendar: "team".to_string(),
+                    ..Schedule::test(
+                        0,
+                        "勉強会",
+                        naive_date_time(2024, 1, 1, 9, 0, 0),
+                        naive_date_time(2024, 1, 1, 10, 0, 0),
+                    )
+                },
+                ConflictScope::All,
+            )
+            .unwrap();
+        store
+            .add_schedule(
+                Schedule {
+                    calendar: "team".to_string(),
+                    ..Schedule::test(
+                        0,
+                        "勉強会",
+                        naive_date_time(2024, 1, 1, 10, 0, 0),
+                        naive_date_time(2024, 1, 1, 11, 0, 0),
+                    )
+                },
+                ConflictScope::All,
+            )
+            .unwrap();
+
+        let moved = store
+            .update_schedule(
+                Schedule {
+                    calendar: "team".to_string(),
+                    ..Schedule::test(
+                        0,
+                        "勉強会",
+                        naive_date_time(2024, 1, 1, 8, 0, 0),
+                        naive_date_time(2024, 1, 1, 10, 0, 0),
+                    )
+                },
+                ConflictScope::All,
+            )
+            .unwrap();
+        let conflict = store
+            .update_schedule(
+                Schedule {
+                    calendar: "team".to_string(),
+                    ..Schedule::test(
+                        0,
+                        "勉強会",
+                        naive_date_time(2024, 1, 1, 9, 0, 0),
+                        naive_date_time(2024, 1, 1, 11, 0, 0),
+                    )
+                },
+                ConflictScope::All,
+            )
+            .unwrap();
+        let missing = store
+            .update_schedule(
+                Schedule {
+                    calendar: "team".to_string(),
+                    ..Schedule::test(
+                        9,
+                        "勉強会",
+                        naive_date_time(2024, 1, 1, 12, 0, 0),
+                        naive_date_time(2024, 1, 1, 13, 0, 0),
+                    )
+                },
+                ConflictScope::All,
+            )
+            .unwrap();
+
+        assert!(matches!(moved, WriteOutcome::Saved(_)));
+        assert!(matches!(conflict, WriteOutcome::Conflict(ref conflicts) if conflicts[0].id == 1));
+        assert_eq!(WriteOutcome::NotFound, missing);
+    }
+
+    #[test]
+    fn test_sqlite_store_save_replaces_all() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        store
+            .add_schedule(
+                Schedule {
+                    calendar: "team".to_string(),
+                    ..Schedule::test(
+                        0,
+                        "勉強会",
+                        naive_date_time(2024, 1, 1, 9, 0, 0),
+                        naive_date_time(2024, 1, 1, 10, 0, 0),
+                    )
+                },
+                ConflictScope::All,
+            )
+            .unwrap();
+        let calendar = Calendar {
+            version: CALENDAR_VERSION,
+            schedules: vec![Schedule {
+                calendar: "rust-study".to_string(),
+                tags: vec!["rust".to_string()],
+                ..Schedule::test(
+                    5,
+                    "勉強会",
+                    naive_date_time(2024, 1, 1, 19, 0, 0),
+                    naive_date_time(2024, 1, 1, 20, 0, 0),
+                )
+            }],
+            rooms: vec![],
+        };
+
+        store.save_calendar(&calendar).unwrap();
+
+        assert_eq!(calendar, store.read_calendar().unwrap());
+    }
+
+    #[test]
+    fn test_sqlite_store_room_conflicts() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        let with_room = |calendar: &str, start_hour, end_hour, room: &str| Schedule {
+            rooms: vec![room.to_string()],
+            calendar: calendar.to_string(),
+            ..Schedule::test(
+                0,
+                "勉強会",
+                naive_date_time(2024, 1, 1, start_hour, 0, 0),
+                naive_date_time(2024, 1, 1, end_hour, 0, 0),
+            )
+        };
+        store
+            .save_calendar(&Calendar {
+                version: CALENDAR_VERSION,
+                schedules: vec![with_room("rust-study", 19, 21, "会議室A")],
+                rooms: vec![
+                    Room {
+                        name: "会議室A".to_string(),
+                        capacity: 10,
+                        location: Some("3階".to_string()),
+                    },
+                    Room {
+                        name: "会議室B".to_string(),
+                        capacity: 4,
+                        location: None,
+                    },
+                ],
+            })
+            .unwrap();
+
+        let other_room = store
+            .add_schedule(with_room("team", 20, 22, "会議室B"), ConflictScope::Room)
+            .unwrap();
+        let same_room = store
+            .add_schedule(with_room("team", 20, 22, "会議室A"), ConflictScope::Room)
+            .unwrap();
+        let calendar = store.read_calendar().unwrap();
+
+        assert!(
+            matches!(other_room, WriteOutcome::Saved(ref schedule) if schedule.rooms == ["会議室B"])
+        );
+        assert!(matches!(same_room, WriteOutcome::Conflict(ref conflicts) if conflicts[0].id == 0));
+        assert_eq!(2, calendar.rooms.len());
+        assert_eq!(Some("3階".to_string()), calendar.rooms[0].location);
+    }
+}
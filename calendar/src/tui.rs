@@ -0,0 +1,918 @@
+use crate::{
+    datetime::{self, Clock},
+    list::{self, GridView},
+    storage::{Action, CalendarStore, WriteOutcome},
+    Calendar, ConflictScope, MyError, Schedule, DEFAULT_CALENDAR,
+};
+use chrono::{Datelike, Days, Months, NaiveDate};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+use std::collections::HashSet;
+
+const WEEKDAY_LABELS: [&str; 7] = ["月", "火", "水", "木", "金", "土", "日"];
+const FIELD_LABELS: [&str; 4] = ["件名", "開始", "終了", "カレンダー"];
+
+/// カレンダーを操作する端末画面を表示する
+pub fn run(store: &mut dyn CalendarStore, clock: &dyn Clock) -> Result<(), MyError> {
+    let mut app = App::new(store.read_calendar()?, clock.now().date());
+
+    let mut terminal = ratatui::init();
+    // NOTE: エラーで抜ける場合も端末の状態を必ず元に戻す
+    let result = (|| -> Result<(), MyError> {
+        while !app.quit {
+            let today = clock.now().date();
+            terminal.draw(|frame| app.draw(frame, today))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if let Some(action) = app.handle_key(key, clock) {
+                // NOTE: 変更のたびに予定を1件ずつ保存する（undo 用の履歴は保存先が記録する）
+                if let Err(message) = apply(store, action) {
+                    app.message = Some(message);
+                }
+                // NOTE: 別のプロセス（CLI やサーバー）による変更も画面に反映する
+                match store.read_calendar() {
+                    Ok(calendar) => app.calendar = calendar,
+                    Err(error) => app.message = Some(format!("読み込みに失敗しました：{}", error)),
+                }
+            }
+        }
+        Ok(())
+    })();
+    ratatui::restore();
+    result
+}
+
+/// 画面で行った変更を保存先に反映する
+// NOTE: 起動時に読み込んだカレンダー全体を書き戻すと、その後に別のプロセスが加えた変更を消してしまう
+fn apply(store: &mut dyn CalendarStore, action: Action) -> Result<(), String> {
+    let failed = |error: MyError| format!("保存に失敗しました：{}", error);
+    let conflict = |conflicts: Vec<Schedule>| {
+        let subject = conflicts
+            .first()
+            .map(|s| s.subject.clone())
+            .unwrap_or_default();
+        format!("同じ会議室の予定と重複しています：{}", subject)
+    };
+    match action {
+        Action::Add { after } => match store
+            .add_schedule(after, ConflictScope::Room)
+            .map_err(failed)?
+        {
+            WriteOutcome::Saved(_) => Ok(()),
+            WriteOutcome::Conflict(conflicts) => Err(conflict(conflicts)),
+            WriteOutcome::NotFound => Err("予定を追加できませんでした".to_string()),
+        },
+        Action::Edit { after, .. } => match store
+            .update_schedule(after, ConflictScope::Room)
+            .map_err(failed)?
+        {
+            WriteOutcome::Saved(_) => Ok(()),
+            WriteOutcome::Conflict(conflicts) => Err(conflict(conflicts)),
+            WriteOutcome::NotFound => Err("予定は既に削除されています".to_string()),
+        },
+        Action::Delete { before } => match store.delete_schedule(before.id).map_err(failed)? {
+            true => Ok(()),
+            false => Err("予定は既に削除されています".to_string()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// 画面の状態
+struct App {
+    calendar: Calendar,
+    view: GridView,
+    /// 選択中の日
+    cursor: NaiveDate,
+    /// 選択中の日の予定のうち、選択中のもの
+    selected: usize,
+    mode: Mode,
+    /// 画面下部に表示するメッセージ
+    message: Option<String>,
+    quit: bool,
+}
+
+/// キー入力の受け付け方
+#[derive(Debug, PartialEq, Eq)]
+enum Mode {
+    /// 日付や予定の選択
+    Normal,
+    /// 予定の追加・編集
+    Form(Form),
+    /// 予定の削除の確認
+    ConfirmDelete(u64),
+    /// 移動先の日付の入力
+    Jump(String),
+}
+
+/// 予定の入力欄
+#[derive(Debug, PartialEq, Eq)]
+struct Form {
+    /// 編集する予定のID（None の場合は追加）
+    id: Option<u64>,
+    /// 件名、開始、終了、カレンダーの入力値
+    fields: [String; 4],
+    /// 入力中の欄
+    focus: usize,
+}
+
+impl App {
+    fn new(calendar: Calendar, today: NaiveDate) -> Self {
+        Self {
+            calendar,
+            view: GridView::Month,
+            cursor: today,
+            selected: 0,
+            mode: Mode::Normal,
+            message: None,
+            quit: false,
+        }
+    }
+
+    /// 指定した日に掛かる予定（開始時刻順）
+    fn schedules_on(&self, day: NaiveDate) -> Vec<(String, String, &Schedule)> {
+        let mut schedules: Vec<&Schedule> = self.calendar.schedules.iter().collect();
+        schedules.sort_by_key(|schedule| (schedule.start, schedule.id));
+        list::day_segments(&schedules, day)
+    }
+
+    fn selected_schedule(&self) -> Option<&Schedule> {
+        self.schedules_on(self.cursor)
+            .get(self.selected)
+            .map(|(_, _, schedule)| *schedule)
+    }
+
+    /// 他の予定と時間帯が重なる予定のID
+    fn conflicting_ids(&self) -> HashSet<u64> {
+        let schedules = &self.calendar.schedules;
+        let mut ids = HashSet::new();
+        for (index, schedule) in schedules.iter().enumerate() {
+            for other in &schedules[index + 1..] {
+                if schedule.intersects(other) {
+                    ids.insert(schedule.id);
+                    ids.insert(other.id);
+                }
+            }
+        }
+        ids
+    }
+
+    fn move_to(&mut self, date: Option<NaiveDate>) {
+        // NOTE: 月表示・週表示の枠が日付の範囲に収まらない日には移動しない
+        let date = date.filter(|date| month_grid(*date).is_some() && week_range(*date).is_some());
+        if let Some(date) = date {
+            self.cursor = date;
+            self.selected = 0;
+        }
+    }
+
+    /// キー入力を処理する（カレンダーを変更した場合はその操作を返す）
+    fn handle_key(&mut self, key: KeyEvent, clock: &dyn Clock) -> Option<Action> {
+        self.message = None;
+        match std::mem::replace(&mut self.mode, Mode::Normal) {
+            Mode::Normal => self.handle_normal_key(key, clock),
+            Mode::Form(form) => self.handle_form_key(form, key, clock),
+            Mode::ConfirmDelete(id) => match key.code {
+                KeyCode::Char('y') => self.delete(id),
+                _ => None,
+            },
+            Mode::Jump(input) => {
+                self.handle_jump_key(input, key, clock);
+                None
+            }
+        }
+    }
+
+    fn handle_normal_key(&mut self, key: KeyEvent, clock: &dyn Clock) -> Option<Action> {
+        let cursor = self.cursor;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Left | KeyCode::Char('h') => self.move_to(cursor.pred_opt()),
+            KeyCode::Right | KeyCode::Char('l') => self.move_to(cursor.succ_opt()),
+            KeyCode::Up | KeyCode::Char('k') => self.move_to(cursor.checked_sub_days(Days::new(7))),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.move_to(cursor.checked_add_days(Days::new(7)))
+            }
+            KeyCode::PageUp => self.move_to(match self.view {
+                GridView::Month => cursor.checked_sub_months(Months::new(1)),
+                GridView::Week => cursor.checked_sub_days(Days::new(7)),
+            }),
+            KeyCode::PageDown => self.move_to(match self.view {
+                GridView::Month => cursor.checked_add_months(Months::new(1)),
+                GridView::Week => cursor.checked_add_days(Days::new(7)),
+            }),
+            KeyCode::Tab | KeyCode::BackTab => {
+                let count = self.schedules_on(cursor).len();
+                if count > 0 {
+                    self.selected = if key.code == KeyCode::Tab {
+                        (self.selected + 1) % count
+                    } else {
+                        (self.selected + count - 1) % count
+                    };
+                }
+            }
+            KeyCode::Char('t') => self.move_to(Some(clock.now().date())),
+            KeyCode::Char('v') => {
+                self.view = match self.view {
+                    GridView::Month => GridView::Week,
+                    GridView::Week => GridView::Month,
+                }
+            }
+            KeyCode::Char('g') => self.mode = Mode::Jump(String::new()),
+            KeyCode::Char('a') => {
+                self.mode = Mode::Form(Form {
+                    id: None,
+                    fields: [
+                        String::new(),
+                        format!("{} 19:00", cursor),
+                        "20:00".to_string(),
+                        DEFAULT_CALENDAR.to_string(),
+                    ],
+                    focus: 0,
+                })
+            }
+            KeyCode::Char('e') | KeyCode::Enter => {
+                if let Some(schedule) = self.selected_schedule() {
+                    self.mode = Mode::Form(Form {
+                        id: Some(schedule.id),
+                        fields: [
+                            schedule.subject.clone(),
+                            schedule.start.format("%Y-%m-%d %H:%M").to_string(),
+                            schedule.end.format("%Y-%m-%d %H:%M").to_string(),
+                            schedule.calendar.clone(),
+                        ],
+                        focus: 0,
+                    })
+                }
+            }
+            KeyCode::Char('d') => {
+                if let Some(schedule) = self.selected_schedule() {
+                    self.mode = Mode::ConfirmDelete(schedule.id)
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn handle_form_key(
+        &mut self,
+        mut form: Form,
+        key: KeyEvent,
+        clock: &dyn Clock,
+    ) -> Option<Action> {
+        match key.code {
+            KeyCode::Esc => return None,
+            KeyCode::Tab | KeyCode::Down => form.focus = (form.focus + 1) % form.fields.len(),
+            KeyCode::BackTab | KeyCode::Up => {
+                form.focus = (form.focus + form.fields.len() - 1) % form.fields.len()
+            }
+            KeyCode::Backspace => {
+                form.fields[form.focus].pop();
+            }
+            KeyCode::Char(c) => form.fields[form.focus].push(c),
+            KeyCode::Enter => match self.submit(&form, clock) {
+                Ok(action) => return Some(action),
+                Err(message) => self.message = Some(message),
+            },
+            _ => {}
+        }
+        self.mode = Mode::Form(form);
+        None
+    }
+
+    fn handle_jump_key(&mut self, mut input: String, key: KeyEvent, clock: &dyn Clock) {
+        match key.code {
+            KeyCode::Esc => return,
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Enter => {
+                // NOTE: 予定の追加と同じ形式（明日、next tue など）で日付だけを指定できるようにする
+                match datetime::parse_date_time(&format!("{} 00:00", input), clock) {
+                    Ok(date_time) => self.move_to(Some(date_time.date())),
+                    Err(_) => self.message = Some(format!("日付を解釈できません：{}", input)),
+                }
+                return;
+            }
+            _ => {}
+        }
+        self.mode = Mode::Jump(input);
+    }
+
+    /// 入力欄の内容で予定を追加・更新する
+    fn submit(&mut self, form: &Form, clock: &dyn Clock) -> Result<Action, String> {
+        let [subject, start, end, calendar_name] = &form.fields;
+        if subject.trim().is_empty() {
+            return Err("件名を入力してください".to_string());
+        }
+        let start = datetime::parse_date_time(start, clock)
+            .map_err(|_| format!("開始時刻を解釈できません：{}", start))?;
+        let end = datetime::parse_end(end, start, clock)
+            .map_err(|_| format!("終了時刻を解釈できません：{}", end))?;
+        if end <= start {
+            return Err("終了時刻は開始時刻より後にしてください".to_string());
+        }
+
+        let before = form
+            .id
+            .and_then(|id| self.calendar.schedules.iter().find(|s| s.id == id))
+            .cloned();
+        let schedule = Schedule {
+            id: form.id.unwrap_or_else(|| crate::next_id(&self.calendar)),
+            calendar: match calendar_name.trim() {
+                "" => DEFAULT_CALENDAR.to_string(),
+                name => name.to_string(),
+            },
+            subject: subject.trim().to_string(),
+            start,
+            end,
+            ..before.clone().unwrap_or_default()
+        };
+        // NOTE: 時間帯の重なりは画面上で強調するだけにし、会議室の二重予約だけは保存しない
+        if let Some(conflict) =
+            crate::find_conflicts(&self.calendar, &schedule, ConflictScope::Room).first()
+        {
+            return Err(format!(
+                "同じ会議室の予定と重複しています：{}",
+                conflict.subject
+            ));
+        }
+
+        let action = match before {
+            Some(before) => {
+                let index = self
+                    .calendar
+                    .schedules
+                    .iter()
+                    .position(|s| s.id == before.id)
+                    .unwrap();
+                self.calendar.schedules[index] = schedule.clone();
+                self.message = Some("予定を更新しました。".to_string());
+                Action::Edit {
+                    before,
+                    after: schedule.clone(),
+                }
+            }
+            None => {
+                self.calendar.schedules.push(schedule.clone());
+                self.message = Some("予定を追加しました。".to_string());
+                Action::Add {
+                    after: schedule.clone(),
+                }
+            }
+        };
+        self.move_to(Some(schedule.start.date()));
+        self.selected = self
+            .schedules_on(self.cursor)
+            .iter()
+            .position(|(_, _, s)| s.id == schedule.id)
+            .unwrap_or_default();
+        Ok(action)
+    }
+
+    fn delete(&mut self, id: u64) -> Option<Action> {
+        let index = self.calendar.schedules.iter().position(|s| s.id == id)?;
+        let before = self.calendar.schedules.remove(index);
+        self.selected = self.selected.saturating_sub(1);
+        self.message = Some("予定を削除しました。".to_string());
+        Some(Action::Delete { before })
+    }
+
+    fn draw(&self, frame: &mut Frame, today: NaiveDate) {
+        let [header, grid, detail, status] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(8),
+            Constraint::Length(8),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let conflicts = self.conflicting_ids();
+        let title = match self.view {
+            GridView::Month => self.cursor.format("%Y年%m月").to_string(),
+            GridView::Week => match week_range(self.cursor) {
+                Some((first, last)) => format!("{} 〜 {}", first, last),
+                None => String::new(),
+            },
+        };
+        frame.render_widget(
+            Paragraph::new(title).style(Style::new().add_modifier(Modifier::BOLD)),
+            header,
+        );
+        match self.view {
+            GridView::Month => self.draw_month(frame, grid, today, &conflicts),
+            GridView::Week => self.draw_week(frame, grid, today, &conflicts),
+        }
+        self.draw_detail(frame, detail, &conflicts);
+
+        let help =
+            "←→↑↓:移動 PgUp/PgDn:前後 Tab:予定 a:追加 e:編集 d:削除 g:日付 t:今日 v:月/週 q:終了";
+        let status_line = match &self.message {
+            Some(message) => Line::from(message.as_str()).style(Style::new().fg(Color::Yellow)),
+            None => Line::from(help).style(Style::new().fg(Color::DarkGray)),
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+
+        self.draw_popup(frame);
+    }
+
+    fn draw_month(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        today: NaiveDate,
+        conflicts: &HashSet<u64>,
+    ) {
+        let Some((start, weeks)) = month_grid(self.cursor) else {
+            return;
+        };
+        let month = self.cursor.month();
+
+        let [labels, body] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+        let columns = Layout::horizontal([Constraint::Ratio(1, 7); 7]).split(labels);
+        for (label, column) in WEEKDAY_LABELS.iter().zip(columns.iter()) {
+            frame.render_widget(Paragraph::new(*label).centered(), *column);
+        }
+
+        let rows = Layout::vertical(vec![Constraint::Ratio(1, weeks); weeks as usize]).split(body);
+        for (week, row) in rows.iter().enumerate() {
+            let columns = Layout::horizontal([Constraint::Ratio(1, 7); 7]).split(*row);
+            for (weekday, column) in columns.iter().enumerate() {
+                let day = start + Days::new((week * 7 + weekday) as u64);
+                let in_month = day.month() == month;
+                self.draw_day(frame, *column, day, today, in_month, conflicts, false);
+            }
+        }
+    }
+
+    fn draw_week(&self, frame: &mut Frame, area: Rect, today: NaiveDate, conflicts: &HashSet<u64>) {
+        let Some((start, _)) = week_range(self.cursor) else {
+            return;
+        };
+        let columns = Layout::horizontal([Constraint::Ratio(1, 7); 7]).split(area);
+        for (offset, column) in columns.iter().enumerate() {
+            let day = start + Days::new(offset as u64);
+            self.draw_day(frame, *column, day, today, true, conflicts, true);
+        }
+    }
+
+    /// 1日分の枠を描く（週表示では終了時刻も表示する）
+    #[allow(clippy::too_many_arguments)]
+    fn draw_day(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        day: NaiveDate,
+        today: NaiveDate,
+        in_month: bool,
+        conflicts: &HashSet<u64>,
+        with_end: bool,
+    ) {
+        let label = if with_end {
+            format!(
+                "{} {}",
+                WEEKDAY_LABELS[day.weekday().num_days_from_monday() as usize],
+                day.format("%m/%d")
+            )
+        } else {
+            day.day().to_string()
+        };
+        let mut title_style = Style::new();
+        if day == today {
+            title_style = title_style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+        }
+        if !in_month {
+            title_style = title_style.fg(Color::DarkGray);
+        }
+        let mut block = Block::bordered().title(Span::styled(label, title_style));
+        if day == self.cursor {
+            block = block.border_style(Style::new().fg(Color::Yellow));
+        }
+
+        let lines: Vec<Line> = self
+            .schedules_on(day)
+            .into_iter()
+            .map(|(start, end, schedule)| {
+                let time = if with_end {
+                    format!("{}-{}", start, end)
+                } else {
+                    start
+                };
+                Line::from(format!("{} {}", time, schedule.subject))
+                    .style(schedule_style(schedule, conflicts))
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn draw_detail(&self, frame: &mut Frame, area: Rect, conflicts: &HashSet<u64>) {
+        let items: Vec<ListItem> = self
+            .schedules_on(self.cursor)
+            .into_iter()
+            .map(|(_, _, schedule)| {
+                let mut text = format!(
+                    "[{}] {} - {}  {} ({})",
+                    schedule.id,
+                    schedule.start.format("%Y-%m-%d %H:%M"),
+                    schedule.end.format("%Y-%m-%d %H:%M"),
+                    schedule.subject,
+                    schedule.calendar
+                );
+                if let Some(location) = &schedule.location {
+                    text.push_str(&format!(" @{}", location));
+                }
+                if !schedule.rooms.is_empty() {
+                    text.push_str(&format!(" 会議室:{}", schedule.rooms.join(",")));
+                }
+                if conflicts.contains(&schedule.id) {
+                    text.push_str(" ※重複");
+                }
+                ListItem::new(text).style(schedule_style(schedule, conflicts))
+            })
+            .collect();
+        let title = format!("{} の予定", self.cursor.format("%Y-%m-%d"));
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    fn draw_popup(&self, frame: &mut Frame) {
+        let (title, lines) = match &self.mode {
+            Mode::Normal => return,
+            Mode::Form(form) => {
+                let title = match form.id {
+                    Some(_) => "予定の編集（Enter:保存 Esc:取消 Tab:次の欄）",
+                    None => "予定の追加（Enter:保存 Esc:取消 Tab:次の欄）",
+                };
+                let lines = FIELD_LABELS
+                    .iter()
+                    .zip(form.fields.iter())
+                    .enumerate()
+                    .map(|(index, (label, value))| {
+                        if index == form.focus {
+                            Line::from(format!("{}: {}_", label, value))
+                                .style(Style::new().add_modifier(Modifier::REVERSED))
+                        } else {
+                            Line::from(format!("{}: {}", label, value))
+                        }
+                    })
+                    .collect();
+                (title, lines)
+            }
+            Mode::ConfirmDelete(id) => {
+                let subject = self
+                    .calendar
+                    .schedules
+                    .iter()
+                    .find(|s| s.id == *id)
+                    .map(|s| s.subject.as_str())
+                    .unwrap_or_default();
+                (
+                    "予定の削除",
+                    vec![Line::from(format!(
+                        "「{}」を削除しますか？（y/n）",
+                        subject
+                    ))],
+                )
+            }
+            Mode::Jump(input) => (
+                "日付へ移動（例：2024-01-01、tomorrow、next tue）",
+                vec![Line::from(format!("{}_", input))],
+            ),
+        };
+
+        let height = lines.len() as u16 + 2;
+        let [area] = Layout::vertical([Constraint::Length(height)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::horizontal([Constraint::Percentage(70)])
+            .flex(Flex::Center)
+            .areas(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            area,
+        );
+    }
+}
+
+/// 他の予定と重なる予定は赤で表示する
+fn schedule_style(schedule: &Schedule, conflicts: &HashSet<u64>) -> Style {
+    if conflicts.contains(&schedule.id) {
+        Style::new().fg(Color::Red)
+    } else {
+        Style::new()
+    }
+}
+
+/// 月表示の枠の最初の日（月曜日）と週の数（枠が日付の範囲に収まらない場合は None）
+fn month_grid(date: NaiveDate) -> Option<(NaiveDate, u32)> {
+    let first = date.with_day(1)?;
+    let (start, _) = week_range(first)?;
+    let next = first.checked_add_months(Months::new(1))?;
+    let weeks = ((next - start).num_days() as u32).div_ceil(7);
+    start.checked_add_days(Days::new(u64::from(weeks * 7 - 1)))?;
+    Some((start, weeks))
+}
+
+/// 指定した日を含む週の月曜日と日曜日（日付の範囲に収まらない場合は None）
+fn week_range(date: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+    let offset = u64::from(date.weekday().num_days_from_monday());
+    let first = date.checked_sub_days(Days::new(offset))?;
+    Some((first, first.checked_add_days(Days::new(6))?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datetime::FixedClock;
+    use crate::tests::naive_date_time;
+    use crate::CALENDAR_VERSION;
+    use ratatui::{backend::TestBackend, crossterm::event::KeyModifiers, Terminal};
+
+    fn clock() -> FixedClock {
+        FixedClock(naive_date_time(2024, 1, 3, 12, 0, 0))
+    }
+
+    fn sample_app() -> App {
+        let calendar = Calendar {
+            version: CALENDAR_VERSION,
+            schedules: vec![
+                Schedule::test(
+                    0,
+                    "Rust入門",
+                    naive_date_time(2024, 1, 3, 19, 0, 0),
+                    naive_date_time(2024, 1, 3, 21, 0, 0),
+                ),
+                Schedule::test(
+                    1,
+                    "定例",
+                    naive_date_time(2024, 1, 3, 20, 0, 0),
+                    naive_date_time(2024, 1, 3, 22, 0, 0),
+                ),
+                Schedule::test(
+                    2,
+                    "Rust応用",
+                    naive_date_time(2024, 1, 10, 19, 0, 0),
+                    naive_date_time(2024, 1, 10, 21, 0, 0),
+                ),
+            ],
+            rooms: vec![],
+        };
+        App::new(calendar, clock().now().date())
+    }
+
+    fn press(app: &mut App, codes: &[KeyCode]) -> Vec<Action> {
+        codes
+            .iter()
+            .filter_map(|code| app.handle_key(KeyEvent::new(*code, KeyModifiers::NONE), &clock()))
+            .collect()
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        let codes: Vec<KeyCode> = text.chars().map(KeyCode::Char).collect();
+        press(app, &codes);
+    }
+
+    #[test]
+    fn test_navigation() {
+        let mut app = sample_app();
+
+        press(&mut app, &[KeyCode::Right, KeyCode::Down]);
+        assert_eq!(NaiveDate::from_ymd_opt(2024, 1, 11).unwrap(), app.cursor);
+        press(&mut app, &[KeyCode::PageDown]);
+        assert_eq!(NaiveDate::from_ymd_opt(2024, 2, 11).unwrap(), app.cursor);
+        press(&mut app, &[KeyCode::Char('v'), KeyCode::PageUp]);
+        assert_eq!(NaiveDate::from_ymd_opt(2024, 2, 4).unwrap(), app.cursor);
+        press(&mut app, &[KeyCode::Char('t')]);
+        assert_eq!(NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(), app.cursor);
+
+        // 日付の範囲の端では、枠が収まる日で止まる
+        app.cursor = NaiveDate::from_ymd_opt(262142, 11, 30).unwrap();
+        press(&mut app, &[KeyCode::PageDown, KeyCode::Right]);
+        assert_eq!(NaiveDate::from_ymd_opt(262142, 11, 30).unwrap(), app.cursor);
+
+        // 日付を指定して移動する
+        press(&mut app, &[KeyCode::Char('g')]);
+        type_text(&mut app, "next tue");
+        press(&mut app, &[KeyCode::Enter]);
+        assert_eq!(NaiveDate::from_ymd_opt(2024, 1, 9).unwrap(), app.cursor);
+        assert_eq!(Mode::Normal, app.mode);
+    }
+
+    #[test]
+    fn test_add_schedule() {
+        let mut app = sample_app();
+
+        // 件名がなければ保存しない
+        press(&mut app, &[KeyCode::Char('a'), KeyCode::Enter]);
+        assert_eq!(Some("件名を入力してください".to_string()), app.message);
+        type_text(&mut app, "Rust実践");
+        press(&mut app, &[KeyCode::Tab]);
+        for _ in 0.."2024-01-03 19:00".len() {
+            press(&mut app, &[KeyCode::Backspace]);
+        }
+        type_text(&mut app, "tomorrow 10:00");
+        press(
+            &mut app,
+            &[KeyCode::Tab, KeyCode::Backspace, KeyCode::Backspace],
+        );
+        type_text(&mut app, "30");
+        let actions = press(&mut app, &[KeyCode::Enter]);
+
+        let expected = Schedule {
+            id: 3,
+            subject: "Rust実践".to_string(),
+            start: naive_date_time(2024, 1, 4, 10, 0, 0),
+            end: naive_date_time(2024, 1, 4, 20, 30, 0),
+            ..Default::default()
+        };
+        assert_eq!(
+            vec![Action::Add {
+                after: expected.clone()
+            }],
+            actions
+        );
+        assert_eq!(Some(&expected), app.calendar.schedules.last());
+        assert_eq!(NaiveDate::from_ymd_opt(2024, 1, 4).unwrap(), app.cursor);
+        assert_eq!(Mode::Normal, app.mode);
+    }
+
+    #[test]
+    fn test_edit_and_delete_schedule() {
+        let mut app = sample_app();
+
+        // 2件目の予定を選んで件名を変える
+        press(&mut app, &[KeyCode::Tab, KeyCode::Char('e')]);
+        type_text(&mut app, "（延長）");
+        let actions = press(&mut app, &[KeyCode::Enter]);
+        assert!(matches!(
+            &actions[..],
+            [Action::Edit { before, after }] if before.subject == "定例" && after.subject == "定例（延長）"
+        ));
+
+        // 削除は確認してから行う
+        press(&mut app, &[KeyCode::Char('d'), KeyCode::Char('n')]);
+        assert_eq!(3, app.calendar.schedules.len());
+        let actions = press(&mut app, &[KeyCode::Char('d'), KeyCode::Char('y')]);
+        assert!(matches!(&actions[..], [Action::Delete { before }] if before.id == 1));
+        assert_eq!(
+            vec![0, 2],
+            app.calendar
+                .schedules
+                .iter()
+                .map(|s| s.id)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_conflicting_ids() {
+        let app = sample_app();
+
+        assert_eq!(HashSet::from([0, 1]), app.conflicting_ids());
+    }
+
+    #[test]
+    fn test_draw_month() {
+        let app = sample_app();
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+
+        terminal
+            .draw(|frame| app.draw(frame, clock().now().date()))
+            .unwrap();
+
+        // NOTE: 全角文字の次のセルは空白で埋められるので、1セルを1文字として連結する
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content.iter().map(|cell| cell.symbol()).collect();
+        assert!(text.starts_with("2024年 01月 "));
+        assert!(text.contains("[0] 2024-01-03 19:00 - 2024-01-03 21:00"));
+        // 重なる予定は赤で表示する
+        let position = text.find("[1] 2024-01-03 20:00").unwrap();
+        let index = text[..position].chars().count();
+        assert_eq!(Color::Red, buffer.content[index].fg);
+    }
+
+    #[test]
+    fn test_draw_week_with_form() {
+        let mut app = sample_app();
+        press(&mut app, &[KeyCode::Char('v'), KeyCode::Char('a')]);
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+
+        terminal
+            .draw(|frame| app.draw(frame, clock().now().date()))
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content.iter().map(|cell| cell.symbol()).collect();
+        assert!(text.starts_with("2024-01-01 〜  2024-01-07"));
+        assert!(text.contains("│19:00-21:00 Rus│"));
+        assert!(text.contains(": 2024-01-03 19:00 "));
+    }
+
+    #[test]
+    fn test_apply_keeps_changes_from_other_processes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schedule.json");
+        let mut store = crate::storage::JsonStore::new(&path);
+        let app = sample_app();
+        store.save_calendar(&app.calendar).unwrap();
+        // 画面を開いている間に、別のプロセスが予定を追加する
+        let added_elsewhere = Schedule {
+            subject: "CLIで追加".to_string(),
+            start: naive_date_time(2024, 1, 5, 9, 0, 0),
+            end: naive_date_time(2024, 1, 5, 10, 0, 0),
+            ..Default::default()
+        };
+        crate::storage::JsonStore::new(&path)
+            .add_schedule(added_elsewhere, ConflictScope::All)
+            .unwrap();
+
+        let deleted = apply(
+            &mut store,
+            Action::Delete {
+                before: app.calendar.schedules[0].clone(),
+            },
+        );
+        let added = apply(
+            &mut store,
+            Action::Add {
+                after: Schedule {
+                    id: 3,
+                    subject: "Rust実践".to_string(),
+                    ..app.calendar.schedules[2].clone()
+                },
+            },
+        );
+
+        assert_eq!(Ok(()), deleted);
+        assert_eq!(Ok(()), added);
+        let schedules: Vec<(u64, String)> = store
+            .read_calendar()
+            .unwrap()
+            .schedules
+            .into_iter()
+            .map(|schedule| (schedule.id, schedule.subject))
+            .collect();
+        // NOTE: 画面が割り当てたIDではなく、保存先が割り当てたIDで保存する
+        assert_eq!(
+            vec![
+                (1, "定例".to_string()),
+                (2, "Rust応用".to_string()),
+                (3, "CLIで追加".to_string()),
+                (4, "Rust実践".to_string()),
+            ],
+            schedules
+        );
+        let deleted_again = apply(
+            &mut store,
+            Action::Delete {
+                before: app.calendar.schedules[0].clone(),
+            },
+        );
+        assert_eq!(Err("予定は既に削除されています".to_string()), deleted_again);
+    }
+
+    #[test]
+    fn test_apply_records_history_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schedule.json");
+        let mut store = crate::storage::open(&path).unwrap();
+        let app = sample_app();
+        store.save_calendar(&app.calendar).unwrap();
+
+        let edited = apply(
+            store.as_mut(),
+            Action::Edit {
+                before: app.calendar.schedules[1].clone(),
+                after: Schedule {
+                    subject: "定例（変更）".to_string(),
+                    ..app.calendar.schedules[1].clone()
+                },
+            },
+        );
+
+        assert_eq!(Ok(()), edited);
+        let history = crate::storage::History::for_store(&path);
+        assert_eq!(1, history.changes().unwrap().len());
+        // NOTE: 1回の undo で元に戻り、次の undo で取り消す変更は残っていない
+        assert!(history.undo(store.as_mut()).unwrap().is_some());
+        assert_eq!("定例", store.read_calendar().unwrap().schedules[1].subject);
+        assert!(history.undo(store.as_mut()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,206 @@
+mod common;
+
+use common::TestServer;
+
+/// クライアントから記録したリクエストの本文
+fn fixture(name: &str) -> String {
+    let path = format!(
+        "{}/tests/fixtures/caldav/{}",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    );
+    std::fs::read_to_string(path).unwrap()
+}
+
+const EVENT_PATH: &str = "/dav/calendars/rust-study/5C1A2B3D-7E8F-4A5B-9C0D-1E2F3A4B5C6D.ics";
+
+#[test]
+fn test_discovery() {
+    let server = TestServer::start();
+
+    let options = server.send("OPTIONS", "/dav/", &[], "");
+    assert_eq!(200, options.status);
+    assert!(options.header("dav").unwrap().contains("calendar-access"));
+
+    let principal = server.send(
+        "PROPFIND",
+        "/dav/",
+        &[("Depth", "0")],
+        &fixture("propfind_principal.xml"),
+    );
+    assert_eq!(207, principal.status);
+    assert!(principal
+        .body
+        .contains("<c:calendar-home-set><d:href>/dav/calendars/</d:href></c:calendar-home-set>"));
+
+    let calendars = server.send(
+        "PROPFIND",
+        "/dav/calendars/",
+        &[("Depth", "1")],
+        &fixture("propfind_calendars.xml"),
+    );
+    assert_eq!(207, calendars.status);
+    assert!(calendars
+        .body
+        .contains("<d:href>/dav/calendars/default/</d:href>"));
+    assert!(calendars.body.contains("<c:comp name=\"VEVENT\"/>"));
+}
+
+#[test]
+fn test_put_get_report_delete() {
+    let server = TestServer::start();
+
+    // 作成（If-None-Match: * は新規作成のときだけ成功する）
+    let put = server.send(
+        "PUT",
+        EVENT_PATH,
+        &[
+            ("Content-Type", "text/calendar; charset=utf-8"),
+            ("If-None-Match", "*"),
+        ],
+        &fixture("put_event.ics"),
+    );
+    assert_eq!(201, put.status);
+    let etag = put.header("etag").unwrap().to_string();
+    let again = server.send(
+        "PUT",
+        EVENT_PATH,
+        &[("If-None-Match", "*")],
+        &fixture("put_event.ics"),
+    );
+    assert_eq!(412, again.status);
+
+    // REST API からも同じ予定が見える
+    let (_, schedules) = server.request("GET", "/schedules?calendar=rust-study", None);
+    assert_eq!("Rust勉強会 第3回", schedules[0]["subject"]);
+    assert_eq!("2024-12-03T19:00:00", schedules[0]["start"]);
+    assert_eq!("2024-12-03T20:30:00", schedules[0]["end"]);
+    assert_eq!("オンライン", schedules[0]["location"]);
+
+    // 取得
+    let get = server.send("GET", EVENT_PATH, &[], "");
+    assert_eq!(200, get.status);
+    assert_eq!(Some(etag.as_str()), get.header("etag"));
+    assert!(get
+        .body
+        .contains("UID:5C1A2B3D-7E8F-4A5B-9C0D-1E2F3A4B5C6D\r\n"));
+    assert!(get.body.contains("DTSTART:20241203T190000\r\n"));
+
+    // 一覧（Depth: 1）
+    let events = server.send(
+        "PROPFIND",
+        "/dav/calendars/rust-study/",
+        &[("Depth", "1")],
+        &fixture("propfind_events.xml"),
+    );
+    assert_eq!(207, events.status);
+    assert!(events.body.contains(&format!(
+        "<d:getetag>{}</d:getetag>",
+        etag.replace('"', "&quot;")
+    )));
+
+    // calendar-query：期間内の予定だけを返す
+    let query = server.send(
+        "REPORT",
+        "/dav/calendars/rust-study/",
+        &[("Depth", "1")],
+        &fixture("report_calendar_query.xml"),
+    );
+    assert_eq!(207, query.status);
+    assert!(query.body.contains(EVENT_PATH));
+    let outside = server.send(
+        "REPORT",
+        "/dav/calendars/rust-study/",
+        &[("Depth", "1")],
+        &fixture("report_calendar_query.xml").replace("20241201T000000Z", "20241204T000000Z"),
+    );
+    assert!(!outside.body.contains(EVENT_PATH));
+
+    // calendar-multiget：存在しないリソースは 404 として返す
+    let multiget = server.send(
+        "REPORT",
+        "/dav/calendars/rust-study/",
+        &[("Depth", "1")],
+        &fixture("report_calendar_multiget.xml"),
+    );
+    assert_eq!(207, multiget.status);
+    assert!(multiget.body.contains("SUMMARY:Rust勉強会 第3回"));
+    assert!(multiget.body.contains(
+        "<d:href>/dav/calendars/rust-study/missing.ics</d:href><d:status>HTTP/1.1 404 Not Found</d:status>"
+    ));
+
+    // 更新（古い ETag では更新できない）
+    let updated_body = fixture("put_event.ics").replace(
+        "SUMMARY:Rust勉強会 第3回",
+        "SUMMARY:Rust勉強会 第3回（延長）",
+    );
+    let stale = server.send("PUT", EVENT_PATH, &[("If-Match", "\"0\"")], &updated_body);
+    assert_eq!(412, stale.status);
+    let update = server.send("PUT", EVENT_PATH, &[("If-Match", &etag)], &updated_body);
+    assert_eq!(204, update.status);
+    assert_ne!(Some(etag.as_str()), update.header("etag"));
+
+    // 削除
+    let delete = server.send("DELETE", EVENT_PATH, &[], "");
+    assert_eq!(204, delete.status);
+    let get = server.send("GET", EVENT_PATH, &[], "");
+    assert_eq!(404, get.status);
+}
+
+#[test]
+fn test_put_overlapping_event() {
+    let server = TestServer::start();
+    let put = server.send("PUT", EVENT_PATH, &[], &fixture("put_event.ics"));
+    assert_eq!(201, put.status);
+
+    // REST API と同じく、会議室を予約していない予定どうしは重なっても追加できる
+    let other = fixture("put_event.ics").replace(
+        "UID:5C1A2B3D-7E8F-4A5B-9C0D-1E2F3A4B5C6D",
+        "UID:6D2B3C4E-8F90-4B6C-0D1E-2F3A4B5C6D7E",
+    );
+    let put = server.send(
+        "PUT",
+        "/dav/calendars/rust-study/6D2B3C4E-8F90-4B6C-0D1E-2F3A4B5C6D7E.ics",
+        &[],
+        &other,
+    );
+    assert_eq!(201, put.status);
+    let (_, schedules) = server.request("GET", "/schedules?calendar=rust-study", None);
+    assert_eq!(2, schedules.as_array().unwrap().len());
+}
+
+#[test]
+fn test_calendar_query_in_local_timezone() {
+    let server = TestServer::start_in("Asia/Tokyo");
+    // UTC の 10:00 は日本時間の 19:00 として保存される
+    let event = fixture("put_event.ics")
+        .replace(
+            "DTSTART;TZID=Asia/Tokyo:20241203T190000",
+            "DTSTART:20241203T100000Z",
+        )
+        .replace(
+            "DTEND;TZID=Asia/Tokyo:20241203T203000",
+            "DTEND:20241203T113000Z",
+        );
+    let put = server.send("PUT", EVENT_PATH, &[], &event);
+    assert_eq!(201, put.status);
+    let (_, schedules) = server.request("GET", "/schedules?calendar=rust-study", None);
+    assert_eq!("2024-12-03T19:00:00", schedules[0]["start"]);
+
+    // time-range の UTC の時刻も、同じく日本時間に変換して比較する
+    let query = |start: &str, end: &str| {
+        let body = fixture("report_calendar_query.xml")
+            .replace("20241201T000000Z", start)
+            .replace("20241208T000000Z", end);
+        server
+            .send(
+                "REPORT",
+                "/dav/calendars/rust-study/",
+                &[("Depth", "1")],
+                &body,
+            )
+            .body
+    };
+    assert!(query("20241203T095000Z", "20241203T101000Z").contains(EVENT_PATH));
+    assert!(!query("20241203T185000Z", "20241203T191000Z").contains(EVENT_PATH));
+}
@@ -0,0 +1,135 @@
+use serde_json::Value;
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    process::{Child, Command, Stdio},
+};
+
+/// テスト用に空いているポートでサーバーを起動し、終了時に停止する
+pub struct TestServer {
+    child: Child,
+    address: String,
+    _dir: tempfile::TempDir,
+}
+impl TestServer {
+    pub fn start() -> Self {
+        Self::start_in("UTC")
+    }
+
+    /// 指定したタイムゾーン（TZ 環境変数の値）でサーバーを起動する
+    pub fn start_in(timezone: &str) -> Self {
+        Self::spawn(r#"{"version":2,"schedules":[]}"#, timezone)
+    }
+
+    /// 指定した内容のカレンダーでサーバーを起動する
+    // NOTE: テストのファイルごとに使う関数が異なるため、使わないファイルでの警告を抑える
+    #[allow(dead_code)]
+    pub fn start_with(calendar: &str) -> Self {
+        Self::spawn(calendar, "UTC")
+    }
+
+    // NOTE: 結果が手元のタイムゾーンに左右されないよう、タイムゾーンを指定して起動する
+    fn spawn(calendar: &str, timezone: &str) -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let store = dir.path().join("schedule.json");
+        std::fs::write(&store, calendar).unwrap();
+
+        let mut child = Command::new(env!("CARGO_BIN_EXE_calendar"))
+            .arg("--store")
+            .arg(&store)
+            .args(["serve", "--bind", "127.0.0.1:0"])
+            .env("TZ", timezone)
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        // 起動時に表示される「サーバーを起動しました：http://127.0.0.1:xxxxx」からアドレスを得る
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let address = line.trim().rsplit("http://").next().unwrap().to_string();
+
+        Self {
+            child,
+            address,
+            _dir: dir,
+        }
+    }
+
+    /// JSON のリクエストを送り、ステータスコードと JSON の本文を返す
+    pub fn request(&self, method: &str, path: &str, body: Option<Value>) -> (u16, Value) {
+        let body = body.map(|body| body.to_string()).unwrap_or_default();
+        let response = self.send(method, path, &[("Content-Type", "application/json")], &body);
+        let body = if response.body.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_str(&response.body).unwrap()
+        };
+        (response.status, body)
+    }
+
+    /// 任意のヘッダーと本文でリクエストを送る
+    pub fn send(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> TestResponse {
+        let mut stream = TcpStream::connect(&self.address).unwrap();
+        let mut request = format!("{method} {path} HTTP/1.1\r\nHost: {}\r\n", self.address);
+        for (name, value) in headers {
+            request.push_str(&format!("{name}: {value}\r\n"));
+        }
+        request.push_str(&format!(
+            "Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        ));
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let mut lines = head.lines();
+        let status = lines
+            .next()
+            .unwrap()
+            .split(' ')
+            .nth(1)
+            .unwrap()
+            .parse()
+            .unwrap();
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+        TestResponse {
+            status,
+            headers,
+            body: body.to_string(),
+        }
+    }
+}
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// サーバーからの応答
+pub struct TestResponse {
+    pub status: u16,
+    /// ヘッダー（名前は小文字）
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+impl TestResponse {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
@@ -0,0 +1,194 @@
+mod common;
+
+use common::TestServer;
+use serde_json::json;
+
+#[test]
+fn test_schedule_crud() {
+    let server = TestServer::start();
+
+    // 追加
+    let (status, created) = server.request(
+        "POST",
+        "/schedules",
+        Some(json!({
+            "subject": "Rust入門",
+            "start": "2024-12-01T19:00:00",
+            "end": "2024-12-01T20:30:00",
+            "tags": ["rust"]
+        })),
+    );
+    assert_eq!(201, status);
+    assert_eq!(0, created["id"]);
+    assert_eq!("default", created["calendar"]);
+
+    // 一覧（期間で絞り込み）
+    let (status, schedules) =
+        server.request("GET", "/schedules?from=2024-12-01&to=2024-12-01", None);
+    assert_eq!(200, status);
+    assert_eq!(json!([created]), schedules);
+    let (_, schedules) = server.request("GET", "/schedules?from=2024-12-02", None);
+    assert_eq!(json!([]), schedules);
+
+    // 更新
+    let (status, updated) = server.request(
+        "PATCH",
+        "/schedules/0",
+        Some(json!({ "subject": "Rust応用", "location": "会議室A" })),
+    );
+    assert_eq!(200, status);
+    assert_eq!("Rust応用", updated["subject"]);
+    assert_eq!("会議室A", updated["location"]);
+    assert_eq!("2024-12-01T19:00:00", updated["start"]);
+
+    // Location ヘッダーで作成した予定を指す
+    let created_again = server.send(
+        "POST",
+        "/schedules",
+        &[("Content-Type", "application/json")],
+        &json!({
+            "subject": "Rust実践",
+            "start": "2024-12-02T19:00:00",
+            "end": "2024-12-02T20:00:00"
+        })
+        .to_string(),
+    );
+    assert_eq!(Some("/schedules/1"), created_again.header("location"));
+
+    // 削除
+    let (status, _) = server.request("DELETE", "/schedules/0", None);
+    assert_eq!(204, status);
+    let (status, _) = server.request("DELETE", "/schedules/0", None);
+    assert_eq!(404, status);
+    let (status, _) = server.request("PATCH", "/schedules/0", Some(json!({})));
+    assert_eq!(404, status);
+}
+
+#[test]
+fn test_schedule_conflict() {
+    let server = TestServer::start();
+    let (_, existing) = server.request(
+        "POST",
+        "/schedules",
+        Some(json!({
+            "calendar": "rust-study",
+            "subject": "Rust入門",
+            "start": "2024-12-01T19:00:00",
+            "end": "2024-12-01T20:00:00"
+        })),
+    );
+    let overlapping = json!({
+        "calendar": "team",
+        "subject": "定例",
+        "start": "2024-12-01T19:30:00",
+        "end": "2024-12-01T20:30:00"
+    });
+
+    // すべてのカレンダーと重複を確認する場合は 409 を返す
+    let (status, body) = server.request(
+        "POST",
+        "/schedules?conflict_scope=all",
+        Some(overlapping.clone()),
+    );
+    assert_eq!(409, status);
+    assert_eq!(json!([existing]), body["conflicts"]);
+
+    // 既定では会議室を予約した予定どうしだけを重複として扱うので追加できる
+    let (status, created) = server.request("POST", "/schedules", Some(overlapping));
+    assert_eq!(201, status);
+
+    // 更新で重複する場合も 409 を返す
+    let path = format!("/schedules/{}", created["id"]);
+    let (status, body) = server.request(
+        "PATCH",
+        &format!("{}?conflict_scope=calendar", path),
+        Some(json!({ "calendar": "rust-study" })),
+    );
+    assert_eq!(409, status);
+    assert_eq!(json!([existing]), body["conflicts"]);
+}
+
+#[test]
+fn test_room_conflict() {
+    let server = TestServer::start_with(
+        r#"{"version":3,"schedules":[],"rooms":[
+            {"name":"会議室A","capacity":10},
+            {"name":"会議室B","capacity":4}
+        ]}"#,
+    );
+    let (status, existing) = server.request(
+        "POST",
+        "/schedules",
+        Some(json!({
+            "calendar": "rust-study",
+            "subject": "Rust入門",
+            "start": "2024-12-01T19:00:00",
+            "end": "2024-12-01T20:00:00",
+            "rooms": ["会議室A"]
+        })),
+    );
+    assert_eq!(201, status);
+
+    // 同じ会議室を予約した予定は、カレンダーごとの重複判定でも 409 を返す
+    let (status, body) = server.request(
+        "POST",
+        "/schedules?conflict_scope=calendar",
+        Some(json!({
+            "calendar": "team",
+            "subject": "定例",
+            "start": "2024-12-01T19:30:00",
+            "end": "2024-12-01T20:30:00",
+            "rooms": ["会議室A"]
+        })),
+    );
+    assert_eq!(409, status);
+    assert_eq!(json!([existing]), body["conflicts"]);
+
+    // 別の会議室であれば、既定の会議室ごとの重複判定で追加できる
+    let (status, _) = server.request(
+        "POST",
+        "/schedules",
+        Some(json!({
+            "calendar": "team",
+            "subject": "定例",
+            "start": "2024-12-01T19:30:00",
+            "end": "2024-12-01T20:30:00",
+            "rooms": ["会議室B"]
+        })),
+    );
+    assert_eq!(201, status);
+}
+
+#[test]
+fn test_invalid_request() {
+    let server = TestServer::start();
+
+    let (status, _) = server.request(
+        "POST",
+        "/schedules",
+        Some(json!({
+            "subject": "逆転",
+            "start": "2024-12-01T20:00:00",
+            "end": "2024-12-01T19:00:00"
+        })),
+    );
+    assert_eq!(400, status);
+
+    let (status, body) =
+        server.request("POST", "/schedules", Some(json!({ "subject": "時刻なし" })));
+    assert_eq!(400, status);
+    assert!(body["error"].is_string());
+
+    let (status, body) = server.request(
+        "POST",
+        "/schedules",
+        Some(json!({
+            "subject": "会議室なし",
+            "start": "2024-12-01T19:00:00",
+            "end": "2024-12-01T20:00:00",
+            "rooms": ["会議室Z"]
+        })),
+    );
+    assert_eq!(400, status);
+    assert_eq!("会議室「会議室Z」は登録されていません", body["error"]);
+}